@@ -0,0 +1,153 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    parse_macro_input, parse_quote, visit_mut::VisitMut, Data, DeriveInput, Error, Fields, Ident,
+    LitStr, Result, Type,
+};
+
+/// Derives `ipi::schema::HasSchema` from the definition of the type.
+///
+/// The fields are described in the declaration order. The references to the type itself are
+/// described as `Self`, and a field may be described by a given name with
+/// `#[schema(name = "...")]` to break the cycles of mutually recursive types,
+/// or left out of the schema with `#[schema(skip)]` if it holds no data.
+#[proc_macro_derive(HasSchema, attributes(schema))]
+pub fn derive_has_schema(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(mut input: DeriveInput) -> Result<TokenStream> {
+    let ident = &input.ident;
+    let params: Vec<_> = input
+        .generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .collect();
+
+    let name = ident.to_string();
+    let name = if params.is_empty() {
+        quote! { ::std::string::String::from(#name) }
+    } else {
+        quote! {
+            ::std::format!(
+                "{}<{}>",
+                #name,
+                [ #( <#params as ::ipi::schema::HasSchema>::schema() ),* ].join(","),
+            )
+        }
+    };
+
+    let body = match &input.data {
+        Data::Struct(data) => describe_fields(ident, &quote! { &name }, &data.fields)?
+            .unwrap_or_else(|| quote! { name }),
+        Data::Enum(data) => {
+            let variants = data
+                .variants
+                .iter()
+                .map(|variant| {
+                    let variant_name = variant.ident.to_string();
+                    Ok(
+                        describe_fields(ident, &quote! { #variant_name }, &variant.fields)?
+                            .unwrap_or_else(
+                                || quote! { ::std::string::String::from(#variant_name) },
+                            ),
+                    )
+                })
+                .collect::<Result<Vec<_>>>()?;
+            quote! { ::ipi::schema::describe_enum(&name, &[ #( #variants ),* ]) }
+        }
+        Data::Union(_) => return Err(Error::new_spanned(&input.ident, "unions have no schemas")),
+    };
+
+    let where_clause = input.generics.make_where_clause();
+    for param in &params {
+        where_clause
+            .predicates
+            .push(parse_quote! { #param: ::ipi::schema::HasSchema });
+    }
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::ipi::schema::HasSchema for #ident #ty_generics #where_clause {
+            fn schema() -> ::std::string::String {
+                let name = #name;
+                #body
+            }
+        }
+    })
+}
+
+/// Describes the fields, or returns `None` if there are no fields.
+fn describe_fields(ty: &Ident, name: &TokenStream, fields: &Fields) -> Result<Option<TokenStream>> {
+    let mut names = Vec::new();
+    let mut schemas = Vec::new();
+    for field in fields {
+        if let Some(schema) = describe_field(ty, &field.attrs, &field.ty)? {
+            names.extend(field.ident.as_ref().map(Ident::to_string));
+            schemas.push(schema);
+        }
+    }
+
+    Ok(match fields {
+        Fields::Named(_) => Some(quote! {
+            ::ipi::schema::describe_struct(#name, &[ #( (#names, #schemas) ),* ])
+        }),
+        Fields::Unnamed(_) => Some(quote! {
+            ::ipi::schema::describe_tuple(#name, &[ #( #schemas ),* ])
+        }),
+        Fields::Unit => None,
+    })
+}
+
+/// Describes the field, or returns `None` if the field is skipped.
+fn describe_field(
+    ty: &Ident,
+    attrs: &[syn::Attribute],
+    field: &Type,
+) -> Result<Option<TokenStream>> {
+    let mut name = None;
+    let mut skip = false;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("schema")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                name = Some(meta.value()?.parse::<LitStr>()?);
+                Ok(())
+            } else if meta.path.is_ident("skip") {
+                skip = true;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported schema attribute"))
+            }
+        })?;
+    }
+    if skip {
+        return Ok(None);
+    }
+    if let Some(name) = name {
+        return Ok(Some(quote! { ::std::string::String::from(#name) }));
+    }
+
+    let mut field = field.clone();
+    ReplaceRecursive(ty).visit_type_mut(&mut field);
+    Ok(Some(
+        quote! { <#field as ::ipi::schema::HasSchema>::schema() },
+    ))
+}
+
+/// Replaces the references to the type itself with `Recursive`.
+struct ReplaceRecursive<'a>(&'a Ident);
+
+impl VisitMut for ReplaceRecursive<'_> {
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        if let Type::Path(path) = ty {
+            if path.qself.is_none() && (path.path.is_ident(self.0) || path.path.is_ident("Self")) {
+                *ty = parse_quote! { ::ipi::schema::Recursive };
+                return;
+            }
+        }
+        syn::visit_mut::visit_type_mut(self, ty);
+    }
+}
@@ -3,12 +3,25 @@ use bytecheck::CheckBytes;
 use rkyv::{Archive, Deserialize, Serialize};
 
 use crate::{
+    canonical::Canonical,
     metadata::Metadata,
-    signature::{Keypair, PublicKey, Signature, SignatureSerializer},
+    schema::HasSchema,
+    signature::{Keypair, PublicKey, Signature, SignatureContext},
 };
 
 #[derive(
-    Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Archive, Serialize, Deserialize,
+    Copy,
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Archive,
+    Serialize,
+    Deserialize,
+    HasSchema,
 )]
 #[archive(bound(archive = "
     <GuaranteeSigned<T> as Archive>::Archived: ::core::fmt::Debug + PartialEq,
@@ -30,19 +43,24 @@ impl<T> ::core::ops::Deref for GuarantorSigned<T> {
 
 impl<T> Signer<GuaranteeSigned<T>> for GuarantorSigned<T>
 where
-    T: ::core::fmt::Debug + PartialEq + Archive + Serialize<SignatureSerializer>,
+    T: ::core::fmt::Debug + PartialEq + Archive + Canonical,
     <T as Archive>::Archived: ::core::fmt::Debug + PartialEq,
 {
-    fn sign(account: &Account, data: GuaranteeSigned<T>) -> Result<Self>
-    where
-        Self: Sized,
-    {
+    fn sign(account: &Account, data: GuaranteeSigned<T>) -> Result<Self> {
+        Self::sign_with(&SignatureContext::default(), account, data)
+    }
+
+    fn sign_with(
+        context: &SignatureContext,
+        account: &Account,
+        data: GuaranteeSigned<T>,
+    ) -> Result<Self> {
         if account.account_ref() != data.guarantor {
             bail!("guarantor mismatching");
         }
 
         Ok(GuarantorSigned {
-            guarantor: account.sign(&data)?,
+            guarantor: account.sign_with(context, &data),
             data,
         })
     }
@@ -50,21 +68,36 @@ where
 
 impl<T> Verifier for GuarantorSigned<T>
 where
-    T: Archive + Serialize<SignatureSerializer> + ::core::fmt::Debug + PartialEq,
+    T: Archive + Canonical + HasSchema + ::core::fmt::Debug + PartialEq,
     <T as Archive>::Archived: ::core::fmt::Debug + PartialEq,
 {
     fn verify(&self, guarantor: Option<AccountRef>) -> Result<()> {
+        self.verify_with(&SignatureContext::default(), guarantor)
+    }
+
+    fn verify_with(&self, context: &SignatureContext, guarantor: Option<AccountRef>) -> Result<()> {
         if self.guarantor.account != self.data.data.guarantor {
             bail!("guarantor mismatching");
         }
 
-        self.guarantor.verify(&self.data)?;
-        self.data.verify(guarantor)
+        self.guarantor.verify_with(context, &self.data)?;
+        self.data.verify_with(context, guarantor)
     }
 }
 
 #[derive(
-    Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Archive, Serialize, Deserialize,
+    Copy,
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Archive,
+    Serialize,
+    Deserialize,
+    HasSchema,
 )]
 #[archive(bound(archive = "
     <Metadata<T> as Archive>::Archived: ::core::fmt::Debug + PartialEq,
@@ -86,15 +119,16 @@ impl<T> ::core::ops::Deref for GuaranteeSigned<T> {
 
 impl<T> Signer<Metadata<T>> for GuaranteeSigned<T>
 where
-    T: Archive + Serialize<SignatureSerializer>,
+    T: Archive + Canonical,
     <T as Archive>::Archived: ::core::fmt::Debug + PartialEq,
 {
-    fn sign(account: &Account, data: Metadata<T>) -> Result<Self>
-    where
-        Self: Sized,
-    {
+    fn sign(account: &Account, data: Metadata<T>) -> Result<Self> {
+        Self::sign_with(&SignatureContext::default(), account, data)
+    }
+
+    fn sign_with(context: &SignatureContext, account: &Account, data: Metadata<T>) -> Result<Self> {
         Ok(Self {
-            guarantee: account.sign(&data)?,
+            guarantee: account.sign_with(context, &data),
             data,
         })
     }
@@ -102,17 +136,22 @@ where
 
 impl<T> Verifier for GuaranteeSigned<T>
 where
-    T: Archive + Serialize<SignatureSerializer>,
+    T: Archive + Canonical + HasSchema,
     <T as Archive>::Archived: ::core::fmt::Debug + PartialEq,
 {
     fn verify(&self, guarantor: Option<AccountRef>) -> Result<()> {
+        self.verify_with(&SignatureContext::default(), guarantor)
+    }
+
+    fn verify_with(&self, context: &SignatureContext, guarantor: Option<AccountRef>) -> Result<()> {
         if let Some(guarantor) = guarantor {
             if self.data.guarantor != guarantor {
                 bail!("guarantor mismatching");
             }
         }
+        self.data.schema.ensure()?;
 
-        self.guarantee.verify(&self.data)
+        self.guarantee.verify_with(context, &self.data)
     }
 }
 
@@ -150,15 +189,34 @@ where
 
 pub trait Signer<T>
 where
-    T: Serialize<SignatureSerializer>,
+    T: Canonical,
 {
     fn sign(account: &Account, data: T) -> Result<Self>
     where
         Self: Sized;
+
+    /// Signs the data, reusing the buffers of the context.
+    ///
+    /// Falls back to `sign` unless overridden.
+    fn sign_with(context: &SignatureContext, account: &Account, data: T) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        let _ = context;
+        Self::sign(account, data)
+    }
 }
 
 pub trait Verifier {
     fn verify(&self, guarantor: Option<AccountRef>) -> Result<()>;
+
+    /// Verifies the signatures, reusing the buffers of the context.
+    ///
+    /// Falls back to `verify` unless overridden.
+    fn verify_with(&self, context: &SignatureContext, guarantor: Option<AccountRef>) -> Result<()> {
+        let _ = context;
+        self.verify(guarantor)
+    }
 }
 
 impl<T> Verifier for &T
@@ -168,6 +226,10 @@ where
     fn verify(&self, guarantor: Option<AccountRef>) -> Result<()> {
         (**self).verify(guarantor)
     }
+
+    fn verify_with(&self, context: &SignatureContext, guarantor: Option<AccountRef>) -> Result<()> {
+        (**self).verify_with(context, guarantor)
+    }
 }
 
 impl<T> Verifier for Box<T>
@@ -177,6 +239,10 @@ where
     fn verify(&self, guarantor: Option<AccountRef>) -> Result<()> {
         (**self).verify(guarantor)
     }
+
+    fn verify_with(&self, context: &SignatureContext, guarantor: Option<AccountRef>) -> Result<()> {
+        (**self).verify_with(context, guarantor)
+    }
 }
 
 impl<T> Verifier for ::core::pin::Pin<T>
@@ -187,10 +253,25 @@ where
     fn verify(&self, guarantor: Option<AccountRef>) -> Result<()> {
         (**self).verify(guarantor)
     }
+
+    fn verify_with(&self, context: &SignatureContext, guarantor: Option<AccountRef>) -> Result<()> {
+        (**self).verify_with(context, guarantor)
+    }
 }
 
 #[derive(
-    Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Archive, Serialize, Deserialize,
+    Copy,
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Archive,
+    Serialize,
+    Deserialize,
+    HasSchema,
 )]
 #[archive(compare(PartialEq, PartialOrd))]
 #[archive_attr(derive(CheckBytes, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash))]
@@ -200,15 +281,14 @@ pub struct Identity {
 }
 
 impl Identity {
-    fn verify<T>(&self, data: &T) -> Result<()>
+    fn verify_with<T>(&self, context: &SignatureContext, data: &T) -> Result<()>
     where
-        T: Serialize<SignatureSerializer>,
+        T: Canonical,
     {
-        let data = ::rkyv::to_bytes::<_, 64>(data)?;
-        self.verify_archived(&data)
+        context.encode(data, |data| self.verify_bytes(data))
     }
 
-    fn verify_archived(&self, data: &[u8]) -> Result<()> {
+    pub fn verify_bytes(&self, data: &[u8]) -> Result<()> {
         use ed25519_dalek::Verifier;
 
         self.account.public_key.verify(data, &self.signature)?;
@@ -217,7 +297,18 @@ impl Identity {
 }
 
 #[derive(
-    Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Archive, Serialize, Deserialize,
+    Copy,
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Archive,
+    Serialize,
+    Deserialize,
+    HasSchema,
 )]
 #[archive(compare(PartialEq, PartialOrd))]
 #[archive_attr(derive(CheckBytes, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash))]
@@ -243,7 +334,7 @@ impl ::core::str::FromStr for AccountRef {
     }
 }
 
-#[derive(Debug, Archive, Serialize, Deserialize)]
+#[derive(Debug, Archive, Serialize, Deserialize, HasSchema)]
 #[archive(compare(PartialEq, PartialOrd))]
 #[archive_attr(derive(CheckBytes, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash))]
 pub struct Account {
@@ -281,17 +372,21 @@ impl Account {
         }
     }
 
-    pub(crate) fn sign<T>(&self, data: &T) -> Result<Identity>
+    pub(crate) fn sign_with<T>(&self, context: &SignatureContext, data: &T) -> Identity
     where
-        T: Serialize<SignatureSerializer>,
+        T: Canonical,
     {
+        context.encode(data, |data| self.sign_bytes(data))
+    }
+
+    pub fn sign_bytes(&self, data: &[u8]) -> Identity {
         use ed25519_dalek::Signer;
 
-        Ok(Identity {
+        Identity {
             account: AccountRef {
                 public_key: PublicKey(self.keypair.public),
             },
-            signature: Signature(self.keypair.sign(&::rkyv::to_bytes(data)?)),
-        })
+            signature: Signature(self.keypair.sign(data)),
+        }
     }
 }
@@ -0,0 +1,713 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    marker::PhantomData,
+    rc::Rc,
+    sync::Arc,
+};
+
+use crate::{
+    account::{AccountRef, GuaranteeSigned, GuarantorSigned, Identity},
+    credit::{CreditRating, CreditRatingPayload},
+    detached::Detached,
+    metadata::Metadata,
+    schema::SchemaHash,
+    signature::{PublicKey, Signature},
+    value::{
+        array::{
+            chunked::ChunkedArray,
+            sparse::{CooArray, CsrArray},
+            Array, ArrayRaw, Order,
+        },
+        chrono::{DateTime, Duration, NaiveDateTime},
+        compressed::{CompressedArray, Compression, Encoding},
+        dyn_array::DynArray,
+        expr::{CompareOp, Expr},
+        hash::Hash,
+        nonce::Nonce,
+        primitives::U64,
+        schema::{Constraint, FieldSchema, RecordSchema},
+        series::TimeSeries,
+        text::{LanguageTag, Text, TextHash},
+        unit_interval::UnitInterval,
+        uuid::Uuid,
+        Field, FieldType, Value, ValueType,
+    },
+};
+
+/// The byte representation that signatures are taken over.
+///
+/// Unlike the rkyv archive, the canonical form does not depend on rkyv
+/// feature flags, the target endianness or the compiler's field layout:
+///
+/// - integers and floats are written in big-endian, `usize`/`isize` as 64 bits
+/// - sequences and strings are prefixed with their length as `u64`
+/// - `Option`s are prefixed with a `u8` tag (0: `None`, 1: `Some`)
+/// - struct fields and tuple elements are written in declaration order
+/// - enums are prefixed with their variant name, as a string
+/// - unordered collections are written in the order of their encoded entries
+pub trait Canonical {
+    fn write_canonical(&self, buf: &mut Vec<u8>);
+
+    fn to_canonical_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.write_canonical(&mut buf);
+        buf
+    }
+}
+
+pub fn write_len(len: usize, buf: &mut Vec<u8>) {
+    (len as u64).write_canonical(buf)
+}
+
+pub fn write_variant(name: &str, buf: &mut Vec<u8>) {
+    name.write_canonical(buf)
+}
+
+fn write_unordered<'a, T, I>(iter: I, len: usize, buf: &mut Vec<u8>)
+where
+    T: Canonical + ?Sized + 'a,
+    I: Iterator<Item = &'a T>,
+{
+    let mut entries: Vec<_> = iter.map(Canonical::to_canonical_bytes).collect();
+    entries.sort();
+
+    write_len(len, buf);
+    for entry in entries {
+        buf.extend_from_slice(&entry);
+    }
+}
+
+/// Implements `Canonical` by writing the listed fields, in the given order.
+///
+/// Every field should be listed, so a field left out of the signed bytes fails to compile:
+///
+/// ```
+/// pub struct MyData {
+///     pub msg: String,
+///     pub num: u64,
+/// }
+///
+/// ::ipi::impl_canonical!(MyData { msg, num });
+/// ```
+///
+/// ```compile_fail
+/// pub struct MyData {
+///     pub msg: String,
+///     pub num: u64,
+///     pub added: bool,
+/// }
+///
+/// ::ipi::impl_canonical!(MyData { msg, num });
+/// ```
+#[macro_export]
+macro_rules! impl_canonical {
+    (
+        enum $name:ident {
+            $(
+                $variant:ident
+                $( ( $( $tuple:ident ),* $(,)? ) )?
+                $( { $( $named:ident ),* $(,)? } )?
+            ),* $(,)?
+        }
+    ) => {
+        impl $crate::canonical::Canonical for $name {
+            fn write_canonical(&self, buf: &mut ::std::vec::Vec<u8>) {
+                match self {
+                    $(
+                        Self::$variant $( ( $( $tuple ),* ) )? $( { $( $named ),* } )? => {
+                            $crate::canonical::write_variant(stringify!($variant), buf);
+                            $( $( $crate::canonical::Canonical::write_canonical($tuple, buf); )* )?
+                            $( $( $crate::canonical::Canonical::write_canonical($named, buf); )* )?
+                        }
+                    )*
+                }
+            }
+        }
+    };
+    ( $name:ident { $( $field:ident ),* $(,)? } ) => {
+        impl $crate::canonical::Canonical for $name {
+            fn write_canonical(&self, buf: &mut ::std::vec::Vec<u8>) {
+                let Self { $( $field ),* } = self;
+                $( $crate::canonical::Canonical::write_canonical($field, buf); )*
+            }
+        }
+    };
+    ( $name:ident ( $( $field:ident ),* $(,)? ) ) => {
+        impl $crate::canonical::Canonical for $name {
+            fn write_canonical(&self, buf: &mut ::std::vec::Vec<u8>) {
+                let Self( $( $field ),* ) = self;
+                $( $crate::canonical::Canonical::write_canonical($field, buf); )*
+            }
+        }
+    };
+}
+
+macro_rules! impl_for_numbers {
+    ( $( $ty:ty ,)* ) => {
+        $(
+            impl Canonical for $ty {
+                fn write_canonical(&self, buf: &mut Vec<u8>) {
+                    buf.extend_from_slice(&self.to_be_bytes());
+                }
+            }
+        )*
+    };
+}
+
+impl_for_numbers!(i8, i16, i32, i64, i128, u8, u16, u32, u64, u128, f32, f64,);
+
+impl Canonical for isize {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        (*self as i64).write_canonical(buf)
+    }
+}
+
+impl Canonical for usize {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        (*self as u64).write_canonical(buf)
+    }
+}
+
+impl Canonical for () {
+    fn write_canonical(&self, _buf: &mut Vec<u8>) {}
+}
+
+impl Canonical for bool {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        (*self as u8).write_canonical(buf)
+    }
+}
+
+impl Canonical for char {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        (*self as u32).write_canonical(buf)
+    }
+}
+
+impl Canonical for str {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        write_len(self.len(), buf);
+        buf.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl Canonical for String {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        self.as_str().write_canonical(buf)
+    }
+}
+
+impl<T: ?Sized> Canonical for PhantomData<T> {
+    fn write_canonical(&self, _buf: &mut Vec<u8>) {}
+}
+
+impl Canonical for ::std::net::SocketAddr {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        match self {
+            Self::V4(addr) => {
+                write_variant("V4", buf);
+                addr.write_canonical(buf);
+            }
+            Self::V6(addr) => {
+                write_variant("V6", buf);
+                addr.write_canonical(buf);
+            }
+        }
+    }
+}
+
+impl Canonical for ::std::net::SocketAddrV4 {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        self.ip().octets().write_canonical(buf);
+        self.port().write_canonical(buf);
+    }
+}
+
+impl Canonical for ::std::net::SocketAddrV6 {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        self.ip().octets().write_canonical(buf);
+        self.port().write_canonical(buf);
+        self.flowinfo().write_canonical(buf);
+        self.scope_id().write_canonical(buf);
+    }
+}
+
+macro_rules! impl_for_tuples {
+    ( $( $ty:ident ,)* ) => {
+        impl< $( $ty: Canonical ,)* > Canonical for ( $( $ty ,)* ) {
+            #[allow(non_snake_case)]
+            fn write_canonical(&self, buf: &mut Vec<u8>) {
+                let ( $( $ty ,)* ) = self;
+                $( $ty.write_canonical(buf); )*
+            }
+        }
+    };
+}
+
+impl_for_tuples!(T1,);
+impl_for_tuples!(T1, T2,);
+impl_for_tuples!(T1, T2, T3,);
+impl_for_tuples!(T1, T2, T3, T4,);
+impl_for_tuples!(T1, T2, T3, T4, T5,);
+impl_for_tuples!(T1, T2, T3, T4, T5, T6,);
+impl_for_tuples!(T1, T2, T3, T4, T5, T6, T7,);
+impl_for_tuples!(T1, T2, T3, T4, T5, T6, T7, T8,);
+impl_for_tuples!(T1, T2, T3, T4, T5, T6, T7, T8, T9,);
+impl_for_tuples!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10,);
+impl_for_tuples!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11,);
+impl_for_tuples!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12,);
+
+impl<T: Canonical, const N: usize> Canonical for [T; N] {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        for item in self {
+            item.write_canonical(buf);
+        }
+    }
+}
+
+impl<T: Canonical> Canonical for [T] {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        write_len(self.len(), buf);
+        for item in self {
+            item.write_canonical(buf);
+        }
+    }
+}
+
+impl<T: Canonical> Canonical for Vec<T> {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        self.as_slice().write_canonical(buf)
+    }
+}
+
+impl<T: Canonical> Canonical for VecDeque<T> {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        write_len(self.len(), buf);
+        for item in self {
+            item.write_canonical(buf);
+        }
+    }
+}
+
+impl<K: Canonical, V: Canonical> Canonical for BTreeMap<K, V> {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        write_len(self.len(), buf);
+        for (key, value) in self {
+            key.write_canonical(buf);
+            value.write_canonical(buf);
+        }
+    }
+}
+
+impl<T: Canonical> Canonical for BTreeSet<T> {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        write_len(self.len(), buf);
+        for item in self {
+            item.write_canonical(buf);
+        }
+    }
+}
+
+impl<K: Canonical, V: Canonical, S> Canonical for HashMap<K, V, S> {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        let entries: Vec<_> = self.iter().collect();
+        write_unordered(entries.iter(), self.len(), buf)
+    }
+}
+
+impl<T: Canonical, S> Canonical for HashSet<T, S> {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        write_unordered(self.iter(), self.len(), buf)
+    }
+}
+
+impl<T: Canonical> Canonical for Option<T> {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        match self {
+            Some(value) => {
+                1u8.write_canonical(buf);
+                value.write_canonical(buf);
+            }
+            None => 0u8.write_canonical(buf),
+        }
+    }
+}
+
+impl<T: Canonical + ?Sized> Canonical for &T {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        (**self).write_canonical(buf)
+    }
+}
+
+impl<T: Canonical + ?Sized> Canonical for Box<T> {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        (**self).write_canonical(buf)
+    }
+}
+
+impl<T: Canonical + ?Sized> Canonical for Rc<T> {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        (**self).write_canonical(buf)
+    }
+}
+
+impl<T: Canonical + ?Sized> Canonical for Arc<T> {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        (**self).write_canonical(buf)
+    }
+}
+
+impl Canonical for Signature {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        self.0.to_bytes().write_canonical(buf)
+    }
+}
+
+impl Canonical for PublicKey {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        self.0.to_bytes().write_canonical(buf)
+    }
+}
+
+impl_canonical!(Identity { account, signature });
+impl_canonical!(AccountRef { public_key });
+impl_canonical!(CreditRating(payload));
+impl_canonical!(CreditRatingPayload { value });
+impl_canonical!(Detached { hash, len });
+
+impl<T: Canonical> Canonical for GuarantorSigned<T> {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        let GuarantorSigned { guarantor, data } = self;
+        guarantor.write_canonical(buf);
+        data.write_canonical(buf);
+    }
+}
+
+impl<T: Canonical> Canonical for GuaranteeSigned<T> {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        let GuaranteeSigned { guarantee, data } = self;
+        guarantee.write_canonical(buf);
+        data.write_canonical(buf);
+    }
+}
+
+impl<T: Canonical> Canonical for Metadata<T> {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        let Metadata {
+            nonce,
+            created_date,
+            expiration_date,
+            guarantor,
+            schema,
+            data,
+        } = self;
+        nonce.write_canonical(buf);
+        created_date.write_canonical(buf);
+        expiration_date.write_canonical(buf);
+        guarantor.write_canonical(buf);
+        schema.write_canonical(buf);
+        data.write_canonical(buf);
+    }
+}
+
+impl<T> Canonical for SchemaHash<T> {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        (**self).write_canonical(buf)
+    }
+}
+
+impl<A: Canonical, D: ::ndarray::Dimension> Canonical for Array<A, D> {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        // the elements are written in the logical order, regardless of the memory layout
+        self.shape().write_canonical(buf);
+        for item in self.iter() {
+            item.write_canonical(buf);
+        }
+    }
+}
+
+impl<A: Canonical, D: Canonical> Canonical for ArrayRaw<A, D> {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        let ArrayRaw { data, dim, order } = self;
+        data.write_canonical(buf);
+        dim.write_canonical(buf);
+        order.write_canonical(buf);
+    }
+}
+
+impl<A> Canonical for ChunkedArray<A> {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        let ChunkedArray {
+            shape,
+            chunk_shape,
+            encoding,
+            chunks,
+            _element: _,
+        } = self;
+        shape.write_canonical(buf);
+        chunk_shape.write_canonical(buf);
+        encoding.write_canonical(buf);
+        chunks.write_canonical(buf);
+    }
+}
+
+impl<A: Canonical> Canonical for CooArray<A> {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        let CooArray {
+            shape,
+            indices,
+            values,
+        } = self;
+        shape.write_canonical(buf);
+        indices.write_canonical(buf);
+        values.write_canonical(buf);
+    }
+}
+
+impl<A: Canonical> Canonical for CsrArray<A> {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        let CsrArray {
+            shape,
+            indptr,
+            indices,
+            values,
+        } = self;
+        shape.write_canonical(buf);
+        indptr.write_canonical(buf);
+        indices.write_canonical(buf);
+        values.write_canonical(buf);
+    }
+}
+
+impl<A, D> Canonical for CompressedArray<A, D> {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        let CompressedArray {
+            data,
+            shape,
+            encoding,
+            _element: _,
+        } = self;
+        data.write_canonical(buf);
+        shape.write_canonical(buf);
+        encoding.write_canonical(buf);
+    }
+}
+
+impl Canonical for DateTime {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        NaiveDateTime(self.naive_utc()).write_canonical(buf)
+    }
+}
+
+impl Canonical for NaiveDateTime {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        self.timestamp().write_canonical(buf);
+        self.timestamp_subsec_nanos().write_canonical(buf);
+    }
+}
+
+impl Canonical for Duration {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        let (secs, nanos) = self.as_secs_nanos();
+        secs.write_canonical(buf);
+        nanos.write_canonical(buf);
+    }
+}
+
+impl Canonical for Hash {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.0)
+    }
+}
+
+impl_canonical!(Nonce(uuid));
+impl_canonical!(U64(value));
+
+impl Canonical for LanguageTag {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        self.as_str().write_canonical(buf)
+    }
+}
+
+impl_canonical!(Text { msg, lang });
+impl_canonical!(TextHash { msg, lang });
+impl_canonical!(TimeSeries { len, data });
+
+impl Canonical for UnitInterval {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        self.to_bits().write_canonical(buf)
+    }
+}
+
+impl Canonical for Uuid {
+    fn write_canonical(&self, buf: &mut Vec<u8>) {
+        self.as_u128().write_canonical(buf)
+    }
+}
+
+impl_canonical!(
+    enum Value {
+        None,
+        Dyn,
+        Bool(value),
+        I8(value),
+        I16(value),
+        I32(value),
+        I64(value),
+        U8(value),
+        U16(value),
+        U32(value),
+        U64(value),
+        F32(value),
+        F64(value),
+        Bytes(value),
+        String(value),
+        Text(value),
+        I128(value),
+        U128(value),
+        DateTime(value),
+        Uuid(value),
+        Hash(value),
+        UnitInterval(value),
+        Array(value),
+        List(value),
+        Map(value),
+        Record(value),
+        Tensor(value),
+        TimeSeries(value),
+    }
+);
+
+impl_canonical!(
+    enum ValueType {
+        None,
+        Dyn,
+        Bool,
+        I8,
+        I16,
+        I32,
+        I64,
+        U8,
+        U16,
+        U32,
+        U64,
+        F32,
+        F64,
+        Bytes,
+        String,
+        Text,
+        I128,
+        U128,
+        DateTime,
+        Uuid,
+        Hash,
+        UnitInterval,
+        Array,
+        List(ty),
+        Map(ty),
+        Record(fields),
+        Tensor(ty),
+        TimeSeries,
+    }
+);
+
+impl_canonical!(
+    enum DynArray {
+        Bool(value),
+        I8(value),
+        I16(value),
+        I32(value),
+        I64(value),
+        U8(value),
+        U16(value),
+        U32(value),
+        U64(value),
+        F32(value),
+        F64(value),
+        I128(value),
+        U128(value),
+    }
+);
+
+impl_canonical!(Field { name, value });
+impl_canonical!(FieldType { name, ty });
+
+impl_canonical!(RecordSchema { fields });
+impl_canonical!(FieldSchema {
+    name,
+    ty,
+    optional,
+    default,
+    constraints,
+});
+impl_canonical!(
+    enum Constraint {
+        Range { min, max },
+        Length { min, max },
+        Regex(pattern),
+        Language(range),
+        Items(constraints),
+        Record(schema),
+    }
+);
+
+impl_canonical!(
+    enum Expr {
+        Literal(value),
+        Field(path),
+        Not(expr),
+        And(exprs),
+        Or(exprs),
+        Compare { op, lhs, rhs },
+        In { value, list },
+        StartsWith { value, prefix },
+        Between { value, min, max },
+    }
+);
+impl_canonical!(
+    enum CompareOp {
+        Eq,
+        Ne,
+        Lt,
+        Le,
+        Gt,
+        Ge,
+    }
+);
+impl_canonical!(Encoding {
+    compression,
+    shuffle,
+});
+impl_canonical!(
+    enum Compression {
+        None,
+        Zstd,
+        Lz4,
+    }
+);
+impl_canonical!(
+    enum Order {
+        RowMajor,
+        ColumnMajor,
+    }
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonical_bytes() {
+        assert_eq!(0x0102u16.to_canonical_bytes(), [1, 2]);
+        assert_eq!(1usize.to_canonical_bytes(), [0, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(
+            Some("a").to_canonical_bytes(),
+            [1, 0, 0, 0, 0, 0, 0, 0, 1, b'a'],
+        );
+
+        // unordered collections do not depend on the insertion order
+        let a: HashSet<_> = (0..64u32).collect();
+        let b: HashSet<_> = (0..64u32).rev().collect();
+        assert_eq!(a.to_canonical_bytes(), b.to_canonical_bytes());
+    }
+}
@@ -4,11 +4,24 @@ use rkyv::{Archive, Deserialize, Serialize};
 
 use crate::{
     account::{AccountRef, GuarantorSigned, Verifier},
+    schema::HasSchema,
+    signature::SignatureContext,
     value::primitives::U64,
 };
 
 #[derive(
-    Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Archive, Serialize, Deserialize,
+    Copy,
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Archive,
+    Serialize,
+    Deserialize,
+    HasSchema,
 )]
 #[archive(compare(PartialEq))]
 #[archive_attr(derive(CheckBytes, Debug, PartialEq))]
@@ -26,10 +39,25 @@ impl Verifier for CreditRating {
     fn verify(&self, guarantor: Option<AccountRef>) -> Result<()> {
         self.0.verify(guarantor)
     }
+
+    fn verify_with(&self, context: &SignatureContext, guarantor: Option<AccountRef>) -> Result<()> {
+        self.0.verify_with(context, guarantor)
+    }
 }
 
 #[derive(
-    Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Archive, Serialize, Deserialize,
+    Copy,
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Archive,
+    Serialize,
+    Deserialize,
+    HasSchema,
 )]
 #[archive(compare(PartialEq, PartialOrd))]
 #[archive_attr(derive(CheckBytes, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash))]
@@ -0,0 +1,103 @@
+use std::io::Read;
+
+use anyhow::{bail, Result};
+use bytecheck::CheckBytes;
+use rkyv::{Archive, Deserialize, Serialize};
+
+use crate::{
+    account::{AccountRef, GuaranteeSigned, GuarantorSigned, Verifier},
+    schema::HasSchema,
+    value::{
+        hash::{Hash, Hasher},
+        primitives::U64,
+    },
+};
+
+/// A reference to external content, signed in place of the content itself.
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Archive,
+    Serialize,
+    Deserialize,
+    HasSchema,
+)]
+#[archive(compare(PartialEq, PartialOrd))]
+#[archive_attr(derive(CheckBytes, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash))]
+pub struct Detached {
+    pub hash: Hash,
+    pub len: U64,
+}
+
+impl Detached {
+    pub fn with_bytes(bytes: &[u8]) -> Self {
+        Self {
+            hash: Hash::with_bytes(bytes),
+            len: U64(bytes.len() as u64),
+        }
+    }
+
+    pub fn with_reader(reader: impl Read) -> Result<Self> {
+        let mut hasher = Hasher::default();
+        hasher.update_reader(reader)?;
+
+        Ok(Self {
+            len: U64(hasher.len()),
+            hash: hasher.finalize(),
+        })
+    }
+
+    pub fn verify_reader(&self, reader: impl Read) -> Result<()> {
+        let content = Self::with_reader(reader)?;
+
+        if self.len != content.len {
+            bail!(
+                "content length mismatching: expected {}, found {}",
+                *self.len,
+                *content.len,
+            );
+        }
+        if self.hash != content.hash {
+            bail!("content hash mismatching");
+        }
+        Ok(())
+    }
+}
+
+impl ArchivedDetached {
+    pub fn verify_reader(&self, reader: impl Read) -> Result<()> {
+        let content = Detached::with_reader(reader)?;
+
+        if self.len != content.len {
+            bail!(
+                "content length mismatching: expected {}, found {}",
+                self.len.0.value(),
+                *content.len,
+            );
+        }
+        if self.hash != content.hash {
+            bail!("content hash mismatching");
+        }
+        Ok(())
+    }
+}
+
+impl GuaranteeSigned<Detached> {
+    pub fn verify_content(&self, guarantor: Option<AccountRef>, reader: impl Read) -> Result<()> {
+        self.verify(guarantor)?;
+        self.data.data.verify_reader(reader)
+    }
+}
+
+impl GuarantorSigned<Detached> {
+    pub fn verify_content(&self, guarantor: Option<AccountRef>, reader: impl Read) -> Result<()> {
+        self.verify(guarantor)?;
+        self.data.data.data.verify_reader(reader)
+    }
+}
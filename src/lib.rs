@@ -1,3 +1,6 @@
+// the derive macros refer to this crate as `::ipi`
+extern crate self as ipi;
+
 pub extern crate anyhow;
 pub extern crate chrono;
 pub extern crate ed25519_dalek;
@@ -8,8 +11,11 @@ pub extern crate sha2;
 pub extern crate uuid;
 
 pub mod account;
+pub mod canonical;
 pub mod credit;
+pub mod detached;
 pub mod metadata;
+pub mod schema;
 pub mod signature;
 pub mod signed;
 pub mod value;
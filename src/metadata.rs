@@ -1,20 +1,37 @@
 use std::marker::PhantomData;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use bytecheck::CheckBytes;
 use rkyv::{Archive, Deserialize, Serialize};
 
 use crate::{
     account::{Account, AccountRef, GuaranteeSigned, Signer},
-    signature::SignatureSerializer,
-    value::{chrono::DateTime, nonce::Nonce},
+    canonical::Canonical,
+    schema::{HasSchema, SchemaHash},
+    signature::SignatureContext,
+    value::{
+        chrono::{DateTime, Duration},
+        nonce::Nonce,
+    },
 };
 
 #[derive(
-    Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Archive, Serialize, Deserialize,
+    Copy,
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Archive,
+    Serialize,
+    Deserialize,
+    HasSchema,
 )]
 #[archive(bound(archive = "
     <T as Archive>::Archived: ::core::fmt::Debug + PartialEq,
+    <SchemaHash<T> as Archive>::Archived: ::core::fmt::Debug + PartialEq,
 "))]
 #[archive(compare(PartialEq))]
 #[archive_attr(derive(CheckBytes, Debug, PartialEq))]
@@ -23,6 +40,7 @@ pub struct Metadata<T> {
     pub created_date: DateTime,
     pub expiration_date: Option<DateTime>,
     pub guarantor: AccountRef,
+    pub schema: SchemaHash<T>,
     pub data: T,
 }
 
@@ -37,20 +55,32 @@ impl<T> ::core::ops::Deref for Metadata<T> {
 impl<T> Metadata<T> {
     pub fn builder() -> MetadataBuilder<T> {
         MetadataBuilder {
-            expiration_date: None,
+            expiration: None,
             _data: Default::default(),
         }
     }
 }
 
 pub struct MetadataBuilder<T> {
-    expiration_date: Option<DateTime>,
+    expiration: Option<Expiration>,
     _data: PhantomData<T>,
 }
 
+enum Expiration {
+    Date(DateTime),
+    Ttl(Duration),
+}
+
 impl<T> MetadataBuilder<T> {
     pub fn expiration_date(mut self, date: DateTime) -> Self {
-        self.expiration_date = Some(date);
+        self.expiration = Some(Expiration::Date(date));
+        self
+    }
+
+    /// Expires the metadata after the duration since it is built,
+    /// replacing the `expiration_date` if given.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.expiration = Some(Expiration::Ttl(ttl));
         self
     }
 
@@ -61,17 +91,43 @@ impl<T> MetadataBuilder<T> {
         data: T,
     ) -> Result<GuaranteeSigned<T>>
     where
-        T: Archive + Serialize<SignatureSerializer>,
+        T: Archive + Canonical + HasSchema,
         <T as Archive>::Archived: ::core::fmt::Debug + PartialEq,
     {
+        self.build_with(&SignatureContext::default(), account, guarantor, data)
+    }
+
+    pub fn build_with(
+        self,
+        context: &SignatureContext,
+        account: &Account,
+        guarantor: AccountRef,
+        data: T,
+    ) -> Result<GuaranteeSigned<T>>
+    where
+        T: Archive + Canonical + HasSchema,
+        <T as Archive>::Archived: ::core::fmt::Debug + PartialEq,
+    {
+        let created_date = DateTime::now();
+        let expiration_date = match self.expiration {
+            Some(Expiration::Date(date)) => Some(date),
+            Some(Expiration::Ttl(ttl)) => Some(
+                created_date
+                    .checked_add(ttl)
+                    .ok_or_else(|| anyhow!("the expiration date overflowed: {ttl:?}"))?,
+            ),
+            None => None,
+        };
+
         let metadata = Metadata {
             nonce: Nonce::generate(),
-            created_date: DateTime::now(),
-            expiration_date: self.expiration_date,
+            created_date,
+            expiration_date,
             guarantor,
+            schema: SchemaHash::new(),
             data,
         };
 
-        Signer::sign(account, metadata)
+        Signer::sign_with(context, account, metadata)
     }
 }
@@ -0,0 +1,470 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    marker::PhantomData,
+    rc::Rc,
+    sync::Arc,
+};
+
+use bytecheck::CheckBytes;
+use ndarray::{Dim, Ix, IxDyn};
+use rkyv::{Archive, Deserialize, Fallible, Serialize};
+
+use crate::{
+    signature::{Keypair, PublicKey, Signature},
+    value::{
+        array::Array,
+        chrono::{DateTime, Duration, NaiveDateTime},
+        hash::Hash,
+        text::LanguageTag,
+        unit_interval::UnitInterval,
+        uuid::Uuid,
+    },
+};
+
+pub use ipi_derive::HasSchema;
+
+/// A deterministic description of a payload type.
+///
+/// The schema is a canonical string built from the type name and the names,
+/// types and order of its fields, so any layout change changes the hash.
+pub trait HasSchema {
+    fn schema() -> String;
+
+    fn schema_hash() -> Hash {
+        Hash::with_str(&Self::schema())
+    }
+}
+
+pub fn describe_struct(name: &str, fields: &[(&str, String)]) -> String {
+    let fields: Vec<_> = fields
+        .iter()
+        .map(|(name, ty)| format!("{name}:{ty}"))
+        .collect();
+    format!("{name}{{{}}}", fields.join(","))
+}
+
+pub fn describe_tuple(name: &str, fields: &[String]) -> String {
+    format!("{name}({})", fields.join(","))
+}
+
+pub fn describe_enum(name: &str, variants: &[String]) -> String {
+    format!("{name}[{}]", variants.join(","))
+}
+
+/// Implements `HasSchema` for an opaque type, described by its name alone.
+///
+/// The types with fields derive `HasSchema` from their definitions instead,
+/// so the schema follows the names, types and order of the fields:
+///
+/// ```
+/// use ipi::schema::HasSchema;
+///
+/// #[derive(HasSchema)]
+/// pub struct MyData {
+///     pub msg: String,
+///     pub num: u64,
+/// }
+///
+/// assert_eq!(MyData::schema(), "MyData{msg:String,num:u64}");
+/// ```
+///
+/// A reordered struct gets another schema:
+///
+/// ```
+/// use ipi::schema::HasSchema;
+///
+/// #[derive(HasSchema)]
+/// pub struct MyData {
+///     pub num: u64,
+///     pub msg: String,
+/// }
+///
+/// assert_eq!(MyData::schema(), "MyData{num:u64,msg:String}");
+/// ```
+///
+/// The fields cannot be listed by hand, as the list would drift from the definition:
+///
+/// ```compile_fail
+/// pub struct MyData {
+///     pub num: u64,
+///     pub msg: String,
+/// }
+///
+/// ::ipi::impl_has_schema!(MyData {
+///     msg: String,
+///     num: u64,
+/// });
+/// ```
+#[macro_export]
+macro_rules! impl_has_schema {
+    ( $name:ident ) => {
+        impl $crate::schema::HasSchema for $name {
+            fn schema() -> ::std::string::String {
+                ::std::string::String::from(stringify!($name))
+            }
+        }
+    };
+}
+
+pub struct SchemaHash<T> {
+    hash: Hash,
+    _type: PhantomData<T>,
+}
+
+impl<T> Copy for SchemaHash<T> {}
+
+impl<T> Clone for SchemaHash<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> ::core::fmt::Debug for SchemaHash<T> {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+        f.debug_tuple("SchemaHash").field(&self.hash).finish()
+    }
+}
+
+impl<T> Default for SchemaHash<T>
+where
+    T: HasSchema,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> PartialEq for SchemaHash<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash
+    }
+}
+
+impl<T> Eq for SchemaHash<T> {}
+
+impl<T> PartialOrd for SchemaHash<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<::core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for SchemaHash<T> {
+    fn cmp(&self, other: &Self) -> ::core::cmp::Ordering {
+        self.hash.cmp(&other.hash)
+    }
+}
+
+impl<T> ::core::hash::Hash for SchemaHash<T> {
+    fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+        self.hash.hash(state)
+    }
+}
+
+impl<T> ::core::ops::Deref for SchemaHash<T> {
+    type Target = Hash;
+
+    fn deref(&self) -> &Self::Target {
+        &self.hash
+    }
+}
+
+impl<T> SchemaHash<T> {
+    pub fn new() -> Self
+    where
+        T: HasSchema,
+    {
+        Self {
+            hash: T::schema_hash(),
+            _type: PhantomData,
+        }
+    }
+
+    pub fn ensure(&self) -> Result<(), SchemaMismatchError>
+    where
+        T: HasSchema,
+    {
+        let expected = T::schema_hash();
+        if self.hash == expected {
+            Ok(())
+        } else {
+            Err(SchemaMismatchError {
+                expected,
+                found: self.hash,
+            })
+        }
+    }
+}
+
+#[repr(transparent)]
+pub struct ArchivedSchemaHash<T> {
+    hash: <Hash as Archive>::Archived,
+    _type: PhantomData<T>,
+}
+
+impl<T> ::core::fmt::Debug for ArchivedSchemaHash<T> {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+        f.debug_tuple("ArchivedSchemaHash")
+            .field(&self.hash)
+            .finish()
+    }
+}
+
+impl<T> PartialEq for ArchivedSchemaHash<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash
+    }
+}
+
+impl<T> PartialEq<SchemaHash<T>> for ArchivedSchemaHash<T> {
+    fn eq(&self, other: &SchemaHash<T>) -> bool {
+        self.hash == other.hash
+    }
+}
+
+impl<T> ArchivedSchemaHash<T> {
+    pub fn ensure(&self) -> Result<(), SchemaMismatchError>
+    where
+        T: HasSchema,
+    {
+        let expected = T::schema_hash();
+        if self.hash == expected {
+            Ok(())
+        } else {
+            Err(SchemaMismatchError {
+                expected,
+                found: Hash(self.hash.into()),
+            })
+        }
+    }
+}
+
+impl<C: ?Sized, T> CheckBytes<C> for ArchivedSchemaHash<T>
+where
+    T: HasSchema,
+{
+    type Error = SchemaMismatchError;
+
+    #[inline]
+    unsafe fn check_bytes<'a>(
+        value: *const Self,
+        _context: &mut C,
+    ) -> Result<&'a Self, Self::Error> {
+        // every bit pattern is a valid hash, so only the schema itself is checked
+        let value = &*value;
+        value.ensure().map(|()| value)
+    }
+}
+
+impl<T> Archive for SchemaHash<T> {
+    type Archived = ArchivedSchemaHash<T>;
+    type Resolver = <Hash as Archive>::Resolver;
+
+    #[inline]
+    unsafe fn resolve(&self, pos: usize, resolver: Self::Resolver, out: *mut Self::Archived) {
+        self.hash.resolve(pos, resolver, out.cast())
+    }
+}
+
+impl<T, S: Fallible + ?Sized> Serialize<S> for SchemaHash<T> {
+    #[inline]
+    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        self.hash.serialize(serializer)
+    }
+}
+
+impl<T, D: Fallible + ?Sized> Deserialize<SchemaHash<T>, D> for ArchivedSchemaHash<T> {
+    #[inline]
+    fn deserialize(&self, _deserializer: &mut D) -> Result<SchemaHash<T>, D::Error> {
+        Ok(SchemaHash {
+            hash: Hash(self.hash.into()),
+            _type: PhantomData,
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SchemaMismatchError {
+    pub expected: Hash,
+    pub found: Hash,
+}
+
+impl ::core::fmt::Display for SchemaMismatchError {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+        write!(
+            f,
+            "schema mismatching: expected {}, found {}",
+            self.expected.to_string(),
+            self.found.to_string(),
+        )
+    }
+}
+
+impl ::std::error::Error for SchemaMismatchError {}
+
+macro_rules! impl_for_primitives {
+    ( $( $ty:ty => $name:expr ,)* ) => {
+        $(
+            impl HasSchema for $ty {
+                fn schema() -> String {
+                    String::from($name)
+                }
+            }
+        )*
+    };
+}
+
+impl_for_primitives!(
+    () => "()",
+    bool => "bool",
+    char => "char",
+    i8 => "i8",
+    i16 => "i16",
+    i32 => "i32",
+    i64 => "i64",
+    i128 => "i128",
+    isize => "isize",
+    u8 => "u8",
+    u16 => "u16",
+    u32 => "u32",
+    u64 => "u64",
+    u128 => "u128",
+    usize => "usize",
+    f32 => "f32",
+    f64 => "f64",
+    str => "String",
+    String => "String",
+    ::std::net::SocketAddr => "SocketAddr",
+    ::std::net::SocketAddrV4 => "SocketAddrV4",
+    ::std::net::SocketAddrV6 => "SocketAddrV6",
+);
+
+macro_rules! impl_for_tuples {
+    ( $( $ty:ident ,)* ) => {
+        impl< $( $ty: HasSchema ,)* > HasSchema for ( $( $ty ,)* ) {
+            fn schema() -> String {
+                describe_tuple("", &[ $( $ty::schema() ,)* ])
+            }
+        }
+    };
+}
+
+impl_for_tuples!(T1,);
+impl_for_tuples!(T1, T2,);
+impl_for_tuples!(T1, T2, T3,);
+impl_for_tuples!(T1, T2, T3, T4,);
+impl_for_tuples!(T1, T2, T3, T4, T5,);
+impl_for_tuples!(T1, T2, T3, T4, T5, T6,);
+impl_for_tuples!(T1, T2, T3, T4, T5, T6, T7,);
+impl_for_tuples!(T1, T2, T3, T4, T5, T6, T7, T8,);
+impl_for_tuples!(T1, T2, T3, T4, T5, T6, T7, T8, T9,);
+impl_for_tuples!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10,);
+impl_for_tuples!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11,);
+impl_for_tuples!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12,);
+
+macro_rules! impl_for_generics {
+    ( $( $name:expr => $ty:ident < $( $param:ident ),* > ,)* ) => {
+        $(
+            impl< $( $param: HasSchema + ?Sized ),* > HasSchema for $ty < $( $param ),* > {
+                fn schema() -> String {
+                    format!(
+                        "{}<{}>",
+                        $name,
+                        [ $( $param::schema() ),* ].join(","),
+                    )
+                }
+            }
+        )*
+    };
+}
+
+impl_for_generics!(
+    "Box" => Box<T>,
+    "Rc" => Rc<T>,
+    "Arc" => Arc<T>,
+    "PhantomData" => PhantomData<T>,
+);
+
+macro_rules! impl_for_collections {
+    ( $( $name:expr => $ty:ident < $( $param:ident ),* > ,)* ) => {
+        $(
+            impl< $( $param: HasSchema ),* > HasSchema for $ty < $( $param ),* > {
+                fn schema() -> String {
+                    format!(
+                        "{}<{}>",
+                        $name,
+                        [ $( $param::schema() ),* ].join(","),
+                    )
+                }
+            }
+        )*
+    };
+}
+
+impl_for_collections!(
+    "Vec" => Vec<T>,
+    "VecDeque" => VecDeque<T>,
+    "Option" => Option<T>,
+    "BTreeMap" => BTreeMap<K, V>,
+    "BTreeSet" => BTreeSet<T>,
+    "HashMap" => HashMap<K, V>,
+    "HashSet" => HashSet<T>,
+);
+
+impl<T: HasSchema, const N: usize> HasSchema for [T; N] {
+    fn schema() -> String {
+        format!("[{};{N}]", T::schema())
+    }
+}
+
+impl<T> HasSchema for SchemaHash<T> {
+    fn schema() -> String {
+        String::from("SchemaHash")
+    }
+}
+
+impl_has_schema!(Signature);
+impl_has_schema!(PublicKey);
+impl_has_schema!(Keypair);
+impl_has_schema!(DateTime);
+impl_has_schema!(NaiveDateTime);
+impl_has_schema!(Duration);
+impl_has_schema!(Hash);
+impl_has_schema!(LanguageTag);
+impl_has_schema!(UnitInterval);
+impl_has_schema!(Uuid);
+
+impl<A: HasSchema, D: ::ndarray::Dimension + HasSchema> HasSchema for Array<A, D> {
+    fn schema() -> String {
+        format!("Array<{},{}>", A::schema(), D::schema())
+    }
+}
+
+/// Describes the dimensions by their numbers of axes.
+macro_rules! impl_for_dimensions {
+    ( $( $ndim:literal ,)* ) => {
+        $(
+            impl HasSchema for Dim<[Ix; $ndim]> {
+                fn schema() -> String {
+                    String::from(stringify!($ndim))
+                }
+            }
+        )*
+    };
+}
+
+impl_for_dimensions!(0, 1, 2, 3, 4, 5, 6,);
+
+impl HasSchema for IxDyn {
+    fn schema() -> String {
+        String::from("dyn")
+    }
+}
+
+/// Stands for the enclosing type in the schemas of recursive types.
+pub struct Recursive;
+
+impl HasSchema for Recursive {
+    fn schema() -> String {
+        "Self".into()
+    }
+}
@@ -1,8 +1,56 @@
+use std::sync::Mutex;
+
 use anyhow::anyhow;
 use base58::{FromBase58, ToBase58};
-use rkyv::{ser::serializers::AllocSerializer, Archive, Deserialize, Fallible, Serialize};
+use bytecheck::CheckBytes;
+use rkyv::{Archive, Deserialize, Fallible, Serialize};
+
+use crate::canonical::Canonical;
+
+/// A pool of buffers for the canonical bytes, shared across threads to reuse their allocations.
+///
+/// At most `MAX_POOLED_BUFFERS` buffers are kept, and the buffers which have grown beyond
+/// `MAX_POOLED_CAPACITY` bytes are dropped instead of being kept for the life of the context.
+#[derive(Debug, Default)]
+pub struct SignatureContext {
+    pool: Mutex<Vec<Vec<u8>>>,
+}
+
+impl SignatureContext {
+    pub const MAX_POOLED_BUFFERS: usize = 64;
+    pub const MAX_POOLED_CAPACITY: usize = 4 << 20;
 
-pub type SignatureSerializer = AllocSerializer<64>;
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn encode<T, F, R>(&self, data: &T, f: F) -> R
+    where
+        T: Canonical + ?Sized,
+        F: FnOnce(&[u8]) -> R,
+    {
+        let mut buf = self
+            .pool
+            .lock()
+            .ok()
+            .and_then(|mut pool| pool.pop())
+            .unwrap_or_default();
+
+        data.write_canonical(&mut buf);
+        let result = f(&buf);
+
+        // return the buffer to the pool, unless it is too large to keep
+        if buf.capacity() <= Self::MAX_POOLED_CAPACITY {
+            buf.clear();
+            if let Ok(mut pool) = self.pool.lock() {
+                if pool.len() < Self::MAX_POOLED_BUFFERS {
+                    pool.push(buf);
+                }
+            }
+        }
+        result
+    }
+}
 
 #[derive(Copy, Clone, Debug, Eq)]
 pub struct Signature(pub(crate) ::ed25519_dalek::Signature);
@@ -69,29 +117,68 @@ impl ToString for Signature {
 }
 
 impl Archive for Signature {
-    type Archived = <[u8; 64] as Archive>::Archived;
-    type Resolver = <[u8; 64] as Archive>::Resolver;
+    type Archived = ArchivedSignature;
+    type Resolver = ();
 
     #[inline]
-    unsafe fn resolve(&self, pos: usize, resolver: Self::Resolver, out: *mut Self::Archived) {
-        self.0.to_bytes().resolve(pos, resolver, out)
+    unsafe fn resolve(&self, _pos: usize, _resolver: Self::Resolver, out: *mut Self::Archived) {
+        out.write(ArchivedSignature(self.0.to_bytes()))
     }
 }
 
 impl<S: Fallible + ?Sized> Serialize<S> for Signature {
     #[inline]
-    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
-        self.0.to_bytes().serialize(serializer)
+    fn serialize(&self, _serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        Ok(())
     }
 }
 
-impl<D: Fallible + ?Sized> Deserialize<Signature, D> for <Signature as Archive>::Archived {
+impl<D: Fallible + ?Sized> Deserialize<Signature, D> for ArchivedSignature
+where
+    D::Error: From<::ed25519_dalek::SignatureError>,
+{
     #[inline]
-    fn deserialize(&self, deserializer: &mut D) -> Result<Signature, D::Error> {
-        Deserialize::<[u8; 64], D>::deserialize(self, deserializer)
-            // FIXME: handle signature errors
-            .map(|ref e| ::ed25519_dalek::Signature::from_bytes(e).unwrap())
+    fn deserialize(&self, _deserializer: &mut D) -> Result<Signature, D::Error> {
+        ::ed25519_dalek::Signature::from_bytes(&self.0)
             .map(Signature)
+            .map_err(Into::into)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct ArchivedSignature([u8; 64]);
+
+impl ::core::ops::Deref for ArchivedSignature {
+    type Target = [u8; 64];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl PartialEq<Signature> for ArchivedSignature {
+    fn eq(&self, other: &Signature) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialOrd<Signature> for ArchivedSignature {
+    fn partial_cmp(&self, other: &Signature) -> Option<::core::cmp::Ordering> {
+        self.0.partial_cmp(other)
+    }
+}
+
+impl<C: ?Sized> CheckBytes<C> for ArchivedSignature {
+    type Error = ::ed25519_dalek::SignatureError;
+
+    #[inline]
+    unsafe fn check_bytes<'a>(
+        value: *const Self,
+        _context: &mut C,
+    ) -> Result<&'a Self, Self::Error> {
+        let value = &*value;
+        ::ed25519_dalek::Signature::from_bytes(&value.0).map(|_| value)
     }
 }
 
@@ -160,29 +247,68 @@ impl ToString for PublicKey {
 }
 
 impl Archive for PublicKey {
-    type Archived = <[u8; 32] as Archive>::Archived;
-    type Resolver = <[u8; 32] as Archive>::Resolver;
+    type Archived = ArchivedPublicKey;
+    type Resolver = ();
 
     #[inline]
-    unsafe fn resolve(&self, pos: usize, resolver: Self::Resolver, out: *mut Self::Archived) {
-        self.0.to_bytes().resolve(pos, resolver, out)
+    unsafe fn resolve(&self, _pos: usize, _resolver: Self::Resolver, out: *mut Self::Archived) {
+        out.write(ArchivedPublicKey(self.0.to_bytes()))
     }
 }
 
 impl<S: Fallible + ?Sized> Serialize<S> for PublicKey {
     #[inline]
-    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
-        self.0.to_bytes().serialize(serializer)
+    fn serialize(&self, _serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        Ok(())
     }
 }
 
-impl<D: Fallible + ?Sized> Deserialize<PublicKey, D> for <PublicKey as Archive>::Archived {
+impl<D: Fallible + ?Sized> Deserialize<PublicKey, D> for ArchivedPublicKey
+where
+    D::Error: From<::ed25519_dalek::SignatureError>,
+{
     #[inline]
-    fn deserialize(&self, deserializer: &mut D) -> Result<PublicKey, D::Error> {
-        Deserialize::<[u8; 32], D>::deserialize(self, deserializer)
-            // FIXME: handle signature errors
-            .map(|ref e| ::ed25519_dalek::PublicKey::from_bytes(e).unwrap())
+    fn deserialize(&self, _deserializer: &mut D) -> Result<PublicKey, D::Error> {
+        ::ed25519_dalek::PublicKey::from_bytes(&self.0)
             .map(PublicKey)
+            .map_err(Into::into)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct ArchivedPublicKey([u8; 32]);
+
+impl ::core::ops::Deref for ArchivedPublicKey {
+    type Target = [u8; 32];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl PartialEq<PublicKey> for ArchivedPublicKey {
+    fn eq(&self, other: &PublicKey) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialOrd<PublicKey> for ArchivedPublicKey {
+    fn partial_cmp(&self, other: &PublicKey) -> Option<::core::cmp::Ordering> {
+        self.0.partial_cmp(other)
+    }
+}
+
+impl<C: ?Sized> CheckBytes<C> for ArchivedPublicKey {
+    type Error = ::ed25519_dalek::SignatureError;
+
+    #[inline]
+    unsafe fn check_bytes<'a>(
+        value: *const Self,
+        _context: &mut C,
+    ) -> Result<&'a Self, Self::Error> {
+        let value = &*value;
+        ::ed25519_dalek::PublicKey::from_bytes(&value.0).map(|_| value)
     }
 }
 
@@ -227,29 +353,68 @@ impl ToString for Keypair {
 }
 
 impl Archive for Keypair {
-    type Archived = <[u8; 64] as Archive>::Archived;
-    type Resolver = <[u8; 64] as Archive>::Resolver;
+    type Archived = ArchivedKeypair;
+    type Resolver = ();
 
     #[inline]
-    unsafe fn resolve(&self, pos: usize, resolver: Self::Resolver, out: *mut Self::Archived) {
-        self.0.to_bytes().resolve(pos, resolver, out)
+    unsafe fn resolve(&self, _pos: usize, _resolver: Self::Resolver, out: *mut Self::Archived) {
+        out.write(ArchivedKeypair(self.0.to_bytes()))
     }
 }
 
 impl<S: Fallible + ?Sized> Serialize<S> for Keypair {
     #[inline]
-    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
-        self.0.to_bytes().serialize(serializer)
+    fn serialize(&self, _serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        Ok(())
     }
 }
 
-impl<D: Fallible + ?Sized> Deserialize<Keypair, D> for <Keypair as Archive>::Archived {
+impl<D: Fallible + ?Sized> Deserialize<Keypair, D> for ArchivedKeypair
+where
+    D::Error: From<::ed25519_dalek::SignatureError>,
+{
     #[inline]
-    fn deserialize(&self, deserializer: &mut D) -> Result<Keypair, D::Error> {
-        Deserialize::<[u8; 64], D>::deserialize(self, deserializer)
-            // FIXME: handle signature errors
-            .map(|ref e| ::ed25519_dalek::Keypair::from_bytes(e).unwrap())
+    fn deserialize(&self, _deserializer: &mut D) -> Result<Keypair, D::Error> {
+        ::ed25519_dalek::Keypair::from_bytes(&self.0)
             .map(Keypair)
+            .map_err(Into::into)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct ArchivedKeypair([u8; 64]);
+
+impl ::core::ops::Deref for ArchivedKeypair {
+    type Target = [u8; 64];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl PartialEq<Keypair> for ArchivedKeypair {
+    fn eq(&self, other: &Keypair) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialOrd<Keypair> for ArchivedKeypair {
+    fn partial_cmp(&self, other: &Keypair) -> Option<::core::cmp::Ordering> {
+        self.0.partial_cmp(other)
+    }
+}
+
+impl<C: ?Sized> CheckBytes<C> for ArchivedKeypair {
+    type Error = ::ed25519_dalek::SignatureError;
+
+    #[inline]
+    unsafe fn check_bytes<'a>(
+        value: *const Self,
+        _context: &mut C,
+    ) -> Result<&'a Self, Self::Error> {
+        let value = &*value;
+        ::ed25519_dalek::Keypair::from_bytes(&value.0).map(|_| value)
     }
 }
 
@@ -268,3 +433,52 @@ impl Keypair {
         &self.0.secret
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_pool() {
+        let context = SignatureContext::new();
+        let pooled = || context.pool.lock().unwrap().len();
+
+        // the small buffers are reused
+        context.encode(&42u64, |bytes| assert_eq!(bytes.len(), 8));
+        context.encode(&42u64, |_| {});
+        assert_eq!(pooled(), 1);
+
+        // the large buffers are dropped
+        let large = vec![0u8; SignatureContext::MAX_POOLED_CAPACITY + 1];
+        context.encode(&large, |_| ());
+        assert_eq!(pooled(), 0);
+
+        // the number of the buffers is bounded
+        let barrier = ::std::sync::Barrier::new(SignatureContext::MAX_POOLED_BUFFERS + 1);
+        ::std::thread::scope(|scope| {
+            for _ in 0..=SignatureContext::MAX_POOLED_BUFFERS {
+                scope.spawn(|| context.encode(&42u64, |_| barrier.wait()));
+            }
+        });
+        assert_eq!(pooled(), SignatureContext::MAX_POOLED_BUFFERS);
+    }
+
+    #[test]
+    fn test_malformed_keys() {
+        let identity = crate::account::Account::generate().sign_bytes(b"hello");
+        let bytes = identity.account.public_key.to_bytes();
+        assert!(::rkyv::check_archived_root::<PublicKey>(&bytes).is_ok());
+        let bytes = identity.signature.to_bytes();
+        assert!(::rkyv::check_archived_root::<Signature>(&bytes).is_ok());
+
+        // a point which is not on the curve
+        let bytes = [2u8; 32];
+        assert!(::rkyv::check_archived_root::<PublicKey>(&bytes).is_err());
+        assert!(crate::signed::from_bytes::<PublicKey>(&bytes).is_err());
+
+        // a signature whose scalar is not reduced
+        let bytes = [255u8; 64];
+        assert!(::rkyv::check_archived_root::<Signature>(&bytes).is_err());
+        assert!(crate::signed::from_bytes::<Signature>(&bytes).is_err());
+    }
+}
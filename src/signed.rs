@@ -1,7 +1,241 @@
+use core::{alloc::Layout, any::TypeId};
+
+use rkyv::{
+    de::{deserializers::SharedDeserializeMap, SharedDeserializeRegistry, SharedPointer},
+    validation::{
+        validators::{
+            ArchiveValidator, DefaultValidatorError, PrefixRange, SharedValidator, SuffixRange,
+        },
+        ArchiveContext, SharedContext,
+    },
+    Archive, CheckBytes, Deserialize, Fallible,
+};
+
 pub type Serializer = ::rkyv::ser::serializers::AllocSerializer<SERIALIZER_HEAP_SIZE>;
 
 pub const SERIALIZER_HEAP_SIZE: usize = 4096;
 
+/// The maximum nesting of the pointers in a checked archive, including the root.
+///
+/// The recursive types, such as `Value`, `Constraint` and `Expr`, are checked recursively,
+/// so a crafted archive could otherwise overflow the stack before any signature is verified.
+pub const MAX_DEPTH: usize = 128;
+
+/// Validates the archives as `DefaultValidator`, up to `MAX_DEPTH` nested pointers.
+#[derive(Debug)]
+pub struct Validator<'a> {
+    archive: ArchiveValidator<'a>,
+    shared: SharedValidator,
+}
+
+impl<'a> Validator<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self::with_max_depth(bytes, MAX_DEPTH)
+    }
+
+    pub fn with_max_depth(bytes: &'a [u8], max_depth: usize) -> Self {
+        Self {
+            archive: ArchiveValidator::with_max_depth(bytes, max_depth),
+            shared: SharedValidator::new(),
+        }
+    }
+}
+
+impl Fallible for Validator<'_> {
+    type Error = DefaultValidatorError;
+}
+
+impl ArchiveContext for Validator<'_> {
+    type PrefixRange = PrefixRange;
+    type SuffixRange = SuffixRange;
+
+    unsafe fn bounds_check_ptr(
+        &mut self,
+        base: *const u8,
+        offset: isize,
+    ) -> Result<*const u8, Self::Error> {
+        self.archive
+            .bounds_check_ptr(base, offset)
+            .map_err(DefaultValidatorError::ArchiveError)
+    }
+
+    unsafe fn bounds_check_layout(
+        &mut self,
+        data_address: *const u8,
+        layout: &Layout,
+    ) -> Result<(), Self::Error> {
+        self.archive
+            .bounds_check_layout(data_address, layout)
+            .map_err(DefaultValidatorError::ArchiveError)
+    }
+
+    unsafe fn bounds_check_subtree_ptr_layout(
+        &mut self,
+        data_address: *const u8,
+        layout: &Layout,
+    ) -> Result<(), Self::Error> {
+        self.archive
+            .bounds_check_subtree_ptr_layout(data_address, layout)
+            .map_err(DefaultValidatorError::ArchiveError)
+    }
+
+    unsafe fn push_prefix_subtree_range(
+        &mut self,
+        root: *const u8,
+        end: *const u8,
+    ) -> Result<PrefixRange, Self::Error> {
+        self.archive
+            .push_prefix_subtree_range(root, end)
+            .map_err(DefaultValidatorError::ArchiveError)
+    }
+
+    fn pop_prefix_range(&mut self, range: PrefixRange) -> Result<(), Self::Error> {
+        self.archive
+            .pop_prefix_range(range)
+            .map_err(DefaultValidatorError::ArchiveError)
+    }
+
+    unsafe fn push_suffix_subtree_range(
+        &mut self,
+        start: *const u8,
+        root: *const u8,
+    ) -> Result<SuffixRange, Self::Error> {
+        self.archive
+            .push_suffix_subtree_range(start, root)
+            .map_err(DefaultValidatorError::ArchiveError)
+    }
+
+    fn pop_suffix_range(&mut self, range: SuffixRange) -> Result<(), Self::Error> {
+        self.archive
+            .pop_suffix_range(range)
+            .map_err(DefaultValidatorError::ArchiveError)
+    }
+
+    fn finish(&mut self) -> Result<(), Self::Error> {
+        self.archive
+            .finish()
+            .map_err(DefaultValidatorError::ArchiveError)
+    }
+
+    fn wrap_layout_error(error: ::core::alloc::LayoutError) -> Self::Error {
+        DefaultValidatorError::ArchiveError(ArchiveValidator::wrap_layout_error(error))
+    }
+}
+
+impl SharedContext for Validator<'_> {
+    fn register_shared_ptr(
+        &mut self,
+        ptr: *const u8,
+        type_id: TypeId,
+    ) -> Result<bool, Self::Error> {
+        self.shared
+            .register_shared_ptr(ptr, type_id)
+            .map_err(DefaultValidatorError::SharedError)
+    }
+}
+
+#[derive(Default)]
+pub struct Deserializer(SharedDeserializeMap);
+
+impl Fallible for Deserializer {
+    type Error = DeserializeError;
+}
+
+impl SharedDeserializeRegistry for Deserializer {
+    fn get_shared_ptr(&mut self, ptr: *const u8) -> Option<&dyn SharedPointer> {
+        self.0.get_shared_ptr(ptr)
+    }
+
+    fn add_shared_ptr(
+        &mut self,
+        ptr: *const u8,
+        shared: Box<dyn SharedPointer>,
+    ) -> Result<(), Self::Error> {
+        self.0.add_shared_ptr(ptr, shared).map_err(Into::into)
+    }
+}
+
+#[derive(Debug)]
+pub enum DeserializeError {
+    Shared(::rkyv::de::deserializers::SharedDeserializeMapError),
+    Signature(::ed25519_dalek::SignatureError),
+    DateTime(crate::value::chrono::NaiveDateTimeRangeError),
+    Duration(crate::value::chrono::DurationRangeError),
+    LanguageTag(::language_tags::ParseError),
+    Shape(::ndarray::ShapeError),
+}
+
+impl From<::rkyv::de::deserializers::SharedDeserializeMapError> for DeserializeError {
+    fn from(error: ::rkyv::de::deserializers::SharedDeserializeMapError) -> Self {
+        Self::Shared(error)
+    }
+}
+
+impl From<::ed25519_dalek::SignatureError> for DeserializeError {
+    fn from(error: ::ed25519_dalek::SignatureError) -> Self {
+        Self::Signature(error)
+    }
+}
+
+impl From<crate::value::chrono::NaiveDateTimeRangeError> for DeserializeError {
+    fn from(error: crate::value::chrono::NaiveDateTimeRangeError) -> Self {
+        Self::DateTime(error)
+    }
+}
+
+impl From<crate::value::chrono::DurationRangeError> for DeserializeError {
+    fn from(error: crate::value::chrono::DurationRangeError) -> Self {
+        Self::Duration(error)
+    }
+}
+
+impl From<::language_tags::ParseError> for DeserializeError {
+    fn from(error: ::language_tags::ParseError) -> Self {
+        Self::LanguageTag(error)
+    }
+}
+
+impl From<::ndarray::ShapeError> for DeserializeError {
+    fn from(error: ::ndarray::ShapeError) -> Self {
+        Self::Shape(error)
+    }
+}
+
+impl ::core::fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+        match self {
+            Self::Shared(error) => write!(f, "failed to deserialize shared pointer: {error}"),
+            Self::Signature(error) => write!(f, "failed to deserialize signature: {error}"),
+            Self::DateTime(error) => write!(f, "failed to deserialize datetime: {error}"),
+            Self::Duration(error) => write!(f, "failed to deserialize duration: {error}"),
+            Self::LanguageTag(error) => write!(f, "failed to deserialize language tag: {error}"),
+            Self::Shape(error) => write!(f, "failed to deserialize array: {error}"),
+        }
+    }
+}
+
+impl ::std::error::Error for DeserializeError {}
+
+/// Checks the archive with `Validator`, without deserializing it.
+pub fn check_archived_root<'a, T>(bytes: &'a [u8]) -> ::anyhow::Result<&'a T::Archived>
+where
+    T: Archive,
+    <T as Archive>::Archived: CheckBytes<Validator<'a>> + 'a,
+{
+    ::rkyv::validation::check_archived_root_with_context::<T, _>(bytes, &mut Validator::new(bytes))
+        .map_err(|error| ::anyhow::anyhow!("failed to check archive: {error}"))
+}
+
+pub fn from_bytes<'a, T>(bytes: &'a [u8]) -> ::anyhow::Result<T>
+where
+    T: Archive,
+    <T as Archive>::Archived: CheckBytes<Validator<'a>> + Deserialize<T, Deserializer> + 'a,
+{
+    check_archived_root::<T>(bytes)?
+        .deserialize(&mut Deserializer::default())
+        .map_err(Into::into)
+}
+
 pub trait IsSigned {
     fn is_signed() -> bool {
         false
@@ -162,18 +396,38 @@ impl<T> IsSigned for crate::account::GuarantorSigned<T> {
 }
 impl IsSigned for crate::credit::CreditRating {}
 impl IsSigned for crate::credit::CreditRatingPayload {}
+impl IsSigned for crate::detached::Detached {}
 impl<T> IsSigned for crate::metadata::Metadata<T> {
     fn is_signed() -> bool {
         true
     }
 }
 
+impl<T> IsSigned for crate::schema::SchemaHash<T> {}
+
 impl IsSigned for crate::value::Value {}
 impl IsSigned for crate::value::ValueType {}
+impl IsSigned for crate::value::Field {}
+impl IsSigned for crate::value::FieldType {}
+impl IsSigned for crate::value::schema::RecordSchema {}
+impl IsSigned for crate::value::schema::FieldSchema {}
+impl IsSigned for crate::value::schema::Constraint {}
+impl IsSigned for crate::value::expr::Expr {}
+impl IsSigned for crate::value::expr::CompareOp {}
 impl<A, D: ::ndarray::Dimension> IsSigned for crate::value::array::Array<A, D> {}
 impl<A, D> IsSigned for crate::value::array::ArrayRaw<A, D> {}
+impl IsSigned for crate::value::array::Order {}
+impl<A> IsSigned for crate::value::array::chunked::ChunkedArray<A> {}
+impl<A> IsSigned for crate::value::array::sparse::CooArray<A> {}
+impl<A> IsSigned for crate::value::array::sparse::CsrArray<A> {}
+impl<A, D> IsSigned for crate::value::compressed::CompressedArray<A, D> {}
+impl IsSigned for crate::value::compressed::Compression {}
+impl IsSigned for crate::value::compressed::Encoding {}
+impl IsSigned for crate::value::dyn_array::DynArray {}
+impl IsSigned for crate::value::series::TimeSeries {}
 impl IsSigned for crate::value::chrono::DateTime {}
 impl IsSigned for crate::value::chrono::NaiveDateTime {}
+impl IsSigned for crate::value::chrono::Duration {}
 impl IsSigned for crate::value::hash::Hash {}
 impl IsSigned for crate::value::primitives::U64 {}
 impl IsSigned for crate::value::text::LanguageTag {}
@@ -0,0 +1,123 @@
+//! Aggregates over columns of dynamic values.
+//!
+//! `None`s are treated as missing values and skipped,
+//! so the aggregates of a column of only `None`s are the same as the ones of an empty column.
+
+use std::cmp::Ordering;
+
+use anyhow::{anyhow, bail, Result};
+
+use super::Value;
+
+/// Counts the values which are not `None`.
+pub fn count<'a>(values: impl IntoIterator<Item = &'a Value>) -> u64 {
+    present(values).count() as u64
+}
+
+/// Adds up the numbers with [`Value::checked_add`], or returns `None` if there is none.
+pub fn sum<'a>(values: impl IntoIterator<Item = &'a Value>) -> Result<Value> {
+    present(values).try_fold(Value::None, |sum, value| match sum {
+        Value::None if value.is_number() => Ok(value.clone()),
+        Value::None => bail!("cannot compute the sum of {:?}", value.value_type()),
+        sum => sum.checked_add(value),
+    })
+}
+
+/// Computes the arithmetic mean of the numbers in `f64`, or returns `None` if there is none.
+pub fn mean<'a>(values: impl IntoIterator<Item = &'a Value>) -> Result<Value> {
+    let mut count = 0u64;
+    let mut sum = 0.0f64;
+    for value in present(values) {
+        let number = value
+            .to_number()
+            .filter(|_| value.is_number())
+            .ok_or_else(|| anyhow!("cannot compute the mean of {:?}", value.value_type()))?;
+        count += 1;
+        sum += number.to_f64_rounded();
+    }
+    Ok(if count == 0 {
+        Value::None
+    } else {
+        Value::F64(sum / count as f64)
+    })
+}
+
+/// Returns the least value by [`Value::compare`], or `None` if there is none.
+///
+/// The first one is returned among the equal values.
+pub fn min<'a>(values: impl IntoIterator<Item = &'a Value>) -> Result<Value> {
+    select(values, Ordering::Less)
+}
+
+/// Returns the greatest value by [`Value::compare`], or `None` if there is none.
+///
+/// The first one is returned among the equal values.
+pub fn max<'a>(values: impl IntoIterator<Item = &'a Value>) -> Result<Value> {
+    select(values, Ordering::Greater)
+}
+
+fn select<'a>(values: impl IntoIterator<Item = &'a Value>, ordering: Ordering) -> Result<Value> {
+    let mut selected: Option<&Value> = None;
+    for value in present(values) {
+        selected = match selected {
+            Some(selected) => match value.compare(selected) {
+                Some(found) if found == ordering => Some(value),
+                Some(_) => Some(selected),
+                None => bail!(
+                    "cannot compare {:?} with {:?}",
+                    value.value_type(),
+                    selected.value_type(),
+                ),
+            },
+            None => Some(value),
+        };
+    }
+    Ok(selected.cloned().unwrap_or(Value::None))
+}
+
+fn present<'a>(values: impl IntoIterator<Item = &'a Value>) -> impl Iterator<Item = &'a Value> {
+    values
+        .into_iter()
+        .filter(|value| !matches!(value, Value::None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aggregate() {
+        let column = [
+            Value::I32(3),
+            Value::None,
+            Value::U8(250),
+            Value::I64(-5),
+            Value::F32(0.5),
+        ];
+
+        assert_eq!(count(&column), 4);
+        assert_eq!(sum(&column).unwrap(), Value::F64(248.5));
+        assert_eq!(sum(&column[..4]).unwrap(), Value::I64(248));
+        assert_eq!(mean(&column).unwrap(), Value::F64(62.125));
+        assert_eq!(min(&column).unwrap(), Value::I64(-5));
+        assert_eq!(max(&column).unwrap(), Value::U8(250));
+
+        // empty columns
+        assert_eq!(count(&[Value::None]), 0);
+        assert_eq!(sum(&[]).unwrap(), Value::None);
+        assert_eq!(mean(&[Value::None]).unwrap(), Value::None);
+        assert_eq!(max(&[]).unwrap(), Value::None);
+
+        // single values are checked too
+        assert!(sum(&[Value::String("a".into())]).is_err());
+        assert!(mean(&[Value::Bool(true)]).is_err());
+
+        // overflow
+        assert!(sum(&[Value::U8(200), Value::U8(100)]).is_err());
+
+        // non-numeric values of the same kind can be compared
+        let names = [Value::String("b".into()), Value::String("a".into())];
+        assert_eq!(min(&names).unwrap(), Value::String("a".into()));
+        assert!(max(&[Value::String("a".into()), Value::I8(1)]).is_err());
+    }
+}
@@ -0,0 +1,426 @@
+use std::cmp::Ordering;
+
+use anyhow::{anyhow, bail, Result};
+use fixed::types::U0F32;
+
+use super::{cast::Number, unit_interval::UnitInterval, Value, ValueType};
+
+/// The numeric kinds of the values, with the number of bits.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Kind {
+    Signed(u8),
+    Unsigned(u8),
+    Float(u8),
+    UnitInterval,
+}
+
+impl Kind {
+    fn of(value: &Value) -> Option<Self> {
+        Some(match value {
+            Value::I8(_) => Self::Signed(8),
+            Value::I16(_) => Self::Signed(16),
+            Value::I32(_) => Self::Signed(32),
+            Value::I64(_) => Self::Signed(64),
+            Value::I128(_) => Self::Signed(128),
+            Value::U8(_) => Self::Unsigned(8),
+            Value::U16(_) => Self::Unsigned(16),
+            Value::U32(_) => Self::Unsigned(32),
+            Value::U64(_) => Self::Unsigned(64),
+            Value::U128(_) => Self::Unsigned(128),
+            Value::F32(_) => Self::Float(32),
+            Value::F64(_) => Self::Float(64),
+            Value::UnitInterval(_) => Self::UnitInterval,
+            _ => return None,
+        })
+    }
+
+    /// The smallest kind that both of the kinds are promoted into.
+    ///
+    /// - integers of the same signedness are widened into the larger one
+    /// - signed and unsigned integers are widened into a signed one,
+    ///   which is large enough for both up to `i128`
+    /// - floats are widened into the larger one
+    /// - `UnitInterval`s stay as they are
+    /// - the others are promoted into `f64`
+    fn promote(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Signed(a), Self::Signed(b)) => Self::Signed(a.max(b)),
+            (Self::Unsigned(a), Self::Unsigned(b)) => Self::Unsigned(a.max(b)),
+            (Self::Signed(a), Self::Unsigned(b)) | (Self::Unsigned(b), Self::Signed(a)) => {
+                Self::Signed(a.max(b.saturating_mul(2)).min(128))
+            }
+            (Self::Float(a), Self::Float(b)) => Self::Float(a.max(b)),
+            (Self::UnitInterval, Self::UnitInterval) => Self::UnitInterval,
+            _ => Self::Float(64),
+        }
+    }
+
+    fn value_type(self) -> ValueType {
+        match self {
+            Self::Signed(8) => ValueType::I8,
+            Self::Signed(16) => ValueType::I16,
+            Self::Signed(32) => ValueType::I32,
+            Self::Signed(64) => ValueType::I64,
+            Self::Signed(_) => ValueType::I128,
+            Self::Unsigned(8) => ValueType::U8,
+            Self::Unsigned(16) => ValueType::U16,
+            Self::Unsigned(32) => ValueType::U32,
+            Self::Unsigned(64) => ValueType::U64,
+            Self::Unsigned(_) => ValueType::U128,
+            Self::Float(32) => ValueType::F32,
+            Self::Float(_) => ValueType::F64,
+            Self::UnitInterval => ValueType::UnitInterval,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl Op {
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::Mul => "*",
+            Self::Div => "/",
+        }
+    }
+
+    fn apply_signed(self, a: i128, b: i128) -> Option<i128> {
+        match self {
+            Self::Add => a.checked_add(b),
+            Self::Sub => a.checked_sub(b),
+            Self::Mul => a.checked_mul(b),
+            Self::Div => a.checked_div(b),
+        }
+    }
+
+    fn apply_unsigned(self, a: u128, b: u128) -> Option<u128> {
+        match self {
+            Self::Add => a.checked_add(b),
+            Self::Sub => a.checked_sub(b),
+            Self::Mul => a.checked_mul(b),
+            Self::Div => a.checked_div(b),
+        }
+    }
+
+    fn apply_float(self, a: f64, b: f64) -> f64 {
+        match self {
+            Self::Add => a + b,
+            Self::Sub => a - b,
+            Self::Mul => a * b,
+            Self::Div => a / b,
+        }
+    }
+
+    fn apply_unit_interval(self, a: U0F32, b: U0F32) -> Option<U0F32> {
+        match self {
+            Self::Add => a.checked_add(b),
+            Self::Sub => a.checked_sub(b),
+            Self::Mul => a.checked_mul(b),
+            Self::Div => a.checked_div(b),
+        }
+    }
+}
+
+impl Value {
+    /// Adds the numbers, promoting both into a common type.
+    ///
+    /// Integers of the same signedness are widened into the larger type, and mixed ones
+    /// into a signed type large enough for both, up to `i128`.
+    /// Floats are widened into the larger type, and `UnitInterval`s stay as they are.
+    /// The other combinations, such as integers with floats, are computed in `f64`.
+    ///
+    /// Fails if the result overflows the promoted type.
+    pub fn checked_add(&self, other: &Self) -> Result<Self> {
+        self.apply(other, Op::Add)
+    }
+
+    /// Subtracts the numbers, with the promotion rules of [`Value::checked_add`].
+    pub fn checked_sub(&self, other: &Self) -> Result<Self> {
+        self.apply(other, Op::Sub)
+    }
+
+    /// Multiplies the numbers, with the promotion rules of [`Value::checked_add`].
+    pub fn checked_mul(&self, other: &Self) -> Result<Self> {
+        self.apply(other, Op::Mul)
+    }
+
+    /// Divides the numbers, with the promotion rules of [`Value::checked_add`].
+    ///
+    /// Integers are truncated towards zero, and dividing them by zero fails.
+    pub fn checked_div(&self, other: &Self) -> Result<Self> {
+        self.apply(other, Op::Div)
+    }
+
+    /// Negates the number, failing on overflow or on nonzero unsigned numbers.
+    pub fn checked_neg(&self) -> Result<Self> {
+        let kind =
+            Kind::of(self).ok_or_else(|| anyhow!("cannot negate {:?}", self.value_type()))?;
+        let number = self.to_number().unwrap();
+        let value = match kind {
+            Kind::Signed(_) => number
+                .to_i128()
+                .and_then(i128::checked_neg)
+                .map(Number::Signed),
+            Kind::Unsigned(_) | Kind::UnitInterval => match number.to_u128() {
+                Some(0) => return Ok(self.clone()),
+                _ => None,
+            },
+            Kind::Float(_) => return Ok(float(kind, -number.to_f64().unwrap())),
+        };
+        value
+            .and_then(|value| value.cast(&kind.value_type()))
+            .ok_or_else(|| anyhow!("overflow: -{self}"))
+    }
+
+    /// Returns the absolute value of the number, failing on overflow.
+    pub fn checked_abs(&self) -> Result<Self> {
+        match Kind::of(self) {
+            Some(Kind::Signed(_) | Kind::Float(_))
+                if self.compare(&Self::I8(0)) == Some(Ordering::Less) =>
+            {
+                self.checked_neg()
+            }
+            Some(_) => Ok(self.clone()),
+            None => bail!("cannot take the absolute value of {:?}", self.value_type()),
+        }
+    }
+
+    /// Compares the values, where numbers are compared by their values regardless of the types.
+    ///
+    /// The numbers are promoted as in the arithmetic, so `I8(1)` is greater than `U64(0)`
+    /// and equal to `U64(1)`; the comparison operators agree, except that they break the ties
+    /// of equal numbers by the declaration order of the variants as `Ord` does.
+    ///
+    /// Returns `None` if the values are not comparable,
+    /// such as values of different kinds or `NaN`s.
+    pub fn compare(&self, other: &Self) -> Option<Ordering> {
+        match (Kind::of(self), Kind::of(other)) {
+            (Some(_), Some(_)) => self.to_number()?.compare(other.to_number()?),
+            _ if self.rank() == other.rank() => Some(self.cmp(other)),
+            _ => None,
+        }
+    }
+
+    /// Whether the value is a number, excluding `Bool`.
+    pub fn is_number(&self) -> bool {
+        Kind::of(self).is_some()
+    }
+
+    fn apply(&self, other: &Self, op: Op) -> Result<Self> {
+        let kind = match (Kind::of(self), Kind::of(other)) {
+            (Some(a), Some(b)) => a.promote(b),
+            _ => bail!(
+                "cannot apply {:?} to {:?} and {:?}",
+                op.symbol(),
+                self.value_type(),
+                other.value_type(),
+            ),
+        };
+        let (a, b) = (self.to_number().unwrap(), other.to_number().unwrap());
+
+        let value = match kind {
+            Kind::Signed(_) => a
+                .to_i128()
+                .zip(b.to_i128())
+                .and_then(|(a, b)| op.apply_signed(a, b))
+                .map(Number::Signed),
+            Kind::Unsigned(_) => a
+                .to_u128()
+                .zip(b.to_u128())
+                .and_then(|(a, b)| op.apply_unsigned(a, b))
+                .map(Number::Unsigned),
+            Kind::Float(_) => {
+                let (a, b) = (a.to_f64_rounded(), b.to_f64_rounded());
+                return Ok(float(kind, op.apply_float(a, b)));
+            }
+            Kind::UnitInterval => match (self, other) {
+                (Self::UnitInterval(a), Self::UnitInterval(b)) => {
+                    return op
+                        .apply_unit_interval(a.0, b.0)
+                        .map(UnitInterval)
+                        .map(Self::UnitInterval)
+                        .ok_or_else(|| anyhow!("overflow: {self} {} {other}", op.symbol()))
+                }
+                _ => unreachable!("only unit intervals are promoted into themselves"),
+            },
+        };
+        value
+            .and_then(|value| value.cast(&kind.value_type()))
+            .ok_or_else(|| anyhow!("overflow: {self} {} {other}", op.symbol()))
+    }
+}
+
+fn float(kind: Kind, value: f64) -> Value {
+    match kind {
+        Kind::Float(32) => Value::F32(value as f32),
+        _ => Value::F64(value),
+    }
+}
+
+impl ::core::ops::Add for &Value {
+    type Output = Result<Value>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.checked_add(rhs)
+    }
+}
+
+impl ::core::ops::Sub for &Value {
+    type Output = Result<Value>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.checked_sub(rhs)
+    }
+}
+
+impl ::core::ops::Mul for &Value {
+    type Output = Result<Value>;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.checked_mul(rhs)
+    }
+}
+
+impl ::core::ops::Div for &Value {
+    type Output = Result<Value>;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        self.checked_div(rhs)
+    }
+}
+
+impl ::core::ops::Neg for &Value {
+    type Output = Result<Value>;
+
+    fn neg(self) -> Self::Output {
+        self.checked_neg()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit(value: f64) -> Value {
+        Value::UnitInterval(UnitInterval(U0F32::from_num(value)))
+    }
+
+    #[test]
+    fn test_arith_promotion() {
+        let cases = [
+            (Value::I8(100), Value::I8(20), Value::I8(120)),
+            (Value::I8(-1), Value::I32(2), Value::I32(1)),
+            (Value::U8(200), Value::U16(100), Value::U16(300)),
+            (Value::U8(200), Value::I8(-1), Value::I16(199)),
+            (Value::U64(1), Value::I64(-2), Value::I128(-1)),
+            (Value::F32(0.5), Value::F32(0.25), Value::F32(0.75)),
+            (Value::F32(0.5), Value::F64(0.25), Value::F64(0.75)),
+            (Value::I32(1), Value::F32(0.5), Value::F64(1.5)),
+            (unit(0.25), unit(0.5), unit(0.75)),
+            (unit(0.5), Value::F64(1.0), Value::F64(1.5)),
+            (unit(0.5), Value::U8(1), Value::F64(1.5)),
+        ];
+        for (a, b, expected) in cases {
+            assert_eq!((&a + &b).unwrap(), expected, "{a} + {b}");
+            assert_eq!((&b + &a).unwrap(), expected, "{b} + {a}");
+        }
+
+        assert_eq!((&Value::I32(7) / &Value::I32(-2)).unwrap(), Value::I32(-3));
+        assert_eq!((&unit(0.5) * &unit(0.5)).unwrap(), unit(0.25));
+        assert_eq!((&unit(0.25) / &unit(0.5)).unwrap(), unit(0.5));
+        assert_eq!((&Value::U8(16) * &Value::U8(15)).unwrap(), Value::U8(240));
+    }
+
+    #[test]
+    fn test_arith_overflow() {
+        assert!((&Value::I8(100) + &Value::I8(100)).is_err());
+        assert!((&Value::U8(1) - &Value::U8(2)).is_err());
+        assert!((&Value::U128(u128::MAX) + &Value::I8(0)).is_err());
+        assert!((&Value::I32(1) / &Value::I32(0)).is_err());
+        assert!((&unit(0.5) + &unit(0.5)).is_err());
+        assert!((&unit(0.5) - &unit(0.75)).is_err());
+        assert!((&Value::Bool(true) + &Value::I8(1)).is_err());
+        assert!((&Value::String("1".into()) + &Value::I8(1)).is_err());
+
+        assert_eq!((-&Value::I8(-127)).unwrap(), Value::I8(127));
+        assert!((-&Value::I8(i8::MIN)).is_err());
+        assert!((-&Value::U8(1)).is_err());
+        assert_eq!((-&Value::U8(0)).unwrap(), Value::U8(0));
+        assert_eq!((-&Value::F32(1.5)).unwrap(), Value::F32(-1.5));
+
+        assert_eq!(Value::I16(-3).checked_abs().unwrap(), Value::I16(3));
+        assert_eq!(Value::F64(-0.5).checked_abs().unwrap(), Value::F64(0.5));
+        assert!(Value::I64(i64::MIN).checked_abs().is_err());
+        assert_eq!(unit(0.5).checked_abs().unwrap(), unit(0.5));
+    }
+
+    #[test]
+    fn test_compare() {
+        assert_eq!(Value::I8(-1).compare(&Value::U64(0)), Some(Ordering::Less));
+        assert_eq!(
+            Value::I8(1).compare(&Value::U64(0)),
+            Some(Ordering::Greater)
+        );
+
+        // the operators order the numbers by their values, then by the variants
+        assert!(Value::I8(1) > Value::U64(0));
+        assert!(Value::I8(-1) < Value::U8(0));
+        assert!(Value::I8(1) < Value::U64(1));
+        assert_eq!(Value::I8(1).compare(&Value::U64(1)), Some(Ordering::Equal));
+        assert!(Value::F64(0.5) < Value::I8(1));
+        assert!(Value::F64(f64::NAN) > Value::U128(u128::MAX));
+
+        // the floats are compared with the integers exactly
+        let exact = 1u64 << 53;
+        assert_eq!(
+            Value::F64(exact as f64).compare(&Value::U64(exact + 1)),
+            Some(Ordering::Less)
+        );
+        assert_eq!(
+            Value::F64(exact as f64).compare(&Value::I64(-(exact as i64) - 1)),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(
+            Value::F64(-0.5).compare(&Value::U8(0)),
+            Some(Ordering::Less)
+        );
+        assert_eq!(
+            Value::F64(-1.5).compare(&Value::I8(-1)),
+            Some(Ordering::Less)
+        );
+        assert_eq!(
+            Value::F64(1e40).compare(&Value::U128(u128::MAX)),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(
+            Value::F64(-1e40).compare(&Value::I128(i128::MIN)),
+            Some(Ordering::Less)
+        );
+        assert_eq!(
+            Value::F64(f64::INFINITY).compare(&Value::I8(0)),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(
+            Value::U8(1).compare(&Value::F32(1.0)),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(
+            unit(0.5).compare(&Value::F64(0.25)),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(Value::F64(f64::NAN).compare(&Value::F64(0.0)), None);
+        assert_eq!(
+            Value::String("a".into()).compare(&Value::String("b".into())),
+            Some(Ordering::Less),
+        );
+        assert_eq!(Value::String("a".into()).compare(&Value::I8(1)), None);
+    }
+}
@@ -1,7 +1,18 @@
-use anyhow::Result;
-use bytecheck::CheckBytes;
-use ndarray::{Dim, Dimension, Ix, IxDyn};
-use rkyv::{Archive, Deserialize, Fallible, Serialize};
+pub mod chunked;
+pub mod sparse;
+
+use anyhow::bail;
+use bytecheck::{CheckBytes, StructCheckError};
+use ndarray::{ArrayView, Dim, Dimension, IntoDimension, Ix, IxDyn, ShapeBuilder, ShapeError};
+use rkyv::{
+    from_archived, out_field,
+    ser::{ScratchSpace, Serializer},
+    validation::ArchiveContext,
+    vec::{ArchivedVec, VecResolver},
+    Archive, Deserialize, Fallible, Infallible, Serialize,
+};
+
+use crate::schema::HasSchema;
 
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct Array<A, D>(pub ::ndarray::ArcArray<A, D>)
@@ -32,32 +43,21 @@ impl<A, const D: usize> Array<A, Dim<[Ix; D]>>
 where
     Dim<[Ix; D]>: Dimension,
 {
-    fn try_from_raw(raw: ArrayRaw<A, <Dim<[Ix; D]> as Dimension>::Pattern>) -> Result<Self> {
-        ::ndarray::Array::<A, Dim<[Ix; D]>>::from_shape_vec(raw.dim, raw.data)
-            .map_err(Into::into)
-            .map(::ndarray::ArrayBase::into_shared)
-            .map(|e| Self(e))
-    }
-
-    fn try_to_raw(&self) -> Result<ArrayRaw<A, <Dim<[Ix; D]> as Dimension>::Pattern>>
-    where
-        A: Clone,
-    {
-        Ok(ArrayRaw {
-            data: self
-                .0
-                .as_slice()
-                .ok_or_else(|| ::anyhow::anyhow!("data is not standard layout"))?
-                .to_vec(),
-            dim: self.0.dim(),
-        })
+    fn try_from_raw(
+        raw: ArrayRaw<A, <Dim<[Ix; D]> as Dimension>::Pattern>,
+    ) -> Result<Self, ShapeError> {
+        match raw.order {
+            Order::RowMajor => ::ndarray::Array::from_shape_vec(raw.dim, raw.data),
+            Order::ColumnMajor => ::ndarray::Array::from_shape_vec(raw.dim.f(), raw.data),
+        }
+        .map(::ndarray::ArrayBase::into_shared)
+        .map(Self)
     }
 }
 
 impl<A, const D: usize> Archive for Array<A, Dim<[Ix; D]>>
 where
-    A: Clone,
-    Vec<A>: Archive,
+    A: Archive + Clone,
     <Vec<A> as Archive>::Archived: ::core::fmt::Debug + PartialEq,
     Dim<[Ix; D]>: Dimension,
     <Dim<[Ix; D]> as Dimension>::Pattern: Archive,
@@ -68,74 +68,70 @@ where
 
     #[inline]
     unsafe fn resolve(&self, pos: usize, resolver: Self::Resolver, out: *mut Self::Archived) {
-        self.try_to_raw().unwrap().resolve(pos, resolver, out)
+        let (fp, fo) = out_field!(out.data);
+        ArchivedVec::resolve_from_len(self.0.len(), pos + fp, resolver.data, fo);
+        let (fp, fo) = out_field!(out.dim);
+        self.0.dim().resolve(pos + fp, resolver.dim, fo);
+        let (fp, fo) = out_field!(out.order);
+        order(&self.0).resolve(pos + fp, resolver.order, fo);
     }
 }
 
 impl<A, const D: usize, S: Fallible + ?Sized> Serialize<S> for Array<A, Dim<[Ix; D]>>
 where
-    A: Clone,
-    Vec<A>: Archive + Serialize<S>,
+    A: Archive + Clone + Serialize<S>,
     <Vec<A> as Archive>::Archived: ::core::fmt::Debug + PartialEq,
     Dim<[Ix; D]>: Dimension,
     <Dim<[Ix; D]> as Dimension>::Pattern: Archive + Serialize<S>,
     <<Dim<[Ix; D]> as Dimension>::Pattern as Archive>::Archived: ::core::fmt::Debug + PartialEq,
+    S: ScratchSpace + Serializer,
 {
     #[inline]
     fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
-        self.try_to_raw().unwrap().serialize(serializer)
+        Ok(ArrayRawResolver {
+            data: serialize_data(&self.0, serializer)?,
+            dim: self.0.dim().serialize(serializer)?,
+            order: order(&self.0).serialize(serializer)?,
+        })
     }
 }
 
 impl<A, const D: usize, De: Fallible + ?Sized> Deserialize<Array<A, Dim<[Ix; D]>>, De>
     for <Array<A, Dim<[Ix; D]>> as Archive>::Archived
 where
-    A: Clone,
-    Vec<A>: Archive,
+    A: Archive + Clone,
     <Vec<A> as Archive>::Archived: Deserialize<Vec<A>, De> + ::core::fmt::Debug + PartialEq,
     Dim<[Ix; D]>: Dimension,
     <Dim<[Ix; D]> as Dimension>::Pattern: Archive,
     <<Dim<[Ix; D]> as Dimension>::Pattern as Archive>::Archived:
         Deserialize<<Dim<[Ix; D]> as Dimension>::Pattern, De> + ::core::fmt::Debug + PartialEq,
+    De::Error: From<ShapeError>,
 {
     #[inline]
     fn deserialize(&self, deserializer: &mut De) -> Result<Array<A, Dim<[Ix; D]>>, De::Error> {
-        Deserialize::<ArrayRaw<A, <Dim<[Ix; D]> as Dimension>::Pattern>, De>::deserialize(
-            self,
-            deserializer,
-        )
-        // FIXME: handle ndarray errors
-        .map(|e| Array::try_from_raw(e).unwrap())
+        let raw =
+            Deserialize::<ArrayRaw<A, <Dim<[Ix; D]> as Dimension>::Pattern>, De>::deserialize(
+                self,
+                deserializer,
+            )?;
+        Array::try_from_raw(raw).map_err(Into::into)
     }
 }
 
 impl<A> Array<A, IxDyn> {
-    fn try_from_raw_dyn(raw: ArrayRaw<A, Vec<usize>>) -> Result<Self> {
-        ::ndarray::Array::<A, IxDyn>::from_shape_vec(raw.dim, raw.data)
-            .map_err(Into::into)
-            .map(::ndarray::ArrayBase::into_shared)
-            .map(|e| Self(e))
-    }
-
-    fn try_to_raw_dyn(&self) -> Result<ArrayRaw<A, Vec<usize>>>
-    where
-        A: Clone,
-    {
-        Ok(ArrayRaw {
-            data: self
-                .0
-                .as_slice()
-                .ok_or_else(|| ::anyhow::anyhow!("data is not standard layout"))?
-                .to_vec(),
-            dim: self.0.shape().to_vec(),
-        })
+    fn try_from_raw_dyn(raw: ArrayRaw<A, Vec<usize>>) -> Result<Self, ShapeError> {
+        match raw.order {
+            Order::RowMajor => ::ndarray::Array::from_shape_vec(raw.dim, raw.data),
+            Order::ColumnMajor => ::ndarray::Array::from_shape_vec(raw.dim.f(), raw.data),
+        }
+        .map(::ndarray::ArrayBase::into_shared)
+        .map(Self)
     }
 }
 
 impl<A> Archive for Array<A, IxDyn>
 where
-    A: Clone,
-    Vec<A>: Archive,
+    A: Archive + Clone,
     <Vec<A> as Archive>::Archived: ::core::fmt::Debug + PartialEq,
 {
     type Archived = <ArrayRaw<A, Vec<usize>> as Archive>::Archived;
@@ -143,48 +139,301 @@ where
 
     #[inline]
     unsafe fn resolve(&self, pos: usize, resolver: Self::Resolver, out: *mut Self::Archived) {
-        self.try_to_raw_dyn().unwrap().resolve(pos, resolver, out)
+        let (fp, fo) = out_field!(out.data);
+        ArchivedVec::resolve_from_len(self.0.len(), pos + fp, resolver.data, fo);
+        let (fp, fo) = out_field!(out.dim);
+        ArchivedVec::resolve_from_len(self.0.ndim(), pos + fp, resolver.dim, fo);
+        let (fp, fo) = out_field!(out.order);
+        order(&self.0).resolve(pos + fp, resolver.order, fo);
     }
 }
 
 impl<A, S: Fallible + ?Sized> Serialize<S> for Array<A, IxDyn>
 where
-    A: Clone,
-    Vec<A>: Archive + Serialize<S>,
+    A: Archive + Clone + Serialize<S>,
     <Vec<A> as Archive>::Archived: ::core::fmt::Debug + PartialEq,
-    S: ::rkyv::ser::ScratchSpace + ::rkyv::ser::Serializer,
+    S: ScratchSpace + Serializer,
 {
     #[inline]
     fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
-        self.try_to_raw_dyn().unwrap().serialize(serializer)
+        Ok(ArrayRawResolver {
+            data: serialize_data(&self.0, serializer)?,
+            dim: ArchivedVec::serialize_from_slice(self.0.shape(), serializer)?,
+            order: order(&self.0).serialize(serializer)?,
+        })
     }
 }
 
 impl<A, De: Fallible + ?Sized> Deserialize<Array<A, IxDyn>, De>
     for <Array<A, IxDyn> as Archive>::Archived
 where
-    A: Clone,
-    Vec<A>: Archive,
+    A: Archive + Clone,
     <Vec<A> as Archive>::Archived: Deserialize<Vec<A>, De> + ::core::fmt::Debug + PartialEq,
+    De::Error: From<ShapeError>,
 {
     #[inline]
     fn deserialize(&self, deserializer: &mut De) -> Result<Array<A, IxDyn>, De::Error> {
-        Deserialize::<ArrayRaw<A, Vec<usize>>, De>::deserialize(self, deserializer)
-            // FIXME: handle ndarray errors
-            .map(|e| Array::try_from_raw_dyn(e).unwrap())
+        let raw = Deserialize::<ArrayRaw<A, Vec<usize>>, De>::deserialize(self, deserializer)?;
+        Array::try_from_raw_dyn(raw).map_err(Into::into)
+    }
+}
+
+impl<A> PartialEq<Array<A, IxDyn>> for <Array<A, IxDyn> as Archive>::Archived
+where
+    A: Archive + Clone,
+    A::Archived: PartialEq<A>,
+    <Vec<A> as Archive>::Archived: ::core::fmt::Debug + PartialEq,
+{
+    fn eq(&self, other: &Array<A, IxDyn>) -> bool {
+        // the elements are compared in the logical order, regardless of the memory layout
+        self.dim
+            .iter()
+            .map(|dim| from_archived!(*dim) as usize)
+            .eq(other.shape().iter().copied())
+            && self.iter().zip(other.iter()).all(|(a, b)| a == b)
+    }
+}
+
+fn serialize_data<A, D, S>(
+    array: &::ndarray::ArcArray<A, D>,
+    serializer: &mut S,
+) -> Result<VecResolver, S::Error>
+where
+    A: Clone + Serialize<S>,
+    D: Dimension,
+    S: ScratchSpace + Serializer + ?Sized,
+{
+    match (order(array), array.as_slice_memory_order()) {
+        (Order::RowMajor, Some(data)) if array.is_standard_layout() => {
+            ArchivedVec::serialize_from_slice(data, serializer)
+        }
+        (Order::ColumnMajor, Some(data)) => ArchivedVec::serialize_from_slice(data, serializer),
+        // collect the elements of the other layouts in the logical order
+        _ => {
+            let data: Vec<_> = array.iter().cloned().collect();
+            ArchivedVec::serialize_from_slice(&data, serializer)
+        }
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Archive, Serialize, Deserialize)]
+/// Returns the memory order to store the array in.
+///
+/// Column-major arrays are stored as they are, and the others are collected in the row-major order.
+fn order<A, D>(array: &::ndarray::ArcArray<A, D>) -> Order
+where
+    D: Dimension,
+{
+    if !array.is_standard_layout() && array.t().is_standard_layout() {
+        Order::ColumnMajor
+    } else {
+        Order::RowMajor
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Archive, Serialize, Deserialize, HasSchema)]
 #[archive(bound(archive = "
     <Vec<A> as Archive>::Archived: ::core::fmt::Debug + PartialEq,
     <D as Archive>::Archived: ::core::fmt::Debug + PartialEq,
 ",))]
 #[archive(compare(PartialEq))]
-#[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+#[archive_attr(derive(Debug, PartialEq))]
 pub struct ArrayRaw<A, D> {
-    data: Vec<A>,
-    dim: D,
+    pub(crate) data: Vec<A>,
+    pub(crate) dim: D,
+    pub(crate) order: Order,
+}
+
+impl<A, P, C> CheckBytes<C> for ArchivedArrayRaw<A, P>
+where
+    A: Archive,
+    A::Archived: CheckBytes<C>,
+    P: Archive + IntoDimension,
+    <P as Archive>::Archived:
+        CheckBytes<C> + Deserialize<P, Infallible> + ::core::fmt::Debug + PartialEq,
+    <<P as Archive>::Archived as CheckBytes<C>>::Error: ::std::error::Error + 'static,
+    <Vec<A> as Archive>::Archived: ::core::fmt::Debug + PartialEq,
+    C: ArchiveContext + ?Sized,
+    C::Error: ::std::error::Error,
+    <ArchivedVec<A::Archived> as CheckBytes<C>>::Error: ::std::error::Error + 'static,
+{
+    type Error = ArrayRawCheckError;
+
+    #[inline]
+    unsafe fn check_bytes<'a>(
+        value: *const Self,
+        context: &mut C,
+    ) -> Result<&'a Self, Self::Error> {
+        fn field<E: ::std::error::Error + 'static>(
+            field_name: &'static str,
+        ) -> impl FnOnce(E) -> ArrayRawCheckError {
+            move |error| {
+                ArrayRawCheckError::Field(StructCheckError {
+                    field_name,
+                    inner: Box::new(error),
+                })
+            }
+        }
+
+        ArchivedVec::<A::Archived>::check_bytes(::core::ptr::addr_of!((*value).data), context)
+            .map_err(field("data"))?;
+        <P as Archive>::Archived::check_bytes(::core::ptr::addr_of!((*value).dim), context)
+            .map_err(field("dim"))?;
+        ArchivedOrder::check_bytes(::core::ptr::addr_of!((*value).order), context)
+            .map_err(field("order"))?;
+
+        // the shape should cover the elements exactly, so that they can be viewed in place
+        let value = &*value;
+        let dim = match Deserialize::<P, _>::deserialize(&value.dim, &mut Infallible) {
+            Ok(dim) => dim.into_dimension(),
+            Err(never) => match never {},
+        };
+        let len = value.data.len();
+        if dim.size_checked() != Some(len) {
+            return Err(ArrayRawCheckError::Shape {
+                shape: dim.slice().to_vec(),
+                len,
+            });
+        }
+        Ok(value)
+    }
+}
+
+/// An error from checking the bytes of an archived array.
+#[derive(Debug)]
+pub enum ArrayRawCheckError {
+    /// A field is malformed.
+    Field(StructCheckError),
+    /// The number of the elements does not match the shape.
+    Shape { shape: Vec<usize>, len: usize },
+}
+
+impl ::core::fmt::Display for ArrayRawCheckError {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+        match self {
+            Self::Field(error) => error.fmt(f),
+            Self::Shape { shape, len } => {
+                write!(
+                    f,
+                    "expected the elements of the shape {shape:?}, found {len}"
+                )
+            }
+        }
+    }
+}
+
+impl ::std::error::Error for ArrayRawCheckError {}
+
+/// The memory order of the elements in `ArrayRaw`.
+#[derive(
+    Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Archive, Serialize, Deserialize, HasSchema,
+)]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Copy, Clone, Debug, PartialEq, Eq, Hash))]
+pub enum Order {
+    /// The last axis varies the fastest, as in C.
+    #[default]
+    RowMajor,
+    /// The first axis varies the fastest, as in Fortran.
+    ColumnMajor,
+}
+
+impl<A, P> ArchivedArrayRaw<A, P>
+where
+    A: Archive,
+    P: Archive + IntoDimension,
+    <P as Archive>::Archived: Deserialize<P, Infallible>,
+    <Vec<A> as Archive>::Archived: ::core::fmt::Debug + PartialEq,
+    <P as Archive>::Archived: ::core::fmt::Debug + PartialEq,
+{
+    /// Borrows the archived elements as an array view of the archived elements.
+    pub fn view_archived(&self) -> ::anyhow::Result<ArrayView<'_, A::Archived, P::Dim>> {
+        view::<_, P>(&self.dim, self.order, self.data.as_slice())
+    }
+
+    /// Iterates the archived elements in the logical order.
+    ///
+    /// The elements are iterated in the memory order if the shape is malformed.
+    pub fn iter(&self) -> impl Iterator<Item = &A::Archived> {
+        let (view, data) = match self.order {
+            ArchivedOrder::RowMajor => (None, Some(self.data.iter())),
+            ArchivedOrder::ColumnMajor => match self.view_archived() {
+                Ok(view) => (Some(view.into_iter()), None),
+                Err(_) => (None, Some(self.data.iter())),
+            },
+        };
+        view.into_iter().flatten().chain(data.into_iter().flatten())
+    }
+}
+
+/// Elements which are archived as their native representations, except for the byte order.
+///
+/// # Safety
+///
+/// The archived elements should have the same size as the native ones,
+/// and should be the same as them when the byte order of the archives is the native one.
+pub unsafe trait Plain: Archive + Copy {}
+
+macro_rules! impl_plain {
+    ( $( $ty:ty ),* ) => {
+        $(
+            unsafe impl Plain for $ty {}
+        )*
+    };
+}
+
+impl_plain!(bool, i8, i16, i32, i64, i128, u8, u16, u32, u64, u128, f32, f64);
+
+impl<A, P> ArchivedArrayRaw<A, P>
+where
+    A: Plain,
+    P: Archive + IntoDimension,
+    <P as Archive>::Archived: Deserialize<P, Infallible>,
+    <Vec<A> as Archive>::Archived: ::core::fmt::Debug + PartialEq,
+    <P as Archive>::Archived: ::core::fmt::Debug + PartialEq,
+{
+    /// Borrows the archived elements as an array view, without copying them.
+    ///
+    /// Fails if the elements are not aligned, or the byte order of the archives is not the native
+    /// one, unless the elements are single bytes.
+    /// Enable the `archive_le` feature to view the multi-byte elements on little-endian targets.
+    pub fn view(&self) -> ::anyhow::Result<ArrayView<'_, A, P::Dim>> {
+        let data = self.data.as_slice();
+        if ::core::mem::size_of::<A>() > 1 && !is_native_byte_order() {
+            bail!("the byte order of the archived array is not the native one");
+        }
+        if data.as_ptr().align_offset(::core::mem::align_of::<A>()) != 0 {
+            bail!("the archived array is not aligned");
+        }
+
+        // SAFETY: the elements are aligned, and they are the same as the native ones
+        let data = unsafe { ::core::slice::from_raw_parts(data.as_ptr().cast::<A>(), data.len()) };
+        view::<_, P>(&self.dim, self.order, data)
+    }
+}
+
+fn view<'a, T, P>(
+    dim: &<P as Archive>::Archived,
+    order: ArchivedOrder,
+    data: &'a [T],
+) -> ::anyhow::Result<ArrayView<'a, T, P::Dim>>
+where
+    P: Archive + IntoDimension,
+    <P as Archive>::Archived: Deserialize<P, Infallible>,
+{
+    let dim = Deserialize::<P, _>::deserialize(dim, &mut Infallible)?.into_dimension();
+    match order {
+        ArchivedOrder::RowMajor => ArrayView::from_shape(dim, data),
+        ArchivedOrder::ColumnMajor => ArrayView::from_shape(dim.f(), data),
+    }
+    .map_err(Into::into)
+}
+
+/// Returns `true` if the archives are stored in the native byte order.
+pub(crate) fn is_native_byte_order() -> bool {
+    let probe = <u32 as Archive>::Archived::from(1u32);
+    // SAFETY: the archived `u32`s have the same size as the native ones
+    let bytes: [u8; 4] = unsafe { ::core::mem::transmute(probe) };
+    bytes == 1u32.to_ne_bytes()
 }
 
 #[cfg(test)]
@@ -232,9 +481,157 @@ mod tests {
         let bytes = ::rkyv::to_bytes::<_, 4096>(&tensor).unwrap();
 
         // deserialize
-        let deserialized: MyTensor = ::rkyv::from_bytes(&bytes).unwrap();
+        let deserialized: MyTensor = crate::signed::from_bytes(&bytes).unwrap();
 
         // verify
         assert_eq!(&tensor, &deserialized);
     }
+
+    #[test]
+    fn test_array_malformed_shape() {
+        // the shape does not match the number of the elements
+        let raw = ArrayRaw {
+            data: vec![1u8, 2, 3],
+            dim: (2usize, 2usize),
+            order: Order::RowMajor,
+        };
+        let bytes = ::rkyv::to_bytes::<_, 4096>(&raw).unwrap();
+        assert!(::rkyv::check_archived_root::<Array<u8, ndarray::Ix2>>(&bytes).is_err());
+        let error = crate::signed::from_bytes::<Array<u8, ndarray::Ix2>>(&bytes).unwrap_err();
+        assert!(error.to_string().contains("shape"), "{error}");
+
+        let raw = ArrayRaw {
+            data: vec![1u8, 2, 3],
+            dim: vec![2usize, 2],
+            order: Order::ColumnMajor,
+        };
+        let bytes = ::rkyv::to_bytes::<_, 4096>(&raw).unwrap();
+        assert!(::rkyv::check_archived_root::<Array<u8, ndarray::IxDyn>>(&bytes).is_err());
+        assert!(crate::signed::from_bytes::<Array<u8, ndarray::IxDyn>>(&bytes).is_err());
+
+        // the number of the elements overflows
+        let raw = ArrayRaw {
+            data: Vec::<u8>::new(),
+            dim: vec![usize::MAX, 2],
+            order: Order::RowMajor,
+        };
+        let bytes = ::rkyv::to_bytes::<_, 4096>(&raw).unwrap();
+        assert!(::rkyv::check_archived_root::<Array<u8, ndarray::IxDyn>>(&bytes).is_err());
+
+        // the views are never taken of the malformed shapes
+        let raw = ArrayRaw {
+            data: vec![1u8, 2, 3, 4],
+            dim: vec![2usize, 2],
+            order: Order::ColumnMajor,
+        };
+        let bytes = ::rkyv::to_bytes::<_, 4096>(&raw).unwrap();
+        let archived = ::rkyv::check_archived_root::<Array<u8, ndarray::IxDyn>>(&bytes).unwrap();
+        assert_eq!(archived.view().unwrap().shape(), &[2, 2]);
+    }
+
+    #[test]
+    fn test_array_non_standard_layout() {
+        // create a transposed matrix
+        let matrix = ::ndarray::arr2(&[[1u64, 2, 3], [4, 5, 6]]).reversed_axes();
+        let array = Array(matrix.into_shared());
+        assert!(array.as_slice().is_none());
+
+        // serialize
+        let bytes = ::rkyv::to_bytes::<_, 4096>(&array).unwrap();
+
+        // deserialize
+        let deserialized: Array<u64, ndarray::Ix2> = crate::signed::from_bytes(&bytes).unwrap();
+
+        // verify
+        assert_eq!(&array, &deserialized);
+    }
+
+    #[test]
+    fn test_array_layouts() {
+        use ndarray::s;
+
+        let matrix = ::ndarray::Array::from_shape_fn((4, 6), |(i, j)| (i * 6 + j) as i64);
+        let fortran =
+            ::ndarray::Array::from_shape_vec((4, 6).f(), matrix.t().iter().copied().collect())
+                .unwrap();
+        assert_eq!(&fortran, &matrix);
+
+        let views = [
+            (matrix.view(), Order::RowMajor),
+            (matrix.t(), Order::ColumnMajor),
+            (fortran.view(), Order::ColumnMajor),
+            (matrix.slice(s![.., ..;2]), Order::RowMajor),
+            (matrix.slice(s![..;-1, 1..]), Order::RowMajor),
+            (fortran.slice(s![1..3, ..]), Order::RowMajor),
+        ];
+        for (view, order) in views {
+            let array = Array(view.to_shared().into_dyn());
+            let bytes = ::rkyv::to_bytes::<_, 4096>(&array).unwrap();
+            let archived =
+                ::rkyv::check_archived_root::<Array<i64, ndarray::IxDyn>>(&bytes).unwrap();
+            assert_eq!(archived.order, order);
+            assert_eq!(archived, &array);
+            assert!(archived.iter().eq(view.iter()));
+
+            // the column-major arrays are restored as they are
+            let deserialized: Array<i64, ndarray::IxDyn> =
+                crate::signed::from_bytes(&bytes).unwrap();
+            assert_eq!(&deserialized, &array);
+            assert_eq!(
+                deserialized.t().is_standard_layout(),
+                order == Order::ColumnMajor
+            );
+
+            let array = Array(view.to_shared());
+            let bytes = ::rkyv::to_bytes::<_, 4096>(&array).unwrap();
+            let deserialized: Array<i64, ndarray::Ix2> = crate::signed::from_bytes(&bytes).unwrap();
+            assert_eq!(&deserialized, &array);
+        }
+    }
+
+    #[test]
+    fn test_array_view() {
+        // fixed dimensions
+        let matrix = Array(::ndarray::arr2(&[[1u32, 2, 3], [4, 5, 6]]).into_shared());
+        let bytes = ::rkyv::to_bytes::<_, 4096>(&matrix).unwrap();
+        let archived = ::rkyv::check_archived_root::<Array<u32, ndarray::Ix2>>(&bytes).unwrap();
+        if is_native_byte_order() {
+            assert_eq!(archived.view().unwrap(), matrix.view());
+        } else {
+            assert!(archived.view().is_err());
+        }
+
+        // dynamic dimensions, even from non-standard layouts
+        let tensor = Array(
+            ::ndarray::Array::from_shape_fn((2, 3, 4), |(i, j, k)| (i * 12 + j * 4 + k) as u8)
+                .into_dyn()
+                .reversed_axes()
+                .into_shared(),
+        );
+        let bytes = ::rkyv::to_bytes::<_, 4096>(&tensor).unwrap();
+        let archived = ::rkyv::check_archived_root::<Array<u8, ndarray::IxDyn>>(&bytes).unwrap();
+        let view = archived.view().unwrap();
+        assert_eq!(view.shape(), &[4, 3, 2]);
+        assert_eq!(view, tensor.view());
+
+        // the view borrows the archived elements in place
+        assert_eq!(view.as_ptr(), archived.data.as_ptr());
+    }
+
+    #[test]
+    #[cfg(any(
+        all(feature = "archive_le", target_endian = "little"),
+        all(feature = "archive_be", target_endian = "big"),
+    ))]
+    fn test_array_view_native() {
+        // the multi-byte elements are viewed in place when the byte orders match
+        assert!(is_native_byte_order());
+
+        let matrix = Array(::ndarray::arr2(&[[1.5f64, 2.0], [3.0, 4.5]]).into_shared());
+        let bytes = ::rkyv::to_bytes::<_, 4096>(&matrix).unwrap();
+        let archived = ::rkyv::check_archived_root::<Array<f64, ndarray::Ix2>>(&bytes).unwrap();
+        let view = archived.view().unwrap();
+        assert_eq!(view, matrix.view());
+        assert_eq!(view.as_ptr().cast(), archived.data.as_ptr());
+    }
 }
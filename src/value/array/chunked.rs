@@ -0,0 +1,401 @@
+//! Chunked arrays, which are split into fixed-size tiles and loaded lazily.
+//!
+//! Each chunk is encoded like a `CompressedArray` and stored by its hash,
+//! and the manifest lists the chunks so that signing it covers the whole array.
+//! Reading a slice loads only the chunks it touches, verifying each against the manifest.
+
+use std::{collections::BTreeMap, fs, marker::PhantomData, ops::Range, path::PathBuf};
+
+use anyhow::{anyhow, bail, Result};
+use bytecheck::CheckBytes;
+use ndarray::{ArrayBase, Data, Dimension, IxDyn, Slice};
+use rkyv::{Archive, Deserialize, Infallible, Serialize};
+
+use super::Array;
+use crate::{
+    detached::Detached,
+    schema::HasSchema,
+    value::{
+        compressed::{self, CompressedArray, Element, Encoding},
+        hash::Hash,
+    },
+};
+
+/// The manifest of an array, whose chunks are stored separately.
+#[derive(Clone, Debug, PartialEq, Eq, Archive, Serialize, Deserialize, HasSchema)]
+#[archive(bound(archive = "
+    <PhantomData<A> as Archive>::Archived: ::core::fmt::Debug + PartialEq,
+"))]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+pub struct ChunkedArray<A> {
+    pub(crate) shape: Vec<u64>,
+    /// The shape of the chunks, except for the ones at the ends of the axes.
+    pub(crate) chunk_shape: Vec<u64>,
+    pub(crate) encoding: Encoding,
+    /// The chunks in the row-major order of the grid.
+    pub(crate) chunks: Vec<Detached>,
+    #[schema(skip)]
+    pub(crate) _element: PhantomData<A>,
+}
+
+/// Storages of the encoded chunks, addressed by their hashes.
+pub trait ChunkStore {
+    fn load(&self, hash: &Hash) -> Result<Vec<u8>>;
+
+    fn store(&mut self, hash: &Hash, bytes: Vec<u8>) -> Result<()>;
+}
+
+impl ChunkStore for BTreeMap<Hash, Vec<u8>> {
+    fn load(&self, hash: &Hash) -> Result<Vec<u8>> {
+        self.get(hash)
+            .cloned()
+            .ok_or_else(|| anyhow!("no such chunk: {}", hash.to_string()))
+    }
+
+    fn store(&mut self, hash: &Hash, bytes: Vec<u8>) -> Result<()> {
+        self.insert(*hash, bytes);
+        Ok(())
+    }
+}
+
+/// Stores the chunks as the files in a directory, named by their hashes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DirectoryStore(pub PathBuf);
+
+impl ChunkStore for DirectoryStore {
+    fn load(&self, hash: &Hash) -> Result<Vec<u8>> {
+        fs::read(self.0.join(hash.to_string())).map_err(Into::into)
+    }
+
+    fn store(&mut self, hash: &Hash, bytes: Vec<u8>) -> Result<()> {
+        fs::write(self.0.join(hash.to_string()), bytes).map_err(Into::into)
+    }
+}
+
+impl<A> ChunkedArray<A>
+where
+    A: Element + Default,
+{
+    /// Splits the array into the chunks of the given shape, and stores them.
+    pub fn write<S, D>(
+        array: &ArrayBase<S, D>,
+        chunk_shape: &[usize],
+        encoding: Encoding,
+        store: &mut impl ChunkStore,
+    ) -> Result<Self>
+    where
+        S: Data<Elem = A>,
+        D: Dimension,
+    {
+        let mut chunked = Self {
+            shape: array.shape().iter().map(|&dim| dim as u64).collect(),
+            chunk_shape: chunk_shape.iter().map(|&dim| dim as u64).collect(),
+            encoding,
+            chunks: Vec::new(),
+            _element: PhantomData,
+        };
+        let grid = chunked.grid()?;
+        for index in ::ndarray::indices(IxDyn(&grid)) {
+            let ranges = chunked.chunk_ranges(index.slice());
+            let chunk =
+                array.slice_each_axis(|axis| Slice::from(ranges[axis.axis.index()].clone()));
+            let bytes = CompressedArray::encode(&chunk, encoding)?.data;
+
+            let detached = Detached::with_bytes(&bytes);
+            store.store(&detached.hash, bytes)?;
+            chunked.chunks.push(detached);
+        }
+        Ok(chunked)
+    }
+
+    pub fn shape(&self) -> &[u64] {
+        &self.shape
+    }
+
+    pub fn chunk_shape(&self) -> &[u64] {
+        &self.chunk_shape
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Returns the contents of the chunks in the row-major order of the grid.
+    pub fn chunks(&self) -> &[Detached] {
+        &self.chunks
+    }
+
+    /// Loads all the chunks.
+    pub fn read(&self, store: &impl ChunkStore) -> Result<Array<A, IxDyn>> {
+        let ranges: Vec<_> = self.shape.iter().map(|&dim| 0..dim as usize).collect();
+        self.read_slice(&ranges, store)
+    }
+
+    /// Reads the elements in the ranges of the axes, loading only the chunks which they touch.
+    pub fn read_slice(
+        &self,
+        ranges: &[Range<usize>],
+        store: &impl ChunkStore,
+    ) -> Result<Array<A, IxDyn>> {
+        let grid = self.validate()?;
+        if ranges.len() != self.shape.len()
+            || ranges
+                .iter()
+                .zip(&self.shape)
+                .any(|(range, &dim)| range.start > range.end || range.end as u64 > dim)
+        {
+            bail!("slice out of bounds: {ranges:?} of {:?}", &self.shape);
+        }
+
+        let mut array = ::ndarray::ArrayD::from_elem(
+            ranges
+                .iter()
+                .map(ExactSizeIterator::len)
+                .collect::<Vec<_>>(),
+            A::default(),
+        );
+        if array.is_empty() {
+            return Ok(Array(array.into_shared()));
+        }
+
+        // the chunks which overlap the ranges
+        let (offsets, counts): (Vec<_>, Vec<_>) = ranges
+            .iter()
+            .zip(&self.chunk_shape)
+            .map(|(range, &size)| {
+                let size = size as usize;
+                let first = range.start / size;
+                (first, (range.end - 1) / size + 1 - first)
+            })
+            .unzip();
+
+        for index in ::ndarray::indices(IxDyn(&counts)) {
+            let index: Vec<_> = index
+                .slice()
+                .iter()
+                .zip(&offsets)
+                .map(|(i, o)| i + o)
+                .collect();
+            let chunk = self.read_chunk_at(&grid, &index, store)?;
+
+            let chunk_ranges = self.chunk_ranges(&index);
+            let overlaps: Vec<_> = ranges
+                .iter()
+                .zip(&chunk_ranges)
+                .map(|(range, chunk)| range.start.max(chunk.start)..range.end.min(chunk.end))
+                .collect();
+            array
+                .slice_each_axis_mut(|axis| {
+                    let (overlap, range) =
+                        (&overlaps[axis.axis.index()], &ranges[axis.axis.index()]);
+                    Slice::from(overlap.start - range.start..overlap.end - range.start)
+                })
+                .assign(&chunk.slice_each_axis(|axis| {
+                    let (overlap, chunk) = (
+                        &overlaps[axis.axis.index()],
+                        &chunk_ranges[axis.axis.index()],
+                    );
+                    Slice::from(overlap.start - chunk.start..overlap.end - chunk.start)
+                }));
+        }
+        Ok(Array(array.into_shared()))
+    }
+
+    /// Loads the chunk at the index of the grid.
+    pub fn read_chunk(&self, index: &[usize], store: &impl ChunkStore) -> Result<Array<A, IxDyn>> {
+        let grid = self.validate()?;
+        if index.len() != grid.len() || index.iter().zip(&grid).any(|(i, dim)| i >= dim) {
+            bail!("chunk index out of bounds: {index:?} of {grid:?}");
+        }
+        self.read_chunk_at(&grid, index, store)
+    }
+
+    fn read_chunk_at(
+        &self,
+        grid: &[usize],
+        index: &[usize],
+        store: &impl ChunkStore,
+    ) -> Result<Array<A, IxDyn>> {
+        let position = index
+            .iter()
+            .zip(grid)
+            .fold(0, |position, (i, dim)| position * dim + i);
+        let chunk = &self.chunks[position];
+
+        let bytes = store.load(&chunk.hash)?;
+        chunk.verify_reader(bytes.as_slice())?;
+
+        let shape: Vec<_> = self
+            .chunk_ranges(index)
+            .iter()
+            .map(|range| range.len() as u64)
+            .collect();
+        compressed::decode(&bytes, &shape, self.encoding, compressed::MAX_DECODED_SIZE)
+    }
+
+    /// Returns the number of the chunks along each axis.
+    fn grid(&self) -> Result<Vec<usize>> {
+        if self.chunk_shape.len() != self.shape.len() || self.chunk_shape.contains(&0) {
+            bail!(
+                "invalid chunk shape: {:?} of {:?}",
+                &self.chunk_shape,
+                &self.shape,
+            );
+        }
+
+        self.shape
+            .iter()
+            .zip(&self.chunk_shape)
+            .map(|(&dim, &size)| usize::try_from(dim.div_ceil(size)).map_err(Into::into))
+            .collect()
+    }
+
+    /// Returns the grid, checking that the manifest lists all the chunks.
+    fn validate(&self) -> Result<Vec<usize>> {
+        let grid = self.grid()?;
+        let len = grid
+            .iter()
+            .try_fold(1usize, |len, &dim| len.checked_mul(dim))
+            .ok_or_else(|| anyhow!("too many chunks: {grid:?}"))?;
+        if self.chunks.len() != len {
+            bail!("expected {len} chunks, found {}", self.chunks.len());
+        }
+        Ok(grid)
+    }
+
+    /// Returns the ranges of the elements in the chunk at the index of the grid.
+    fn chunk_ranges(&self, index: &[usize]) -> Vec<Range<usize>> {
+        index
+            .iter()
+            .zip(self.shape.iter().zip(&self.chunk_shape))
+            .map(|(&i, (&dim, &size))| {
+                let (dim, size) = (dim as usize, size as usize);
+                i * size..(i + 1).saturating_mul(size).min(dim)
+            })
+            .collect()
+    }
+}
+
+impl<A> ArchivedChunkedArray<A>
+where
+    A: Element + Default,
+    <PhantomData<A> as Archive>::Archived: ::core::fmt::Debug + PartialEq,
+{
+    /// Reads the elements in the ranges of the axes, loading only the chunks which they touch.
+    pub fn read_slice(
+        &self,
+        ranges: &[Range<usize>],
+        store: &impl ChunkStore,
+    ) -> Result<Array<A, IxDyn>> {
+        let manifest: ChunkedArray<A> = self.deserialize(&mut Infallible)?;
+        manifest.read_slice(ranges, store)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::value::compressed::Compression;
+
+    /// Counts the chunks loaded from the inner store.
+    #[derive(Default)]
+    struct CountingStore {
+        inner: BTreeMap<Hash, Vec<u8>>,
+        loaded: ::core::cell::Cell<usize>,
+    }
+
+    impl ChunkStore for CountingStore {
+        fn load(&self, hash: &Hash) -> Result<Vec<u8>> {
+            self.loaded.set(self.loaded.get() + 1);
+            self.inner.load(hash)
+        }
+
+        fn store(&mut self, hash: &Hash, bytes: Vec<u8>) -> Result<()> {
+            self.inner.store(hash, bytes)
+        }
+    }
+
+    #[test]
+    fn test_chunked_array() {
+        let array =
+            ::ndarray::Array::from_shape_fn((10, 7, 3), |(i, j, k)| (i * 100 + j * 10 + k) as u32);
+        let encoding = Encoding {
+            compression: Compression::None,
+            shuffle: true,
+        };
+
+        let mut store = CountingStore::default();
+        let chunked = ChunkedArray::write(&array, &[4, 4, 3], encoding, &mut store).unwrap();
+        assert_eq!(chunked.shape(), &[10, 7, 3]);
+        assert_eq!(chunked.chunks().len(), 3 * 2);
+        assert_eq!(chunked.read(&store).unwrap().0, array.clone().into_dyn());
+
+        // only the touched chunks are loaded
+        store.loaded.set(0);
+        let slice = chunked.read_slice(&[3..5, 1..3, 0..3], &store).unwrap();
+        assert_eq!(
+            slice.0,
+            array.slice(::ndarray::s![3..5, 1..3, ..]).into_dyn()
+        );
+        assert_eq!(store.loaded.get(), 2);
+
+        // the chunks at the ends are smaller
+        let chunk = chunked.read_chunk(&[2, 1, 0], &store).unwrap();
+        assert_eq!(chunk.shape(), &[2, 3, 3]);
+        assert!(chunked.read_chunk(&[3, 0, 0], &store).is_err());
+
+        // empty and out-of-bounds slices
+        assert!(chunked
+            .read_slice(&[5..5, 0..7, 0..3], &store)
+            .unwrap()
+            .is_empty());
+        assert!(chunked.read_slice(&[0..11, 0..7, 0..3], &store).is_err());
+        assert!(chunked.read_slice(&[0..1, 0..1], &store).is_err());
+
+        // tampered chunks are rejected
+        let hash = chunked.chunks()[0].hash;
+        store.inner.get_mut(&hash).unwrap()[0] ^= 1;
+        assert!(chunked.read_slice(&[0..1, 0..1, 0..1], &store).is_err());
+        assert!(chunked.read_slice(&[9..10, 0..1, 0..1], &store).is_ok());
+
+        // archive
+        let bytes = ::rkyv::to_bytes::<_, 4096>(&chunked).unwrap();
+        let archived = ::rkyv::check_archived_root::<ChunkedArray<u32>>(&bytes).unwrap();
+        assert_eq!(archived, &chunked);
+        assert_eq!(
+            archived.read_slice(&[9..10, 6..7, 2..3], &store).unwrap().0,
+            ::ndarray::arr3(&[[[962u32]]]).into_dyn(),
+        );
+    }
+
+    #[test]
+    fn test_chunked_array_malformed_grid() {
+        let mut store = CountingStore::default();
+        let array = ::ndarray::arr2(&[[1u8]]);
+        let mut chunked =
+            ChunkedArray::write(&array, &[1, 1], Encoding::default(), &mut store).unwrap();
+        chunked.shape = vec![u64::MAX, u64::MAX];
+
+        assert!(chunked.read_slice(&[0..1, 0..1], &store).is_err());
+        let bytes = ::rkyv::to_bytes::<_, 4096>(&chunked).unwrap();
+        let archived = ::rkyv::check_archived_root::<ChunkedArray<u8>>(&bytes).unwrap();
+        assert!(archived.read_slice(&[0..1, 0..1], &store).is_err());
+        assert_eq!(store.loaded.get(), 0);
+    }
+
+    #[test]
+    fn test_directory_store() {
+        let path = ::std::env::temp_dir().join(format!("ipi-chunks-{}", ::std::process::id()));
+        fs::create_dir_all(&path).unwrap();
+        let mut store = DirectoryStore(path.clone());
+
+        let array = ::ndarray::Array::from_shape_fn((5, 5), |(i, j)| (i * j) as f64);
+        let chunked = ChunkedArray::write(&array, &[2, 5], Default::default(), &mut store).unwrap();
+        assert_eq!(chunked.read(&store).unwrap().0, array.into_dyn());
+        assert_eq!(fs::read_dir(&path).unwrap().count(), 3);
+
+        fs::remove_dir_all(&path).unwrap();
+    }
+}
@@ -0,0 +1,586 @@
+//! Sparse arrays, which store only the non-zero elements.
+//!
+//! The non-zero elements are kept in the row-major order of their indices,
+//! so that the same arrays are always archived and signed the same.
+
+use anyhow::{anyhow, bail, Result};
+use bytecheck::CheckBytes;
+use ndarray::{Dimension, IntoDimension, Ix2, IxDyn};
+use rkyv::{from_archived, Archive, Archived, Deserialize, Serialize};
+
+use super::Array;
+use crate::{schema::HasSchema, value::compressed::MAX_DECODED_SIZE};
+
+/// A sparse array in the coordinate format, of any number of dimensions.
+#[derive(Clone, Debug, PartialEq, Eq, Archive, Serialize, Deserialize, HasSchema)]
+#[archive(bound(archive = "<Vec<A> as Archive>::Archived: ::core::fmt::Debug + PartialEq"))]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+pub struct CooArray<A> {
+    pub(crate) shape: Vec<u64>,
+    /// The indices of the non-zero elements, concatenated.
+    pub(crate) indices: Vec<u64>,
+    pub(crate) values: Vec<A>,
+}
+
+impl<A> CooArray<A> {
+    /// Creates a sparse array of the given elements, which are sorted by their indices.
+    pub fn try_new(
+        shape: &[usize],
+        elements: impl IntoIterator<Item = (Vec<usize>, A)>,
+    ) -> Result<Self> {
+        let mut elements: Vec<_> = elements.into_iter().collect();
+        for (index, _) in &elements {
+            if index.len() != shape.len() || index.iter().zip(shape).any(|(i, dim)| i >= dim) {
+                bail!("index out of bounds: {index:?} of {shape:?}");
+            }
+        }
+        elements.sort_by(|(a, _), (b, _)| a.cmp(b));
+        if let Some(pair) = elements.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            bail!("duplicated index: {:?}", &pair[0].0);
+        }
+
+        Ok(Self {
+            shape: shape.iter().map(|&dim| dim as u64).collect(),
+            indices: elements
+                .iter()
+                .flat_map(|(index, _)| index.iter().map(|&i| i as u64))
+                .collect(),
+            values: elements.into_iter().map(|(_, value)| value).collect(),
+        })
+    }
+
+    pub fn shape(&self) -> &[u64] {
+        &self.shape
+    }
+
+    /// Returns the number of the non-zero elements.
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Iterates the indices and the values of the non-zero elements.
+    pub fn iter(&self) -> impl Iterator<Item = (&[u64], &A)> {
+        coo_elements(self.shape.len(), &self.indices, &self.values)
+    }
+}
+
+impl<A> CooArray<A>
+where
+    A: Clone + Default + PartialEq,
+{
+    /// Collects the elements which are not the default ones.
+    pub fn from_dense<D: Dimension>(array: &Array<A, D>) -> Self {
+        let zero = A::default();
+        let mut indices = Vec::new();
+        let mut values = Vec::new();
+        for (index, value) in array.indexed_iter() {
+            if *value != zero {
+                let index = index.into_dimension();
+                indices.extend(index.slice().iter().map(|&i| i as u64));
+                values.push(value.clone());
+            }
+        }
+
+        Self {
+            shape: array.shape().iter().map(|&dim| dim as u64).collect(),
+            indices,
+            values,
+        }
+    }
+
+    /// Fills the other elements with the default ones,
+    /// up to `MAX_DECODED_SIZE` bytes of elements.
+    pub fn to_dense<D: Dimension>(&self) -> Result<Array<A, D>> {
+        self.to_dense_with_limit(MAX_DECODED_SIZE)
+    }
+
+    /// Fills the other elements with the default ones,
+    /// failing before allocating if they would take more than `max_size` bytes.
+    pub fn to_dense_with_limit<D: Dimension>(&self, max_size: usize) -> Result<Array<A, D>> {
+        let shape = to_usizes(&self.shape)?;
+        let ndim = shape.len();
+        if self.indices.len() != self.values.len() * ndim {
+            bail!(
+                "expected {} indices, found {}",
+                self.values.len() * ndim,
+                self.indices.len(),
+            );
+        }
+
+        let mut array = try_from_default(&shape, max_size)?;
+        for (index, value) in self.iter() {
+            let index = to_usizes(index)?;
+            *array
+                .get_mut(IxDyn(&index))
+                .ok_or_else(|| anyhow!("index out of bounds: {index:?}"))? = value.clone();
+        }
+        array
+            .into_dimensionality()
+            .map(::ndarray::ArrayBase::into_shared)
+            .map(Array)
+            .map_err(Into::into)
+    }
+}
+
+impl<A> ArchivedCooArray<A>
+where
+    A: Archive,
+    <Vec<A> as Archive>::Archived: ::core::fmt::Debug + PartialEq,
+{
+    pub fn shape(&self) -> Vec<usize> {
+        self.shape
+            .iter()
+            .map(|dim| from_archived!(*dim) as usize)
+            .collect()
+    }
+
+    /// Returns the number of the non-zero elements.
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Iterates the archived indices and values of the non-zero elements, without copying them.
+    pub fn iter(&self) -> impl Iterator<Item = (&[Archived<u64>], &A::Archived)> {
+        coo_elements(self.shape.len(), &self.indices, &self.values)
+    }
+}
+
+/// A sparse matrix in the compressed sparse row format.
+#[derive(Clone, Debug, PartialEq, Eq, Archive, Serialize, Deserialize, HasSchema)]
+#[archive(bound(archive = "<Vec<A> as Archive>::Archived: ::core::fmt::Debug + PartialEq"))]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+pub struct CsrArray<A> {
+    pub(crate) shape: [u64; 2],
+    /// The offsets of the rows in `indices` and `values`, followed by the number of the elements.
+    pub(crate) indptr: Vec<u64>,
+    /// The column indices of the non-zero elements.
+    pub(crate) indices: Vec<u64>,
+    pub(crate) values: Vec<A>,
+}
+
+impl<A> CsrArray<A> {
+    pub fn shape(&self) -> &[u64] {
+        &self.shape
+    }
+
+    /// Returns the number of the non-zero elements.
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Iterates the column indices and the values of the non-zero elements in the row.
+    pub fn row(&self, row: usize) -> impl Iterator<Item = (usize, &A)> {
+        csr_row(&self.indptr, &self.indices, &self.values, row, |i| {
+            i as usize
+        })
+    }
+
+    /// Iterates the row indices, the column indices and the values of the non-zero elements.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize, &A)> {
+        csr_elements(&self.indptr, &self.indices, &self.values, |i| i as usize)
+    }
+}
+
+impl<A> CsrArray<A>
+where
+    A: Clone + Default + PartialEq,
+{
+    /// Collects the elements which are not the default ones.
+    pub fn from_dense(array: &Array<A, Ix2>) -> Self {
+        let zero = A::default();
+        let mut indptr = vec![0];
+        let mut indices = Vec::new();
+        let mut values = Vec::new();
+        for row in array.rows() {
+            for (col, value) in row.iter().enumerate() {
+                if *value != zero {
+                    indices.push(col as u64);
+                    values.push(value.clone());
+                }
+            }
+            indptr.push(values.len() as u64);
+        }
+
+        let (rows, cols) = array.dim();
+        Self {
+            shape: [rows as u64, cols as u64],
+            indptr,
+            indices,
+            values,
+        }
+    }
+
+    /// Fills the other elements with the default ones,
+    /// up to `MAX_DECODED_SIZE` bytes of elements.
+    pub fn to_dense(&self) -> Result<Array<A, Ix2>> {
+        self.to_dense_with_limit(MAX_DECODED_SIZE)
+    }
+
+    /// Fills the other elements with the default ones,
+    /// failing before allocating if they would take more than `max_size` bytes.
+    pub fn to_dense_with_limit(&self, max_size: usize) -> Result<Array<A, Ix2>> {
+        let [rows, cols] = self.shape;
+        let (rows, cols) = (usize::try_from(rows)?, usize::try_from(cols)?);
+        if self.indptr.len().checked_sub(1) != Some(rows)
+            || self.indptr.first() != Some(&0)
+            || self.indptr.last() != Some(&(self.values.len() as u64))
+            || self.indptr.windows(2).any(|pair| pair[0] > pair[1])
+        {
+            bail!("malformed row offsets: {:?}", &self.indptr);
+        }
+        if self.indices.len() != self.values.len() {
+            bail!(
+                "expected {} column indices, found {}",
+                self.values.len(),
+                self.indices.len(),
+            );
+        }
+
+        let mut array = try_from_default(&[rows, cols], max_size)?.into_dimensionality::<Ix2>()?;
+        for (row, col, value) in self.iter() {
+            *array
+                .get_mut((row, col))
+                .ok_or_else(|| anyhow!("index out of bounds: {:?}", (row, col)))? = value.clone();
+        }
+        Ok(Array(array.into_shared()))
+    }
+}
+
+impl<A> ArchivedCsrArray<A>
+where
+    A: Archive,
+    <Vec<A> as Archive>::Archived: ::core::fmt::Debug + PartialEq,
+{
+    pub fn shape(&self) -> [usize; 2] {
+        self.shape.map(|dim| from_archived!(dim) as usize)
+    }
+
+    /// Returns the number of the non-zero elements.
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Iterates the column indices and the archived values of the non-zero elements in the row,
+    /// without copying them.
+    pub fn row(&self, row: usize) -> impl Iterator<Item = (usize, &A::Archived)> {
+        csr_row(&self.indptr, &self.indices, &self.values, row, |i| {
+            from_archived!(i) as usize
+        })
+    }
+
+    /// Iterates the row indices, the column indices and the archived values of the non-zero
+    /// elements, without copying them.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize, &A::Archived)> {
+        csr_elements(&self.indptr, &self.indices, &self.values, |i| {
+            from_archived!(i) as usize
+        })
+    }
+}
+
+impl<A> From<CsrArray<A>> for CooArray<A> {
+    fn from(array: CsrArray<A>) -> Self {
+        let indices = array
+            .iter()
+            .flat_map(|(row, col, _)| [row as u64, col as u64])
+            .collect();
+        Self {
+            shape: array.shape.to_vec(),
+            indices,
+            values: array.values,
+        }
+    }
+}
+
+impl<A> TryFrom<CooArray<A>> for CsrArray<A> {
+    type Error = ::anyhow::Error;
+
+    /// Converts the sparse matrix, which should be sorted by the indices.
+    fn try_from(array: CooArray<A>) -> Result<Self, Self::Error> {
+        let shape: [u64; 2] = array
+            .shape
+            .as_slice()
+            .try_into()
+            .map_err(|_| anyhow!("expected a matrix, found the shape {:?}", &array.shape))?;
+
+        let mut indptr = vec![0; usize::try_from(shape[0])? + 1];
+        let mut indices = Vec::with_capacity(array.nnz());
+        let mut last = None;
+        for (index, _) in array.iter() {
+            let (row, col) = (index[0], index[1]);
+            if row >= shape[0] || col >= shape[1] {
+                bail!("index out of bounds: {index:?} of {shape:?}");
+            }
+            if last >= Some((row, col)) {
+                bail!("unsorted indices: {index:?}");
+            }
+            last = Some((row, col));
+
+            indptr[row as usize + 1] += 1;
+            indices.push(col);
+        }
+        if indices.len() != array.nnz() {
+            bail!(
+                "expected {} indices, found {}",
+                array.nnz() * 2,
+                array.indices.len()
+            );
+        }
+        for row in 1..indptr.len() {
+            indptr[row] += indptr[row - 1];
+        }
+
+        Ok(Self {
+            shape,
+            indptr,
+            indices,
+            values: array.values,
+        })
+    }
+}
+
+/// Allocates an array of the default elements,
+/// failing instead of panicking or aborting if the shape is too large.
+fn try_from_default<A>(shape: &[usize], max_size: usize) -> Result<::ndarray::ArrayD<A>>
+where
+    A: Clone + Default,
+{
+    let (len, size) = shape
+        .iter()
+        .try_fold(1usize, |len, &dim| len.checked_mul(dim))
+        .and_then(|len| Some((len, len.checked_mul(::core::mem::size_of::<A>())?)))
+        .ok_or_else(|| anyhow!("array too large: {shape:?}"))?;
+    if size > max_size {
+        bail!("array too large: {size} bytes of elements, exceeding {max_size} bytes");
+    }
+
+    let mut data = Vec::new();
+    data.try_reserve_exact(len)
+        .map_err(|_| anyhow!("array too large: {shape:?}"))?;
+    data.resize(len, A::default());
+    ::ndarray::ArrayD::from_shape_vec(shape, data).map_err(Into::into)
+}
+
+fn to_usizes(values: &[u64]) -> Result<Vec<usize>> {
+    values
+        .iter()
+        .map(|&value| usize::try_from(value).map_err(Into::into))
+        .collect()
+}
+
+/// Pairs the indices with the values, stopping at any missing indices.
+fn coo_elements<'a, I, V>(
+    ndim: usize,
+    indices: &'a [I],
+    values: &'a [V],
+) -> impl Iterator<Item = (&'a [I], &'a V)> {
+    values
+        .iter()
+        .enumerate()
+        .map_while(move |(i, value)| Some((indices.get(i * ndim..(i + 1) * ndim)?, value)))
+}
+
+/// Slices the elements of the row, which is empty if the offsets are malformed.
+fn csr_row<'a, I, V>(
+    indptr: &'a [I],
+    indices: &'a [I],
+    values: &'a [V],
+    row: usize,
+    to_usize: fn(I) -> usize,
+) -> impl Iterator<Item = (usize, &'a V)>
+where
+    I: Copy,
+{
+    let offset = |row: usize| indptr.get(row).map(|&i| to_usize(i)).unwrap_or_default();
+    csr_range(indices, values, offset(row)..offset(row + 1), to_usize)
+}
+
+/// Iterates the elements of the rows given by the offsets, rather than by the shape,
+/// so that a malformed shape cannot make it run longer than the offsets.
+fn csr_elements<'a, I, V>(
+    indptr: &'a [I],
+    indices: &'a [I],
+    values: &'a [V],
+    to_usize: fn(I) -> usize,
+) -> impl Iterator<Item = (usize, usize, &'a V)>
+where
+    I: Copy,
+{
+    indptr.windows(2).enumerate().flat_map(move |(row, pair)| {
+        let range = to_usize(pair[0])..to_usize(pair[1]);
+        csr_range(indices, values, range, to_usize).map(move |(col, value)| (row, col, value))
+    })
+}
+
+/// Slices the elements in the range, which is empty if it is out of bounds.
+fn csr_range<'a, I, V>(
+    indices: &'a [I],
+    values: &'a [V],
+    range: ::core::ops::Range<usize>,
+    to_usize: fn(I) -> usize,
+) -> impl Iterator<Item = (usize, &'a V)>
+where
+    I: Copy,
+{
+    let indices = indices.get(range.clone()).unwrap_or_default();
+    let values = values.get(range).unwrap_or_default();
+    indices
+        .iter()
+        .zip(values)
+        .map(move |(&col, value)| (to_usize(col), value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coo_array() {
+        let tensor = Array(
+            ::ndarray::Array::from_shape_fn((3, 4, 5), |(i, j, k)| {
+                if (i + j * k) % 7 == 0 {
+                    (i * 100 + j * 10 + k) as i32
+                } else {
+                    0
+                }
+            })
+            .into_dyn()
+            .into_shared(),
+        );
+
+        let sparse = CooArray::from_dense(&tensor);
+        assert_eq!(sparse.shape(), &[3, 4, 5]);
+        assert_eq!(sparse.nnz(), tensor.iter().filter(|&&x| x != 0).count());
+        assert_eq!(sparse.to_dense::<IxDyn>().unwrap(), tensor);
+        assert!(sparse.to_dense::<Ix2>().is_err());
+
+        // the elements are sorted by their indices
+        let mut unsorted: Vec<_> = sparse
+            .iter()
+            .map(|(index, &value)| (index.iter().map(|&i| i as usize).collect(), value))
+            .collect();
+        unsorted.reverse();
+        assert_eq!(CooArray::try_new(&[3, 4, 5], unsorted).unwrap(), sparse);
+        assert!(CooArray::try_new(&[2, 2], [(vec![0, 2], 1)]).is_err());
+        assert!(CooArray::try_new(&[2, 2], [(vec![1, 1], 1), (vec![1, 1], 2)]).is_err());
+
+        // archive
+        let bytes = ::rkyv::to_bytes::<_, 4096>(&sparse).unwrap();
+        let archived = ::rkyv::check_archived_root::<CooArray<i32>>(&bytes).unwrap();
+        assert_eq!(archived, &sparse);
+        assert_eq!(archived.shape(), [3, 4, 5]);
+        assert_eq!(archived.nnz(), sparse.nnz());
+        assert!(archived.iter().map(|(index, _)| index).eq(sparse
+            .iter()
+            .map(|(index, _)| index)
+            .map(|index| index
+                .iter()
+                .map(|&i| Archived::<u64>::from(i))
+                .collect::<Vec<_>>())));
+        assert!(archived
+            .iter()
+            .map(|(_, value)| from_archived!(*value))
+            .eq(sparse.iter().map(|(_, &value)| value)));
+
+        // deserialize
+        let deserialized: CooArray<i32> = crate::signed::from_bytes(&bytes).unwrap();
+        assert_eq!(deserialized, sparse);
+    }
+
+    #[test]
+    fn test_csr_array() {
+        let matrix = Array(
+            ::ndarray::arr2(&[
+                [0.0f64, 1.5, 0.0, 0.0],
+                [0.0, 0.0, 0.0, 0.0],
+                [2.0, 0.0, 0.0, -3.25],
+            ])
+            .into_shared(),
+        );
+
+        let sparse = CsrArray::from_dense(&matrix);
+        assert_eq!(sparse.shape(), &[3, 4]);
+        assert_eq!(sparse.nnz(), 3);
+        assert_eq!(sparse.indptr, [0, 1, 1, 3]);
+        assert_eq!(sparse.to_dense().unwrap(), matrix);
+        assert!(sparse.row(1).next().is_none());
+        assert!(sparse.row(2).eq([(0, &2.0), (3, &-3.25)]));
+        assert!(sparse.row(3).next().is_none());
+
+        // conversions between the sparse formats
+        let coo = CooArray::from(sparse.clone());
+        assert_eq!(coo, CooArray::from_dense(&matrix));
+        assert_eq!(CsrArray::try_from(coo).unwrap(), sparse);
+        let tensor = Array(matrix.0.clone().insert_axis(::ndarray::Axis(0)).into_dyn());
+        assert!(CsrArray::try_from(CooArray::from_dense(&tensor)).is_err());
+
+        // malformed offsets
+        let mut malformed = sparse.clone();
+        malformed.indptr = vec![0, 2, 1, 3];
+        assert!(malformed.to_dense().is_err());
+
+        // archive
+        let bytes = ::rkyv::to_bytes::<_, 4096>(&sparse).unwrap();
+        let archived = ::rkyv::check_archived_root::<CsrArray<f64>>(&bytes).unwrap();
+        assert_eq!(archived, &sparse);
+        assert_eq!(archived.shape(), [3, 4]);
+        assert!(archived
+            .iter()
+            .map(|(row, col, value)| (row, col, from_archived!(*value)))
+            .eq(sparse.iter().map(|(row, col, &value)| (row, col, value))));
+
+        // deserialize
+        let deserialized: CsrArray<f64> = crate::signed::from_bytes(&bytes).unwrap();
+        assert_eq!(deserialized.to_dense().unwrap(), matrix);
+    }
+
+    #[test]
+    fn test_sparse_malformed_shape() {
+        // the rows are iterated by the offsets, not by the shape
+        let sparse = CsrArray {
+            shape: [u64::MAX, 1],
+            indptr: vec![0, 1],
+            indices: vec![0],
+            values: vec![1u8],
+        };
+        assert!(sparse.iter().eq([(0, 0, &1)]));
+        assert!(sparse.to_dense().is_err());
+
+        let bytes = ::rkyv::to_bytes::<_, 4096>(&sparse).unwrap();
+        let archived = ::rkyv::check_archived_root::<CsrArray<u8>>(&bytes).unwrap();
+        assert_eq!(archived.iter().count(), 1);
+
+        // the dense arrays would be too large
+        let sparse = CsrArray {
+            shape: [1, 1 << 62],
+            indptr: vec![0, 1],
+            indices: vec![0],
+            values: vec![1u32],
+        };
+        assert!(sparse.to_dense().is_err());
+
+        let sparse = CooArray::try_new(&[1 << 40, 1 << 40], [(vec![0, 0], 1u8)]).unwrap();
+        assert!(sparse.to_dense::<IxDyn>().is_err());
+
+        // the dense arrays are limited to MAX_DECODED_SIZE bytes by default
+        let sparse = CooArray::try_new(&[1, 1 << 33], [(vec![0, 0], 1u8)]).unwrap();
+        assert!(sparse.to_dense::<IxDyn>().is_err());
+        let sparse = CsrArray {
+            shape: [1, 1 << 33],
+            indptr: vec![0, 1],
+            indices: vec![0],
+            values: vec![1u8],
+        };
+        assert!(sparse.to_dense().is_err());
+
+        let sparse = CsrArray {
+            shape: [2, 3],
+            indptr: vec![0, 1, 1],
+            indices: vec![2],
+            values: vec![1u32],
+        };
+        assert!(sparse.to_dense_with_limit(23).is_err());
+        assert_eq!(sparse.to_dense_with_limit(24).unwrap().0[(0, 2)], 1);
+    }
+}
@@ -0,0 +1,942 @@
+//! Conversions between records of dynamic values and Apache Arrow `RecordBatch`es.
+//!
+//! The Arrow types are derived from the `ValueType`s:
+//!
+//! - numbers, `Bool`, `String` and `Bytes` map to their Arrow counterparts
+//! - `I128` and `U128` map to 16-byte big-endian fixed-size binaries
+//! - `DateTime` maps to UTC timestamps in nanoseconds
+//! - `Uuid` and `Hash` map to 16-byte and 32-byte fixed-size binaries
+//! - `UnitInterval` maps to `Float64`
+//! - `Text` maps to a struct of `msg` and `lang`
+//! - `List`, `Map` and `Record` map to lists, maps and structs of the element types
+//! - `Dyn`, `Array`, `Tensor` and `TimeSeries` map to strings of typed elements, such as `i32:42`
+//!
+//! Each Arrow field carries its `ValueType` in the metadata, so that the values are converted back
+//! losslessly. Fields without it, such as the ones written by other tools, are converted into
+//! the closest `ValueType`s. `None`s and missing fields are converted into nulls, and vice versa.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use anyhow::{anyhow, bail, Result};
+use arrow::{
+    array::{
+        Array, ArrayData, ArrayRef, BinaryArray, BooleanArray, BooleanBufferBuilder,
+        FixedSizeBinaryArray, Float32Array, Float64Array, Int16Array, Int32Array, Int64Array,
+        Int8Array, ListArray, MapArray, NullArray, StringArray, StructArray,
+        TimestampNanosecondArray, UInt16Array, UInt32Array, UInt64Array, UInt8Array,
+    },
+    buffer::Buffer,
+    compute::cast,
+    datatypes::{DataType, Field as ArrowField, Schema, TimeUnit},
+    record_batch::{RecordBatch, RecordBatchOptions},
+};
+
+use super::{
+    chrono::DateTime, hash::Hash, text::Text, unit_interval::UnitInterval, uuid::Uuid, Field,
+    FieldType, Value, ValueType,
+};
+use crate::{
+    account::AccountRef, metadata::Metadata, schema::SchemaHash, signature::PublicKey,
+    value::nonce::Nonce,
+};
+
+/// The key of the field metadata which holds the `ValueType`.
+pub const VALUE_TYPE_KEY: &str = "ipi.type";
+
+/// The columns of `Metadata`, which precede the fields of the data.
+pub const METADATA_COLUMNS: &[&str] = &["nonce", "created_date", "expiration_date", "guarantor"];
+
+/// Derives the Arrow schema of the records of the given type.
+pub fn schema(ty: &ValueType) -> Result<Schema> {
+    Ok(Schema::new(
+        record_fields(ty)?
+            .iter()
+            .map(|field| arrow_field(&field.name, &field.ty))
+            .collect(),
+    ))
+}
+
+/// Converts the records into a `RecordBatch`, casting their fields into the given record type.
+///
+/// The records may be either `Record`s or `Map`s.
+pub fn to_record_batch(ty: &ValueType, records: &[Value]) -> Result<RecordBatch> {
+    let rows: Vec<_> = records.iter().map(Some).collect();
+    let columns = build_fields(record_fields(ty)?, &rows)?;
+    new_record_batch(schema(ty)?, columns, records.len())
+}
+
+/// Converts the rows of the `RecordBatch` back into records.
+pub fn from_record_batch(batch: &RecordBatch) -> Result<Vec<Value>> {
+    let schema = batch.schema();
+    read_fields(schema.fields(), batch.columns(), batch.num_rows())
+}
+
+/// Converts the signed metadata into a `RecordBatch`.
+///
+/// The metadata columns, `nonce`, `created_date`, `expiration_date` and `guarantor`,
+/// are followed by the fields of the data, cast into the given record type.
+pub fn metadata_to_record_batch<'a>(
+    ty: &ValueType,
+    items: impl IntoIterator<Item = &'a Metadata<Value>>,
+) -> Result<RecordBatch> {
+    let items: Vec<_> = items.into_iter().collect();
+    let fields = record_fields(ty)?;
+    if let Some(field) = fields
+        .iter()
+        .find(|field| METADATA_COLUMNS.contains(&field.name.as_str()))
+    {
+        bail!(
+            "record field conflicts with the metadata column: {:?}",
+            &field.name
+        );
+    }
+
+    let mut schema_fields = vec![
+        typed_field(METADATA_COLUMNS[0], &ValueType::Uuid, false),
+        typed_field(METADATA_COLUMNS[1], &ValueType::DateTime, false),
+        typed_field(METADATA_COLUMNS[2], &ValueType::DateTime, true),
+        ArrowField::new(METADATA_COLUMNS[3], DataType::FixedSizeBinary(32), false),
+    ];
+    schema_fields.extend(
+        fields
+            .iter()
+            .map(|field| arrow_field(&field.name, &field.ty)),
+    );
+
+    let nonces: Vec<_> = items.iter().map(|item| Value::Uuid(item.nonce.0)).collect();
+    let created_dates: Vec<_> = items
+        .iter()
+        .map(|item| Value::DateTime(item.created_date))
+        .collect();
+    let expiration_dates: Vec<_> = items
+        .iter()
+        .map(|item| item.expiration_date.map(Value::DateTime).into())
+        .collect();
+    let guarantors = items
+        .iter()
+        .map(|item| Some(item.guarantor.as_bytes().to_vec()));
+
+    let mut columns = vec![
+        build(
+            &ValueType::Uuid,
+            &nonces.iter().map(Some).collect::<Vec<_>>(),
+        )?,
+        build(
+            &ValueType::DateTime,
+            &created_dates.iter().map(Some).collect::<Vec<_>>(),
+        )?,
+        build(
+            &ValueType::DateTime,
+            &expiration_dates.iter().map(Some).collect::<Vec<_>>(),
+        )?,
+        fixed_size_binary(32, guarantors)?,
+    ];
+    let rows: Vec<_> = items.iter().map(|item| Some(&item.data)).collect();
+    columns.extend(build_fields(fields, &rows)?);
+
+    new_record_batch(Schema::new(schema_fields), columns, items.len())
+}
+
+/// Converts the rows of the `RecordBatch` back into metadata, without the signatures.
+pub fn metadata_from_record_batch(batch: &RecordBatch) -> Result<Vec<Metadata<Value>>> {
+    let schema = batch.schema();
+    let fields = schema.fields();
+    if fields.len() < METADATA_COLUMNS.len()
+        || fields
+            .iter()
+            .zip(METADATA_COLUMNS)
+            .any(|(field, name)| field.name() != name)
+    {
+        bail!("missing metadata columns: {METADATA_COLUMNS:?}");
+    }
+
+    let rows = batch.num_rows();
+    let columns = batch.columns();
+    let nonces = read(&ValueType::Uuid, &columns[0])?;
+    let created_dates = read(&ValueType::DateTime, &columns[1])?;
+    let expiration_dates = read(&ValueType::DateTime, &columns[2])?;
+    let guarantors = downcast::<FixedSizeBinaryArray>(&columns[3])?;
+    let data = read_fields(
+        &fields[METADATA_COLUMNS.len()..],
+        &columns[METADATA_COLUMNS.len()..],
+        rows,
+    )?;
+
+    nonces
+        .into_iter()
+        .zip(created_dates)
+        .zip(expiration_dates)
+        .zip(data)
+        .enumerate()
+        .map(
+            |(index, (((nonce, created_date), expiration_date), data))| {
+                if guarantors.is_null(index) {
+                    bail!("missing guarantor: row {index}");
+                }
+                Ok(Metadata {
+                    nonce: Nonce(nonce.try_into()?),
+                    created_date: created_date.try_into()?,
+                    expiration_date: match expiration_date {
+                        Value::None => None,
+                        value => Some(value.try_into()?),
+                    },
+                    guarantor: AccountRef {
+                        public_key: PublicKey(::ed25519_dalek::PublicKey::from_bytes(
+                            guarantors.value(index),
+                        )?),
+                    },
+                    schema: SchemaHash::new(),
+                    data,
+                })
+            },
+        )
+        .collect()
+}
+
+fn record_fields(ty: &ValueType) -> Result<&[FieldType]> {
+    match ty {
+        ValueType::Record(fields) => Ok(fields),
+        ty => bail!("expected a record type, found {ty}"),
+    }
+}
+
+fn new_record_batch(schema: Schema, columns: Vec<ArrayRef>, rows: usize) -> Result<RecordBatch> {
+    let mut options = RecordBatchOptions::default();
+    options.row_count = Some(rows);
+    RecordBatch::try_new_with_options(Arc::new(schema), columns, &options).map_err(Into::into)
+}
+
+fn arrow_field(name: &str, ty: &ValueType) -> ArrowField {
+    typed_field(name, ty, true)
+}
+
+fn typed_field(name: &str, ty: &ValueType, nullable: bool) -> ArrowField {
+    ArrowField::new(name, data_type(ty), nullable)
+        .with_metadata(Some([(VALUE_TYPE_KEY.to_string(), ty.to_string())].into()))
+}
+
+fn data_type(ty: &ValueType) -> DataType {
+    match ty {
+        ValueType::None => DataType::Null,
+        ValueType::Bool => DataType::Boolean,
+        ValueType::I8 => DataType::Int8,
+        ValueType::I16 => DataType::Int16,
+        ValueType::I32 => DataType::Int32,
+        ValueType::I64 => DataType::Int64,
+        ValueType::U8 => DataType::UInt8,
+        ValueType::U16 => DataType::UInt16,
+        ValueType::U32 => DataType::UInt32,
+        ValueType::U64 => DataType::UInt64,
+        ValueType::F32 => DataType::Float32,
+        ValueType::F64 | ValueType::UnitInterval => DataType::Float64,
+        ValueType::Bytes => DataType::Binary,
+        ValueType::String
+        | ValueType::Dyn
+        | ValueType::Array
+        | ValueType::Tensor(_)
+        | ValueType::TimeSeries => DataType::Utf8,
+        ValueType::Text => DataType::Struct(vec![
+            ArrowField::new("msg", DataType::Utf8, false),
+            ArrowField::new("lang", DataType::Utf8, false),
+        ]),
+        ValueType::I128 | ValueType::U128 | ValueType::Uuid => DataType::FixedSizeBinary(16),
+        ValueType::DateTime => DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into())),
+        ValueType::Hash => DataType::FixedSizeBinary(32),
+        ValueType::List(ty) => DataType::List(Box::new(arrow_field("item", ty))),
+        ValueType::Map(ty) => DataType::Map(Box::new(map_entries(ty)), false),
+        ValueType::Record(fields) => DataType::Struct(
+            fields
+                .iter()
+                .map(|field| arrow_field(&field.name, &field.ty))
+                .collect(),
+        ),
+    }
+}
+
+fn map_entries(ty: &ValueType) -> ArrowField {
+    ArrowField::new(
+        "entries",
+        DataType::Struct(vec![
+            ArrowField::new("key", DataType::Utf8, false),
+            arrow_field("value", ty),
+        ]),
+        false,
+    )
+}
+
+/// Returns the `ValueType` stored in the metadata, or the closest one of the Arrow type.
+fn value_type(field: &ArrowField) -> Result<ValueType> {
+    if let Some(ty) = field
+        .metadata()
+        .as_ref()
+        .and_then(|metadata| metadata.get(VALUE_TYPE_KEY))
+    {
+        return ty.parse();
+    }
+
+    Ok(match field.data_type() {
+        DataType::Null => ValueType::None,
+        DataType::Boolean => ValueType::Bool,
+        DataType::Int8 => ValueType::I8,
+        DataType::Int16 => ValueType::I16,
+        DataType::Int32 => ValueType::I32,
+        DataType::Int64 => ValueType::I64,
+        DataType::UInt8 => ValueType::U8,
+        DataType::UInt16 => ValueType::U16,
+        DataType::UInt32 => ValueType::U32,
+        DataType::UInt64 => ValueType::U64,
+        DataType::Float16 | DataType::Float32 => ValueType::F32,
+        DataType::Float64 => ValueType::F64,
+        DataType::Utf8 | DataType::LargeUtf8 => ValueType::String,
+        DataType::Binary | DataType::LargeBinary | DataType::FixedSizeBinary(_) => ValueType::Bytes,
+        DataType::Timestamp(_, _) | DataType::Date32 | DataType::Date64 => ValueType::DateTime,
+        DataType::List(field) => ValueType::List(Box::new(value_type(field)?)),
+        DataType::Map(entries, _) => match entries.data_type() {
+            DataType::Struct(fields) if fields.len() == 2 => {
+                ValueType::Map(Box::new(value_type(&fields[1])?))
+            }
+            ty => bail!("unsupported Arrow map entries: {ty:?}"),
+        },
+        DataType::Struct(fields) => ValueType::Record(
+            fields
+                .iter()
+                .map(|field| {
+                    Ok(FieldType {
+                        name: field.name().clone(),
+                        ty: value_type(field)?,
+                    })
+                })
+                .collect::<Result<_>>()?,
+        ),
+        ty => bail!("unsupported Arrow type: {ty:?}"),
+    })
+}
+
+/// Builds the columns of the fields of the records, where `None`s are null records.
+fn build_fields(fields: &[FieldType], rows: &[Option<&Value>]) -> Result<Vec<ArrayRef>> {
+    fields
+        .iter()
+        .map(|field| {
+            let values: Vec<_> = rows
+                .iter()
+                .map(|row| match row {
+                    Some(Value::Record(values)) => Ok(values
+                        .iter()
+                        .find(|value| value.name == field.name)
+                        .map(|value| &value.value)),
+                    Some(Value::Map(values)) => Ok(values.get(&field.name)),
+                    Some(Value::None) | None => Ok(None),
+                    Some(row) => bail!("expected a record, found {:?}", row.value_type()),
+                })
+                .collect::<Result<_>>()?;
+            build(&field.ty, &values)
+                .map_err(|error| anyhow!("failed to convert the field {:?}: {error}", &field.name))
+        })
+        .collect()
+}
+
+/// Builds the column of the values, where `None`s are nulls.
+fn build(ty: &ValueType, values: &[Option<&Value>]) -> Result<ArrayRef> {
+    /// Converts the values with the `TryFrom` impls, which cast the other types losslessly.
+    fn convert<T>(values: &[Option<&Value>]) -> Result<Vec<Option<T>>>
+    where
+        T: TryFrom<Value, Error = ::anyhow::Error>,
+    {
+        values
+            .iter()
+            .map(|value| match value {
+                Some(Value::None) | None => Ok(None),
+                Some(value) => T::try_from((*value).clone()).map(Some),
+            })
+            .collect()
+    }
+
+    let values: Vec<_> = values
+        .iter()
+        .map(|value| value.filter(|value| !matches!(value, Value::None)))
+        .collect();
+
+    Ok(match ty {
+        ValueType::None => Arc::new(NullArray::new(values.len())),
+        ValueType::Bool => Arc::new(BooleanArray::from(convert::<bool>(&values)?)),
+        ValueType::I8 => Arc::new(Int8Array::from(convert::<i8>(&values)?)),
+        ValueType::I16 => Arc::new(Int16Array::from(convert::<i16>(&values)?)),
+        ValueType::I32 => Arc::new(Int32Array::from(convert::<i32>(&values)?)),
+        ValueType::I64 => Arc::new(Int64Array::from(convert::<i64>(&values)?)),
+        ValueType::U8 => Arc::new(UInt8Array::from(convert::<u8>(&values)?)),
+        ValueType::U16 => Arc::new(UInt16Array::from(convert::<u16>(&values)?)),
+        ValueType::U32 => Arc::new(UInt32Array::from(convert::<u32>(&values)?)),
+        ValueType::U64 => Arc::new(UInt64Array::from(convert::<u64>(&values)?)),
+        ValueType::F32 => Arc::new(Float32Array::from(convert::<f32>(&values)?)),
+        ValueType::F64 => Arc::new(Float64Array::from(convert::<f64>(&values)?)),
+        ValueType::UnitInterval => Arc::new(Float64Array::from(
+            convert::<UnitInterval>(&values)?
+                .into_iter()
+                .map(|value| value.map(|value| value.to_num()))
+                .collect::<Vec<Option<f64>>>(),
+        )),
+        ValueType::Bytes => Arc::new(
+            convert::<Vec<u8>>(&values)?
+                .iter()
+                .map(Option::as_deref)
+                .collect::<BinaryArray>(),
+        ),
+        ValueType::String => Arc::new(
+            convert::<String>(&values)?
+                .iter()
+                .map(Option::as_deref)
+                .collect::<StringArray>(),
+        ),
+        ValueType::Dyn | ValueType::Array | ValueType::Tensor(_) | ValueType::TimeSeries => {
+            Arc::new(
+                values
+                    .iter()
+                    .map(|value| {
+                        value
+                            .map(|value| Ok(format!("{}:{value}", value.value_type())))
+                            .transpose()
+                    })
+                    .collect::<Result<Vec<Option<String>>>>()?
+                    .iter()
+                    .map(Option::as_deref)
+                    .collect::<StringArray>(),
+            )
+        }
+        ValueType::Text => {
+            let texts = convert::<Text>(&values)?;
+            let msgs: Vec<_> = texts
+                .iter()
+                .map(|text| Some(text.as_ref().map(|text| text.msg.as_str()).unwrap_or("")))
+                .collect();
+            let langs: Vec<_> = texts
+                .iter()
+                .map(|text| Some(text.as_ref().map(|text| text.lang.as_str()).unwrap_or("")))
+                .collect();
+            let children = vec![
+                StringArray::from(msgs).data().clone(),
+                StringArray::from(langs).data().clone(),
+            ];
+            new_array(data_type(ty), &values, vec![], children)?
+        }
+        ValueType::I128 => fixed_size_binary(
+            16,
+            convert::<i128>(&values)?
+                .into_iter()
+                .map(|value| value.map(|value| value.to_be_bytes())),
+        )?,
+        ValueType::U128 => fixed_size_binary(
+            16,
+            convert::<u128>(&values)?
+                .into_iter()
+                .map(|value| value.map(|value| value.to_be_bytes())),
+        )?,
+        ValueType::DateTime => Arc::new(TimestampNanosecondArray::from_opt_vec(
+            convert::<DateTime>(&values)?
+                .into_iter()
+                .map(|value| value.map(|value| to_nanos(&value)).transpose())
+                .collect::<Result<_>>()?,
+            Some("UTC".into()),
+        )),
+        ValueType::Uuid => fixed_size_binary(
+            16,
+            convert::<Uuid>(&values)?
+                .into_iter()
+                .map(|value| value.map(|value| *value.as_bytes())),
+        )?,
+        ValueType::Hash => fixed_size_binary(
+            32,
+            convert::<Hash>(&values)?
+                .into_iter()
+                .map(|value| value.map(|value| value.0)),
+        )?,
+        ValueType::List(item) => {
+            let items = convert::<Vec<Value>>(&values)?;
+            let mut offsets = vec![0i32];
+            let mut children = Vec::new();
+            for item in &items {
+                children.extend(item.iter().flatten().map(Some));
+                offsets.push(children.len().try_into()?);
+            }
+            let child = build(item, &children)?;
+            new_array(
+                data_type(ty),
+                &values,
+                vec![Buffer::from_slice_ref(&offsets)],
+                vec![child.data().clone()],
+            )?
+        }
+        ValueType::Map(value_ty) => {
+            let maps = convert::<BTreeMap<String, Value>>(&values)?;
+            let mut offsets = vec![0i32];
+            let mut keys = Vec::new();
+            let mut children = Vec::new();
+            for map in &maps {
+                for (key, value) in map.iter().flatten() {
+                    keys.push(Some(key.as_str()));
+                    children.push(Some(value));
+                }
+                offsets.push(keys.len().try_into()?);
+            }
+            let entries = ArrayData::builder(map_entries(value_ty).data_type().clone())
+                .len(keys.len())
+                .child_data(vec![
+                    StringArray::from(keys).data().clone(),
+                    build(value_ty, &children)?.data().clone(),
+                ])
+                .build()?;
+            new_array(
+                data_type(ty),
+                &values,
+                vec![Buffer::from_slice_ref(&offsets)],
+                vec![entries],
+            )?
+        }
+        ValueType::Record(fields) => {
+            let children = build_fields(fields, &values)?
+                .into_iter()
+                .map(|child| child.data().clone())
+                .collect();
+            new_array(data_type(ty), &values, vec![], children)?
+        }
+    })
+}
+
+/// Creates a nested array, where `None`s are nulls.
+fn new_array(
+    data_type: DataType,
+    values: &[Option<&Value>],
+    buffers: Vec<Buffer>,
+    children: Vec<ArrayData>,
+) -> Result<ArrayRef> {
+    let mut nulls = BooleanBufferBuilder::new(values.len());
+    for value in values {
+        nulls.append(value.is_some());
+    }
+    let data = ArrayData::builder(data_type)
+        .len(values.len())
+        .null_bit_buffer(Some(nulls.finish()))
+        .buffers(buffers)
+        .child_data(children)
+        .build()?;
+    Ok(::arrow::array::make_array(data))
+}
+
+fn fixed_size_binary<T>(size: usize, values: impl Iterator<Item = Option<T>>) -> Result<ArrayRef>
+where
+    T: AsRef<[u8]>,
+{
+    let mut len = 0;
+    let mut buffer = Vec::new();
+    let mut nulls = BooleanBufferBuilder::new(0);
+    for value in values {
+        match &value {
+            Some(value) if value.as_ref().len() == size => {
+                buffer.extend_from_slice(value.as_ref());
+            }
+            Some(value) => bail!("expected {size} bytes, found {}", value.as_ref().len()),
+            None => buffer.resize(buffer.len() + size, 0),
+        }
+        nulls.append(value.is_some());
+        len += 1;
+    }
+    let data = ArrayData::builder(DataType::FixedSizeBinary(size.try_into()?))
+        .len(len)
+        .null_bit_buffer(Some(nulls.finish()))
+        .add_buffer(Buffer::from_slice_ref(&buffer))
+        .build()?;
+    Ok(Arc::new(FixedSizeBinaryArray::from(data)))
+}
+
+fn to_nanos(value: &DateTime) -> Result<i64> {
+    value
+        .timestamp()
+        .checked_mul(1_000_000_000)
+        .and_then(|nanos| nanos.checked_add(value.timestamp_subsec_nanos().into()))
+        .ok_or_else(|| anyhow!("timestamp out of the range of nanoseconds: {value:?}"))
+}
+
+fn from_nanos(nanos: i64) -> Result<DateTime> {
+    let secs = nanos.div_euclid(1_000_000_000);
+    let nanos = nanos.rem_euclid(1_000_000_000) as u32;
+    ::chrono::NaiveDateTime::from_timestamp_opt(secs, nanos)
+        .map(|datetime| DateTime(::chrono::DateTime::from_utc(datetime, ::chrono::Utc)))
+        .ok_or_else(|| anyhow!("timestamp out of range: {secs}s {nanos}ns"))
+}
+
+/// Reads the columns of the fields into records.
+fn read_fields(fields: &[ArrowField], columns: &[ArrayRef], rows: usize) -> Result<Vec<Value>> {
+    let mut records: Vec<_> = (0..rows)
+        .map(|_| Vec::with_capacity(fields.len()))
+        .collect();
+    for (field, column) in fields.iter().zip(columns) {
+        let values = value_type(field)
+            .and_then(|ty| read(&ty, column))
+            .map_err(|error| anyhow!("failed to convert the field {:?}: {error}", field.name()))?;
+        for (record, value) in records.iter_mut().zip(values) {
+            record.push(Field {
+                name: field.name().clone(),
+                value,
+            });
+        }
+    }
+    Ok(records.into_iter().map(Value::Record).collect())
+}
+
+/// Reads the column into values, where nulls are `None`s.
+fn read(ty: &ValueType, array: &ArrayRef) -> Result<Vec<Value>> {
+    fn collect<A: Array>(array: &A, f: impl Fn(usize) -> Result<Value>) -> Result<Vec<Value>> {
+        (0..array.len())
+            .map(|index| {
+                if array.is_null(index) {
+                    Ok(Value::None)
+                } else {
+                    f(index)
+                }
+            })
+            .collect()
+    }
+
+    // convert the foreign types, such as `LargeUtf8` or other time units
+    let expected = data_type(ty);
+    let array = match ty {
+        ValueType::List(_) | ValueType::Map(_) | ValueType::Record(_) | ValueType::Text => {
+            array.clone()
+        }
+        _ if array.data_type() == &expected => array.clone(),
+        ValueType::Bytes if matches!(array.data_type(), DataType::FixedSizeBinary(_)) => {
+            let array = downcast::<FixedSizeBinaryArray>(array)?;
+            return collect(array, |index| Ok(Value::Bytes(array.value(index).to_vec())));
+        }
+        _ => cast(array, &expected)?,
+    };
+
+    match ty {
+        ValueType::None => Ok(vec![Value::None; array.len()]),
+        ValueType::Bool => {
+            let array = downcast::<BooleanArray>(&array)?;
+            collect(array, |index| Ok(Value::Bool(array.value(index))))
+        }
+        ValueType::I8 => {
+            let array = downcast::<Int8Array>(&array)?;
+            collect(array, |index| Ok(Value::I8(array.value(index))))
+        }
+        ValueType::I16 => {
+            let array = downcast::<Int16Array>(&array)?;
+            collect(array, |index| Ok(Value::I16(array.value(index))))
+        }
+        ValueType::I32 => {
+            let array = downcast::<Int32Array>(&array)?;
+            collect(array, |index| Ok(Value::I32(array.value(index))))
+        }
+        ValueType::I64 => {
+            let array = downcast::<Int64Array>(&array)?;
+            collect(array, |index| Ok(Value::I64(array.value(index))))
+        }
+        ValueType::U8 => {
+            let array = downcast::<UInt8Array>(&array)?;
+            collect(array, |index| Ok(Value::U8(array.value(index))))
+        }
+        ValueType::U16 => {
+            let array = downcast::<UInt16Array>(&array)?;
+            collect(array, |index| Ok(Value::U16(array.value(index))))
+        }
+        ValueType::U32 => {
+            let array = downcast::<UInt32Array>(&array)?;
+            collect(array, |index| Ok(Value::U32(array.value(index))))
+        }
+        ValueType::U64 => {
+            let array = downcast::<UInt64Array>(&array)?;
+            collect(array, |index| Ok(Value::U64(array.value(index))))
+        }
+        ValueType::F32 => {
+            let array = downcast::<Float32Array>(&array)?;
+            collect(array, |index| Ok(Value::F32(array.value(index))))
+        }
+        ValueType::F64 => {
+            let array = downcast::<Float64Array>(&array)?;
+            collect(array, |index| Ok(Value::F64(array.value(index))))
+        }
+        ValueType::UnitInterval => {
+            let array = downcast::<Float64Array>(&array)?;
+            collect(array, |index| {
+                Value::F64(array.value(index)).cast(ValueType::UnitInterval)
+            })
+        }
+        ValueType::Bytes => {
+            let array = downcast::<BinaryArray>(&array)?;
+            collect(array, |index| Ok(Value::Bytes(array.value(index).to_vec())))
+        }
+        ValueType::String => {
+            let array = downcast::<StringArray>(&array)?;
+            collect(array, |index| Ok(Value::String(array.value(index).into())))
+        }
+        ValueType::Dyn | ValueType::Array | ValueType::Tensor(_) | ValueType::TimeSeries => {
+            let array = downcast::<StringArray>(&array)?;
+            collect(array, |index| {
+                Value::parse(array.value(index), ValueType::Dyn)?.cast(ty.clone())
+            })
+        }
+        ValueType::Text => {
+            let array = downcast::<StructArray>(&array)?;
+            let msgs = downcast::<StringArray>(array.column(0))?;
+            let langs = downcast::<StringArray>(array.column(1))?;
+            collect(array, |index| {
+                Ok(Value::Text(Text {
+                    msg: msgs.value(index).into(),
+                    lang: langs.value(index).parse()?,
+                }))
+            })
+        }
+        ValueType::I128 => {
+            let array = downcast::<FixedSizeBinaryArray>(&array)?;
+            collect(array, |index| {
+                Ok(Value::I128(i128::from_be_bytes(
+                    array.value(index).try_into()?,
+                )))
+            })
+        }
+        ValueType::U128 => {
+            let array = downcast::<FixedSizeBinaryArray>(&array)?;
+            collect(array, |index| {
+                Ok(Value::U128(u128::from_be_bytes(
+                    array.value(index).try_into()?,
+                )))
+            })
+        }
+        ValueType::DateTime => {
+            let array = downcast::<TimestampNanosecondArray>(&array)?;
+            collect(array, |index| {
+                from_nanos(array.value(index)).map(Value::DateTime)
+            })
+        }
+        ValueType::Uuid => {
+            let array = downcast::<FixedSizeBinaryArray>(&array)?;
+            collect(array, |index| {
+                Value::Bytes(array.value(index).to_vec()).cast(ValueType::Uuid)
+            })
+        }
+        ValueType::Hash => {
+            let array = downcast::<FixedSizeBinaryArray>(&array)?;
+            collect(array, |index| {
+                Value::Bytes(array.value(index).to_vec()).cast(ValueType::Hash)
+            })
+        }
+        ValueType::List(item) => {
+            let array = downcast::<ListArray>(&array)?;
+            let children = read(item, &array.values())?;
+            let offsets = array.value_offsets();
+            collect(array, |index| {
+                let (start, end) = (offsets[index] as usize, offsets[index + 1] as usize);
+                Ok(Value::List(children[start..end].to_vec()))
+            })
+        }
+        ValueType::Map(value_ty) => {
+            let array = downcast::<MapArray>(&array)?;
+            let keys = read(&ValueType::String, &array.keys())?;
+            let children = read(value_ty, &array.values())?;
+            let offsets = array.value_offsets();
+            collect(array, |index| {
+                let (start, end) = (offsets[index] as usize, offsets[index + 1] as usize);
+                keys[start..end]
+                    .iter()
+                    .zip(&children[start..end])
+                    .map(|(key, value)| match key {
+                        Value::String(key) => Ok((key.clone(), value.clone())),
+                        _ => bail!("map keys should be strings"),
+                    })
+                    .collect::<Result<_>>()
+                    .map(Value::Map)
+            })
+        }
+        ValueType::Record(_) => {
+            let array = downcast::<StructArray>(&array)?;
+            let fields = match array.data_type() {
+                DataType::Struct(fields) => fields,
+                _ => unreachable!("struct arrays should have struct types"),
+            };
+            let records = read_fields(fields, &array.columns_ref(), array.len())?;
+            records
+                .into_iter()
+                .enumerate()
+                .map(|(index, record)| {
+                    if array.is_null(index) {
+                        Ok(Value::None)
+                    } else {
+                        Ok(record)
+                    }
+                })
+                .collect()
+        }
+    }
+}
+
+fn downcast<A: Array + 'static>(array: &ArrayRef) -> Result<&A> {
+    array.as_any().downcast_ref().ok_or_else(|| {
+        anyhow!(
+            "unexpected Arrow type: {:?}, expected {}",
+            array.data_type(),
+            ::core::any::type_name::<A>(),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::account::Account;
+
+    use super::*;
+
+    fn record(fields: Vec<(&str, Value)>) -> Value {
+        Value::Record(
+            fields
+                .into_iter()
+                .map(|(name, value)| Field {
+                    name: name.into(),
+                    value,
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_record_batch() {
+        let ty: ValueType =
+            "record{id: u64, big: i128, name: string, text: text, tags: list<string>, \
+            scores: map<f32>, owner: record{uuid: uuid, hash: hash}, date: datetime, any: dyn}"
+                .parse()
+                .unwrap();
+        let records = vec![
+            record(vec![
+                ("id", Value::U64(1)),
+                ("big", Value::I128(-1 << 100)),
+                ("name", Value::String("hello".into())),
+                (
+                    "text",
+                    Value::Text(Text {
+                        msg: "안녕".into(),
+                        lang: "ko-KR".parse().unwrap(),
+                    }),
+                ),
+                (
+                    "tags",
+                    Value::List(vec![Value::String("a".into()), Value::None]),
+                ),
+                (
+                    "scores",
+                    Value::Map([("a".to_string(), Value::F32(0.5))].into()),
+                ),
+                (
+                    "owner",
+                    record(vec![
+                        ("uuid", Value::Uuid(Uuid::generate())),
+                        ("hash", Value::Hash(Hash([7; 32].into()))),
+                    ]),
+                ),
+                ("date", Value::DateTime(DateTime::now())),
+                ("any", Value::I32(42)),
+            ]),
+            record(vec![
+                ("id", Value::U64(2)),
+                ("big", Value::None),
+                ("name", Value::None),
+                ("text", Value::None),
+                ("tags", Value::None),
+                ("scores", Value::Map(Default::default())),
+                ("owner", Value::None),
+                ("date", Value::None),
+                ("any", Value::String("world".into())),
+            ]),
+        ];
+
+        let batch = to_record_batch(&ty, &records).unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(batch.schema().as_ref(), &schema(&ty).unwrap());
+        assert_eq!(from_record_batch(&batch).unwrap(), records);
+
+        // values are cast into the field types
+        let ty: ValueType = "record{id: u64, name: string}".parse().unwrap();
+        let batch =
+            to_record_batch(&ty, &[record(vec![("id", Value::String("3".into()))])]).unwrap();
+        assert_eq!(
+            from_record_batch(&batch).unwrap(),
+            [record(vec![("id", Value::U64(3)), ("name", Value::None)])],
+        );
+        assert!(to_record_batch(&ty, &[record(vec![("id", Value::I8(-1))])]).is_err());
+        assert!(to_record_batch(&ty, &[Value::U64(1)]).is_err());
+    }
+
+    #[test]
+    fn test_foreign_record_batch() {
+        // fields without the value types are inferred from the Arrow types
+        let schema = Schema::new(vec![
+            ArrowField::new("id", DataType::Int32, false),
+            ArrowField::new("name", DataType::LargeUtf8, true),
+        ]);
+        let batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2])),
+                Arc::new(::arrow::array::LargeStringArray::from(vec![
+                    Some("a"),
+                    None,
+                ])),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            from_record_batch(&batch).unwrap(),
+            [
+                record(vec![
+                    ("id", Value::I32(1)),
+                    ("name", Value::String("a".into()))
+                ]),
+                record(vec![("id", Value::I32(2)), ("name", Value::None)]),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_metadata_record_batch() {
+        let ty: ValueType = "record{title: string, score: u8}".parse().unwrap();
+        let account = Account::generate();
+        let items: Vec<_> = (0..3)
+            .map(|index| Metadata {
+                nonce: Nonce::generate(),
+                created_date: DateTime::now(),
+                expiration_date: if index == 0 {
+                    None
+                } else {
+                    Some(DateTime::now())
+                },
+                guarantor: account.account_ref(),
+                schema: SchemaHash::new(),
+                data: record(vec![
+                    ("title", Value::String(format!("item {index}"))),
+                    ("score", Value::U8(index)),
+                ]),
+            })
+            .collect();
+
+        let batch = metadata_to_record_batch(&ty, &items).unwrap();
+        let schema = batch.schema();
+        let names: Vec<_> = schema.fields().iter().map(ArrowField::name).collect();
+        assert_eq!(
+            names,
+            [
+                "nonce",
+                "created_date",
+                "expiration_date",
+                "guarantor",
+                "title",
+                "score",
+            ],
+        );
+        assert_eq!(metadata_from_record_batch(&batch).unwrap(), items);
+
+        // the data fields should not shadow the metadata columns
+        let ty: ValueType = "record{nonce: string}".parse().unwrap();
+        assert!(metadata_to_record_batch(&ty, &items).is_err());
+        assert!(metadata_from_record_batch(&batch.project(&[4, 5]).unwrap()).is_err());
+    }
+}
@@ -0,0 +1,567 @@
+use std::cmp::Ordering;
+
+use anyhow::{anyhow, bail, Result};
+use fixed::types::U0F32;
+use generic_array::GenericArray;
+use ndarray::IxDyn;
+use rkyv::from_archived;
+
+use super::{
+    array::Array, dyn_array::DynArray, hash::Hash, unit_interval::UnitInterval, uuid::Uuid,
+    ArchivedValue, Field, Value, ValueType,
+};
+
+impl Value {
+    /// Converts the value into the given type, failing if any information would be lost.
+    ///
+    /// - numbers (including `Bool` and `UnitInterval`) are converted only if the result is exact
+    /// - strings are parsed into numbers, dates (RFC 3339), uuids and hashes (base58), and back
+    /// - bytes are converted into hashes (32 bytes), uuids (16 bytes) and UTF-8 strings, and back
+    /// - lists, maps and records are cast element-wise, and maps into records of the same keys
+    /// - arrays and tensors are converted into tensors of the declared element type, and back
+    /// - any value can be cast into `Dyn`, leaving it as is
+    pub fn cast(&self, ty: ValueType) -> Result<Self> {
+        self.cast_ref(&ty)
+    }
+
+    fn cast_ref(&self, ty: &ValueType) -> Result<Self> {
+        let from = self.value_type();
+        if &from == ty || ty == &ValueType::Dyn {
+            return Ok(self.clone());
+        }
+
+        let value = match (self, ty) {
+            (Self::List(values), ValueType::List(ty)) => {
+                return values
+                    .iter()
+                    .map(|value| value.cast_ref(ty))
+                    .collect::<Result<_>>()
+                    .map(Self::List)
+            }
+            (Self::Map(values), ValueType::Map(ty)) => {
+                return values
+                    .iter()
+                    .map(|(key, value)| Ok((key.clone(), value.cast_ref(ty)?)))
+                    .collect::<Result<_>>()
+                    .map(Self::Map)
+            }
+            (Self::Record(fields), ValueType::Record(types)) => {
+                if fields.len() != types.len() {
+                    bail!(
+                        "record fields mismatching: expected {}, found {}",
+                        types.len(),
+                        fields.len(),
+                    );
+                }
+                return fields
+                    .iter()
+                    .zip(types)
+                    .map(|(field, ty)| {
+                        if field.name != ty.name {
+                            bail!(
+                                "record field mismatching: expected {:?}, found {:?}",
+                                &ty.name,
+                                &field.name,
+                            );
+                        }
+                        Ok(Field {
+                            name: field.name.clone(),
+                            value: field.value.cast_ref(&ty.ty)?,
+                        })
+                    })
+                    .collect::<Result<_>>()
+                    .map(Self::Record);
+            }
+            (Self::Map(values), ValueType::Record(types)) => {
+                if let Some(key) = values
+                    .keys()
+                    .find(|key| !types.iter().any(|ty| &&ty.name == key))
+                {
+                    bail!("unexpected record field: {key:?}");
+                }
+                return types
+                    .iter()
+                    .map(|ty| match values.get(&ty.name) {
+                        Some(value) => Ok(Field {
+                            name: ty.name.clone(),
+                            value: value.cast_ref(&ty.ty)?,
+                        }),
+                        None => bail!("missing record field: {:?}", &ty.name),
+                    })
+                    .collect::<Result<_>>()
+                    .map(Self::Record);
+            }
+
+            (Self::Tensor(_), ValueType::Tensor(ty)) if **ty == ValueType::Dyn => {
+                return Ok(self.clone())
+            }
+            (Self::Tensor(values), ValueType::Tensor(ty)) => {
+                return Self::cast_tensor(&values.to_values(), ty)
+            }
+            (Self::Array(values), ValueType::Tensor(ty)) => return Self::cast_tensor(values, ty),
+            (Self::Tensor(values), ValueType::Array) => return Ok(Self::Array(values.to_values())),
+
+            (Self::String(value), ty) => return Self::parse_str(value, ty),
+            (value, ValueType::String) => value.to_str().map(Self::String),
+
+            (Self::Bytes(value), ValueType::Hash) => {
+                GenericArray::from_exact_iter(value.iter().copied())
+                    .map(Hash)
+                    .map(Self::Hash)
+            }
+            (Self::Bytes(value), ValueType::Uuid) => ::uuid::Uuid::from_slice(value)
+                .ok()
+                .map(Uuid)
+                .map(Self::Uuid),
+            (Self::Hash(value), ValueType::Bytes) => Some(Self::Bytes(value.to_vec())),
+            (Self::Uuid(value), ValueType::Bytes) => Some(Self::Bytes(value.as_bytes().to_vec())),
+            (Self::Uuid(value), ValueType::U128) => Some(Self::U128(value.as_u128())),
+            (Self::U128(value), ValueType::Uuid) => {
+                Some(Self::Uuid(Uuid(::uuid::Uuid::from_u128(*value))))
+            }
+
+            (value, ty) => match value.to_number() {
+                Some(number) => {
+                    return number
+                        .cast(ty)
+                        .ok_or_else(|| anyhow!("lossy cast from {from:?} into {ty:?}: {value:?}"))
+                }
+                None => None,
+            },
+        };
+        value.ok_or_else(|| anyhow!("cannot cast {from:?} into {ty:?}"))
+    }
+
+    /// Converts the values into a tensor of the given element type,
+    /// or of the type of the first one if `Dyn`.
+    fn cast_tensor(values: &Array<Value, IxDyn>, ty: &ValueType) -> Result<Self> {
+        if ty == &ValueType::Dyn {
+            return DynArray::try_from_values(values).map(Self::Tensor);
+        }
+
+        let data = values
+            .iter()
+            .map(|value| value.cast_ref(ty))
+            .collect::<Result<_>>()?;
+        let values = Array(::ndarray::ArcArray::from_shape_vec(values.raw_dim(), data)?);
+        DynArray::from_values(ty, &values).map(Self::Tensor)
+    }
+
+    pub(super) fn parse_str(s: &str, ty: &ValueType) -> Result<Self> {
+        fn parse<T>(s: &str, ty: &ValueType) -> Result<T>
+        where
+            T: ::core::str::FromStr,
+        {
+            s.parse()
+                .map_err(|_| anyhow!("failed to parse {ty:?}: {s:?}"))
+        }
+
+        Ok(match ty {
+            ValueType::Bool => Self::Bool(parse(s, ty)?),
+            ValueType::I8 => Self::I8(parse(s, ty)?),
+            ValueType::I16 => Self::I16(parse(s, ty)?),
+            ValueType::I32 => Self::I32(parse(s, ty)?),
+            ValueType::I64 => Self::I64(parse(s, ty)?),
+            ValueType::I128 => Self::I128(parse(s, ty)?),
+            ValueType::U8 => Self::U8(parse(s, ty)?),
+            ValueType::U16 => Self::U16(parse(s, ty)?),
+            ValueType::U32 => Self::U32(parse(s, ty)?),
+            ValueType::U64 => Self::U64(parse(s, ty)?),
+            ValueType::U128 => Self::U128(parse(s, ty)?),
+            ValueType::F32 => Self::F32(parse(s, ty)?),
+            ValueType::F64 => Self::F64(parse(s, ty)?),
+            ValueType::Bytes => Self::Bytes(s.as_bytes().to_vec()),
+            ValueType::String => Self::String(s.to_string()),
+            ValueType::DateTime => Self::DateTime(parse(s, ty)?),
+            ValueType::Uuid => Self::Uuid(Uuid(parse(s, ty)?)),
+            ValueType::Hash => Self::Hash(parse(s, ty)?),
+            ValueType::UnitInterval => Self::UnitInterval(UnitInterval(parse(s, ty)?)),
+            ValueType::Dyn => Self::String(s.to_string()),
+            ValueType::None
+            | ValueType::Text
+            | ValueType::Array
+            | ValueType::List(_)
+            | ValueType::Map(_)
+            | ValueType::Record(_)
+            | ValueType::Tensor(_)
+            | ValueType::TimeSeries => {
+                bail!("cannot cast {:?} into {ty:?}", ValueType::String)
+            }
+        })
+    }
+
+    fn to_str(&self) -> Option<String> {
+        Some(match self {
+            Self::Bool(value) => value.to_string(),
+            Self::I8(value) => value.to_string(),
+            Self::I16(value) => value.to_string(),
+            Self::I32(value) => value.to_string(),
+            Self::I64(value) => value.to_string(),
+            Self::I128(value) => value.to_string(),
+            Self::U8(value) => value.to_string(),
+            Self::U16(value) => value.to_string(),
+            Self::U32(value) => value.to_string(),
+            Self::U64(value) => value.to_string(),
+            Self::U128(value) => value.to_string(),
+            Self::F32(value) => value.to_string(),
+            Self::F64(value) => value.to_string(),
+            Self::Bytes(value) => String::from_utf8(value.clone()).ok()?,
+            Self::String(value) => value.clone(),
+            Self::DateTime(value) => value.to_string(),
+            Self::Uuid(value) => value.to_string(),
+            Self::Hash(value) => value.to_string(),
+            Self::UnitInterval(value) => value.0.to_string(),
+            // the language tag would be lost
+            Self::Text(_) => return None,
+            Self::None
+            | Self::Dyn
+            | Self::Array(_)
+            | Self::List(_)
+            | Self::Map(_)
+            | Self::Record(_)
+            | Self::Tensor(_)
+            | Self::TimeSeries(_) => return None,
+        })
+    }
+
+    pub(super) fn to_number(&self) -> Option<Number> {
+        Some(match self {
+            Self::Bool(value) => Number::Signed(*value as i128),
+            Self::I8(value) => Number::Signed((*value).into()),
+            Self::I16(value) => Number::Signed((*value).into()),
+            Self::I32(value) => Number::Signed((*value).into()),
+            Self::I64(value) => Number::Signed((*value).into()),
+            Self::I128(value) => Number::Signed(*value),
+            Self::U8(value) => Number::Unsigned((*value).into()),
+            Self::U16(value) => Number::Unsigned((*value).into()),
+            Self::U32(value) => Number::Unsigned((*value).into()),
+            Self::U64(value) => Number::Unsigned((*value).into()),
+            Self::U128(value) => Number::Unsigned(*value),
+            Self::F32(value) => Number::Float((*value).into()),
+            Self::F64(value) => Number::Float(*value),
+            // lossless, as f64 has more than 32 bits of mantissa
+            Self::UnitInterval(value) => Number::Float(value.to_num()),
+            _ => return None,
+        })
+    }
+}
+
+impl ArchivedValue {
+    pub(super) fn to_number(&self) -> Option<Number> {
+        Some(match self {
+            Self::Bool(value) => Number::Signed(*value as i128),
+            Self::I8(value) => Number::Signed((*value).into()),
+            Self::I16(value) => Number::Signed(from_archived!(*value).into()),
+            Self::I32(value) => Number::Signed(from_archived!(*value).into()),
+            Self::I64(value) => Number::Signed(from_archived!(*value).into()),
+            Self::I128(value) => Number::Signed(from_archived!(*value)),
+            Self::U8(value) => Number::Unsigned((*value).into()),
+            Self::U16(value) => Number::Unsigned(from_archived!(*value).into()),
+            Self::U32(value) => Number::Unsigned(from_archived!(*value).into()),
+            Self::U64(value) => Number::Unsigned(from_archived!(*value).into()),
+            Self::U128(value) => Number::Unsigned(from_archived!(*value)),
+            Self::F32(value) => Number::Float(from_archived!(*value).into()),
+            Self::F64(value) => Number::Float(from_archived!(*value)),
+            Self::UnitInterval(value) => {
+                Number::Float(U0F32::from_bits(from_archived!(*value)).to_num())
+            }
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub(super) enum Number {
+    Signed(i128),
+    Unsigned(u128),
+    Float(f64),
+}
+
+impl Number {
+    /// 2^127, the exclusive upper bound of `i128` in `f64`.
+    const I128_END: f64 = i128::MAX as f64;
+    /// 2^128, the exclusive upper bound of `u128` in `f64`.
+    const U128_END: f64 = u128::MAX as f64;
+
+    pub(super) fn cast(self, ty: &ValueType) -> Option<Value> {
+        Some(match ty {
+            ValueType::Bool => match self.to_i128()? {
+                0 => Value::Bool(false),
+                1 => Value::Bool(true),
+                _ => return None,
+            },
+            ValueType::I8 => Value::I8(self.to_i128()?.try_into().ok()?),
+            ValueType::I16 => Value::I16(self.to_i128()?.try_into().ok()?),
+            ValueType::I32 => Value::I32(self.to_i128()?.try_into().ok()?),
+            ValueType::I64 => Value::I64(self.to_i128()?.try_into().ok()?),
+            ValueType::I128 => Value::I128(self.to_i128()?),
+            ValueType::U8 => Value::U8(self.to_u128()?.try_into().ok()?),
+            ValueType::U16 => Value::U16(self.to_u128()?.try_into().ok()?),
+            ValueType::U32 => Value::U32(self.to_u128()?.try_into().ok()?),
+            ValueType::U64 => Value::U64(self.to_u128()?.try_into().ok()?),
+            ValueType::U128 => Value::U128(self.to_u128()?),
+            ValueType::F32 => {
+                let value = self.to_f64()?;
+                let narrowed = value as f32;
+                if value.is_nan() || f64::from(narrowed) == value {
+                    Value::F32(narrowed)
+                } else {
+                    return None;
+                }
+            }
+            ValueType::F64 => Value::F64(self.to_f64()?),
+            ValueType::UnitInterval => {
+                let value = self.to_f64()?;
+                let narrowed = U0F32::checked_from_num(value)?;
+                if narrowed.to_num::<f64>() == value {
+                    Value::UnitInterval(UnitInterval(narrowed))
+                } else {
+                    return None;
+                }
+            }
+            _ => return None,
+        })
+    }
+
+    pub(super) fn to_i128(self) -> Option<i128> {
+        match self {
+            Self::Signed(value) => Some(value),
+            Self::Unsigned(value) => value.try_into().ok(),
+            Self::Float(value) => (value.fract() == 0.0
+                && (-Self::I128_END..Self::I128_END).contains(&value))
+            .then_some(value as i128),
+        }
+    }
+
+    pub(super) fn to_u128(self) -> Option<u128> {
+        match self {
+            Self::Signed(value) => value.try_into().ok(),
+            Self::Unsigned(value) => Some(value),
+            Self::Float(value) => (value.fract() == 0.0 && (0.0..Self::U128_END).contains(&value))
+                .then_some(value as u128),
+        }
+    }
+
+    pub(super) fn to_f64(self) -> Option<f64> {
+        match self {
+            Self::Signed(value) => {
+                let float = value as f64;
+                (float < Self::I128_END && float as i128 == value).then_some(float)
+            }
+            Self::Unsigned(value) => {
+                let float = value as f64;
+                (float < Self::U128_END && float as u128 == value).then_some(float)
+            }
+            Self::Float(value) => Some(value),
+        }
+    }
+
+    /// Converts the number into `f64`, rounding it if needed.
+    pub(super) fn to_f64_rounded(self) -> f64 {
+        match self {
+            Self::Signed(value) => value as f64,
+            Self::Unsigned(value) => value as f64,
+            Self::Float(value) => value,
+        }
+    }
+
+    /// Compares the numbers by their values, regardless of the representations.
+    ///
+    /// The floats are compared with the integers exactly, without rounding the integers.
+    pub(super) fn compare(self, other: Self) -> Option<Ordering> {
+        match (self, other) {
+            (Self::Signed(a), Self::Signed(b)) => a.partial_cmp(&b),
+            (Self::Unsigned(a), Self::Unsigned(b)) => a.partial_cmp(&b),
+            (Self::Signed(a), Self::Unsigned(b)) => match u128::try_from(a) {
+                Ok(a) => a.partial_cmp(&b),
+                Err(_) => Some(Ordering::Less),
+            },
+            (Self::Float(a), Self::Float(b)) => a.partial_cmp(&b),
+            (Self::Float(a), Self::Signed(b)) => {
+                if a.is_nan() {
+                    None
+                } else if a >= Self::I128_END {
+                    Some(Ordering::Greater)
+                } else if a < -Self::I128_END {
+                    Some(Ordering::Less)
+                } else {
+                    // the integral part is exact in i128
+                    let trunc = a.trunc();
+                    Some((trunc as i128).cmp(&b).then(a.partial_cmp(&trunc)?))
+                }
+            }
+            (Self::Float(a), Self::Unsigned(b)) => {
+                if a.is_nan() {
+                    None
+                } else if a >= Self::U128_END {
+                    Some(Ordering::Greater)
+                } else if a < 0.0 {
+                    Some(Ordering::Less)
+                } else {
+                    // the integral part is exact in u128
+                    let trunc = a.trunc();
+                    Some((trunc as u128).cmp(&b).then(a.partial_cmp(&trunc)?))
+                }
+            }
+            (a, b) => b.compare(a).map(Ordering::reverse),
+        }
+    }
+
+    /// Compares the numbers by their values, where `NaN`s are equal to each other and the greatest.
+    pub(super) fn total_cmp(self, other: Self) -> Ordering {
+        let is_nan = |number| matches!(number, Self::Float(value) if value.is_nan());
+        match (is_nan(self), is_nan(other)) {
+            (false, false) => self.compare(other).unwrap_or(Ordering::Equal),
+            (a, b) => a.cmp(&b),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::FieldType, *};
+
+    #[test]
+    fn test_cast_numbers() {
+        // widening
+        assert_eq!(Value::I8(-1).cast(ValueType::I64).unwrap(), Value::I64(-1));
+        assert_eq!(Value::U32(7).cast(ValueType::F64).unwrap(), Value::F64(7.0));
+        assert_eq!(Value::Bool(true).cast(ValueType::U8).unwrap(), Value::U8(1));
+
+        // narrowing
+        assert_eq!(Value::I64(42).cast(ValueType::U8).unwrap(), Value::U8(42));
+        assert_eq!(
+            Value::F64(2.5).cast(ValueType::F32).unwrap(),
+            Value::F32(2.5)
+        );
+        assert_eq!(
+            Value::F64(0.5).cast(ValueType::UnitInterval).unwrap(),
+            Value::UnitInterval(UnitInterval(U0F32::from_num(0.5))),
+        );
+
+        // lossy
+        assert!(Value::I64(-1).cast(ValueType::U64).is_err());
+        assert!(Value::I32(256).cast(ValueType::U8).is_err());
+        assert!(Value::I32(2).cast(ValueType::Bool).is_err());
+        assert!(Value::F64(2.5).cast(ValueType::I32).is_err());
+        assert!(Value::F64(0.1).cast(ValueType::F32).is_err());
+        assert!(Value::F64(1.0).cast(ValueType::UnitInterval).is_err());
+        assert!(Value::F64(f64::INFINITY).cast(ValueType::I128).is_err());
+        assert!(Value::U128(u128::MAX).cast(ValueType::F64).is_err());
+        assert!(Value::I64(i64::MAX).cast(ValueType::F64).is_err());
+    }
+
+    #[test]
+    fn test_cast_strings() {
+        assert_eq!(
+            Value::String("-42".into()).cast(ValueType::I16).unwrap(),
+            Value::I16(-42),
+        );
+        assert_eq!(
+            Value::F32(1.5).cast(ValueType::String).unwrap(),
+            Value::String("1.5".into()),
+        );
+        assert!(Value::String("300".into()).cast(ValueType::U8).is_err());
+        assert!(Value::String("1.5".into()).cast(ValueType::I32).is_err());
+
+        // round trips
+        let values = [
+            Value::Hash(Hash::with_str("hello")),
+            Value::Uuid(Uuid(::uuid::Uuid::new_v4())),
+            Value::DateTime(super::super::chrono::DateTime::now()),
+            Value::I128(i128::MIN),
+        ];
+        for value in values {
+            let string = value.cast(ValueType::String).unwrap();
+            assert_eq!(string.cast(value.value_type()).unwrap(), value);
+        }
+
+        // the language tag would be lost
+        assert!(Value::Text(Default::default())
+            .cast(ValueType::String)
+            .is_err());
+    }
+
+    #[test]
+    fn test_cast_others() {
+        assert_eq!(Value::None.cast(ValueType::Dyn).unwrap(), Value::None);
+        assert!(Value::None.cast(ValueType::I32).is_err());
+        assert!(Value::Bytes(vec![0; 31]).cast(ValueType::Hash).is_err());
+
+        let hash = Value::Hash(Hash::with_str("hello"));
+        let bytes = hash.cast(ValueType::Bytes).unwrap();
+        assert_eq!(bytes.cast(ValueType::Hash).unwrap(), hash);
+
+        for ty in [
+            ValueType::I8,
+            ValueType::Text,
+            ValueType::Array,
+            ValueType::Tensor(Box::new(ValueType::F32)),
+        ] {
+            assert_eq!(ty.default_value().unwrap().value_type(), ty);
+        }
+
+        // tensors of unsupported elements have no values
+        for ty in [ValueType::String, ValueType::Dyn] {
+            assert!(ValueType::Tensor(Box::new(ty)).default_value().is_err());
+        }
+    }
+
+    #[test]
+    fn test_cast_composites() {
+        let list = Value::List(vec![Value::I8(1), Value::U16(2)]);
+        assert_eq!(list.value_type(), ValueType::List(Box::new(ValueType::Dyn)));
+        assert_eq!(
+            list.cast(ValueType::List(Box::new(ValueType::I64)))
+                .unwrap(),
+            Value::List(vec![Value::I64(1), Value::I64(2)]),
+        );
+        assert!(list
+            .cast(ValueType::List(Box::new(ValueType::Bool)))
+            .is_err());
+
+        // normalize a map into the declared record
+        let ty = ValueType::Record(vec![
+            FieldType {
+                name: "id".into(),
+                ty: ValueType::U64,
+            },
+            FieldType {
+                name: "name".into(),
+                ty: ValueType::String,
+            },
+        ]);
+        let map = Value::Map(
+            [
+                ("name".to_string(), Value::String("foo".into())),
+                ("id".to_string(), Value::String("42".into())),
+            ]
+            .into(),
+        );
+        let record = map.cast(ty.clone()).unwrap();
+        assert_eq!(record.value_type(), ty);
+        assert_eq!(
+            record,
+            Value::Record(vec![
+                Field {
+                    name: "id".into(),
+                    value: Value::U64(42),
+                },
+                Field {
+                    name: "name".into(),
+                    value: Value::String("foo".into()),
+                },
+            ]),
+        );
+        assert_eq!(ty.default_value().unwrap().value_type(), ty);
+
+        // missing and unexpected fields
+        let mut map = Value::Map([("id".to_string(), Value::U64(42))].into());
+        assert!(map.cast(ty.clone()).is_err());
+        if let Value::Map(map) = &mut map {
+            map.insert("name".into(), Value::String("foo".into()));
+            map.insert("extra".into(), Value::None);
+        }
+        assert!(map.cast(ty).is_err());
+    }
+}
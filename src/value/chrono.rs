@@ -67,7 +67,10 @@ impl<S: Fallible + ?Sized> Serialize<S> for DateTime {
     }
 }
 
-impl<D: Fallible + ?Sized> Deserialize<DateTime, D> for <DateTime as Archive>::Archived {
+impl<D: Fallible + ?Sized> Deserialize<DateTime, D> for <DateTime as Archive>::Archived
+where
+    D::Error: From<NaiveDateTimeRangeError>,
+{
     #[inline]
     fn deserialize(&self, deserializer: &mut D) -> Result<DateTime, D::Error> {
         Deserialize::<NaiveDateTime, D>::deserialize(self, deserializer)
@@ -82,8 +85,278 @@ impl DateTime {
     pub fn now() -> Self {
         Self(::chrono::Utc::now())
     }
+
+    /// Adds the duration, returning `None` on overflow.
+    pub fn checked_add(&self, duration: Duration) -> Option<Self> {
+        self.0.checked_add_signed(duration.0).map(Self)
+    }
+
+    /// Subtracts the duration, returning `None` on overflow.
+    pub fn checked_sub(&self, duration: Duration) -> Option<Self> {
+        self.0.checked_sub_signed(duration.0).map(Self)
+    }
+}
+
+/// Parses an RFC 3339 date and time, converting its offset into UTC.
+impl ::core::str::FromStr for DateTime {
+    type Err = ::chrono::ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ::chrono::DateTime::parse_from_rfc3339(s)
+            .map(|datetime| Self(datetime.with_timezone(&::chrono::Utc)))
+    }
+}
+
+/// Formats the date and time in RFC 3339, such as `2020-01-01T00:00:00.5Z`.
+impl ::core::fmt::Display for DateTime {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+        f.write_str(&self.to_rfc3339_opts(::chrono::SecondsFormat::AutoSi, true))
+    }
+}
+
+impl ::core::ops::Add<Duration> for DateTime {
+    type Output = Self;
+
+    fn add(self, rhs: Duration) -> Self::Output {
+        self.checked_add(rhs)
+            .expect("`DateTime + Duration` overflowed")
+    }
+}
+
+impl ::core::ops::AddAssign<Duration> for DateTime {
+    fn add_assign(&mut self, rhs: Duration) {
+        *self = *self + rhs;
+    }
+}
+
+impl ::core::ops::Sub<Duration> for DateTime {
+    type Output = Self;
+
+    fn sub(self, rhs: Duration) -> Self::Output {
+        self.checked_sub(rhs)
+            .expect("`DateTime - Duration` overflowed")
+    }
+}
+
+impl ::core::ops::SubAssign<Duration> for DateTime {
+    fn sub_assign(&mut self, rhs: Duration) {
+        *self = *self - rhs;
+    }
+}
+
+impl ::core::ops::Sub for DateTime {
+    type Output = Duration;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Duration(self.0 - rhs.0)
+    }
+}
+
+/// A signed span of time, with nanosecond precision.
+#[derive(Copy, Clone, Debug, Eq)]
+pub struct Duration(pub ::chrono::Duration);
+
+impl From<::chrono::Duration> for Duration {
+    fn from(value: ::chrono::Duration) -> Self {
+        Self(value)
+    }
+}
+
+impl ::core::ops::Deref for Duration {
+    type Target = ::chrono::Duration;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl PartialEq for Duration {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl PartialEq<Duration> for <Duration as Archive>::Archived {
+    fn eq(&self, other: &Duration) -> bool {
+        self == &DurationTemplate::from(*other)
+    }
+}
+
+impl PartialOrd<Duration> for <Duration as Archive>::Archived {
+    fn partial_cmp(&self, other: &Duration) -> Option<::core::cmp::Ordering> {
+        self.partial_cmp(&DurationTemplate::from(*other))
+    }
+}
+
+impl PartialOrd for Duration {
+    fn partial_cmp(&self, other: &Self) -> Option<::core::cmp::Ordering> {
+        self.0.partial_cmp(&other.0)
+    }
+}
+
+impl Ord for Duration {
+    fn cmp(&self, other: &Self) -> ::core::cmp::Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl ::core::hash::Hash for Duration {
+    fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl ::core::ops::Add for Duration {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl ::core::ops::Sub for Duration {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self(self.0 - rhs.0)
+    }
 }
 
+impl ::core::ops::Neg for Duration {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self(-self.0)
+    }
+}
+
+impl Archive for Duration {
+    type Archived = <DurationTemplate as Archive>::Archived;
+    type Resolver = <DurationTemplate as Archive>::Resolver;
+
+    #[inline]
+    unsafe fn resolve(&self, pos: usize, resolver: Self::Resolver, out: *mut Self::Archived) {
+        DurationTemplate::from(*self).resolve(pos, resolver, out)
+    }
+}
+
+impl<S: Fallible + ?Sized> Serialize<S> for Duration {
+    #[inline]
+    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        DurationTemplate::from(*self).serialize(serializer)
+    }
+}
+
+impl<D: Fallible + ?Sized> Deserialize<Duration, D> for <Duration as Archive>::Archived
+where
+    D::Error: From<DurationRangeError>,
+{
+    #[inline]
+    fn deserialize(&self, deserializer: &mut D) -> Result<Duration, D::Error> {
+        Deserialize::<DurationTemplate, D>::deserialize(self, deserializer)?
+            .try_into()
+            .map_err(Into::into)
+    }
+}
+
+impl Duration {
+    /// The whole seconds, rounded towards negative infinity, and the remaining nanoseconds.
+    pub fn as_secs_nanos(&self) -> (i64, u32) {
+        let nanos = total_nanos(self.0);
+        (
+            nanos.div_euclid(NANOS_PER_SEC) as i64,
+            nanos.rem_euclid(NANOS_PER_SEC) as u32,
+        )
+    }
+}
+
+const NANOS_PER_SEC: i128 = 1_000_000_000;
+
+fn total_nanos(duration: ::chrono::Duration) -> i128 {
+    let secs = duration.num_seconds();
+    let subsec_nanos = (duration - ::chrono::Duration::seconds(secs))
+        .num_nanoseconds()
+        .unwrap_or_default();
+    i128::from(secs) * NANOS_PER_SEC + i128::from(subsec_nanos)
+}
+
+impl From<Duration> for DurationTemplate {
+    fn from(value: Duration) -> Self {
+        let (secs, nanos) = value.as_secs_nanos();
+        Self { secs, nanos }
+    }
+}
+
+impl TryFrom<DurationTemplate> for Duration {
+    type Error = DurationRangeError;
+
+    fn try_from(value: DurationTemplate) -> Result<Self, Self::Error> {
+        const NANOS_PER_MILLI: i128 = 1_000_000;
+
+        let error = DurationRangeError {
+            secs: value.secs,
+            nanos: value.nanos,
+        };
+        let nanos = i128::from(value.secs) * NANOS_PER_SEC + i128::from(value.nanos);
+        if i128::from(value.nanos) >= NANOS_PER_SEC
+            || nanos < total_nanos(::chrono::Duration::min_value())
+            || nanos > total_nanos(::chrono::Duration::max_value())
+        {
+            return Err(error);
+        }
+
+        ::chrono::Duration::milliseconds(nanos.div_euclid(NANOS_PER_MILLI) as i64)
+            .checked_add(&::chrono::Duration::nanoseconds(
+                nanos.rem_euclid(NANOS_PER_MILLI) as i64,
+            ))
+            .map(Self)
+            .ok_or(error)
+    }
+}
+
+#[derive(
+    Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Archive, Serialize, Deserialize,
+)]
+#[archive(compare(PartialEq, PartialOrd))]
+#[archive_attr(derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash))]
+// FIXME: hide it
+pub struct DurationTemplate {
+    secs: i64,
+    nanos: u32,
+}
+
+impl<C: ?Sized> CheckBytes<C> for ArchivedDurationTemplate {
+    type Error = DurationRangeError;
+
+    #[inline]
+    unsafe fn check_bytes<'a>(
+        value: *const Self,
+        _context: &mut C,
+    ) -> Result<&'a Self, Self::Error> {
+        // every bit pattern is a valid integer, so only the range is checked
+        let value = &*value;
+        Duration::try_from(DurationTemplate {
+            secs: value.secs.value(),
+            nanos: value.nanos.value(),
+        })
+        .map(|_| value)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DurationRangeError {
+    pub secs: i64,
+    pub nanos: u32,
+}
+
+impl ::core::fmt::Display for DurationRangeError {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+        write!(f, "duration out of range: {}s {}ns", self.secs, self.nanos)
+    }
+}
+
+impl ::std::error::Error for DurationRangeError {}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct NaiveDateTime(pub ::chrono::NaiveDateTime);
 
@@ -103,13 +376,15 @@ impl From<NaiveDateTime> for NaiveDateTimeTemplate {
 }
 
 impl TryFrom<NaiveDateTimeTemplate> for NaiveDateTime {
-    type Error = ::core::convert::Infallible;
+    type Error = NaiveDateTimeRangeError;
 
     fn try_from(value: NaiveDateTimeTemplate) -> Result<Self, Self::Error> {
-        // FIXME: handle chrono input errors
-        Ok(Self(
-            ::chrono::NaiveDateTime::from_timestamp_opt(value.secs, value.nanos).unwrap(),
-        ))
+        ::chrono::NaiveDateTime::from_timestamp_opt(value.secs, value.nanos)
+            .map(Self)
+            .ok_or(NaiveDateTimeRangeError {
+                secs: value.secs,
+                nanos: value.nanos,
+            })
     }
 }
 
@@ -152,12 +427,15 @@ impl<S: Fallible + ?Sized> Serialize<S> for NaiveDateTime {
     }
 }
 
-impl<D: Fallible + ?Sized> Deserialize<NaiveDateTime, D> for <NaiveDateTime as Archive>::Archived {
+impl<D: Fallible + ?Sized> Deserialize<NaiveDateTime, D> for <NaiveDateTime as Archive>::Archived
+where
+    D::Error: From<NaiveDateTimeRangeError>,
+{
     #[inline]
     fn deserialize(&self, deserializer: &mut D) -> Result<NaiveDateTime, D::Error> {
-        Deserialize::<NaiveDateTimeTemplate, D>::deserialize(self, deserializer)
-            // FIXME: handle chrono input errors
-            .map(|e| e.try_into().unwrap())
+        Deserialize::<NaiveDateTimeTemplate, D>::deserialize(self, deserializer)?
+            .try_into()
+            .map_err(Into::into)
     }
 }
 
@@ -171,9 +449,104 @@ impl NaiveDateTime {
     Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Archive, Serialize, Deserialize,
 )]
 #[archive(compare(PartialEq, PartialOrd))]
-#[archive_attr(derive(CheckBytes, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash))]
+#[archive_attr(derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash))]
 // FIXME: hide it
 pub struct NaiveDateTimeTemplate {
     secs: i64,
     nanos: u32,
 }
+
+impl<C: ?Sized> CheckBytes<C> for ArchivedNaiveDateTimeTemplate {
+    type Error = NaiveDateTimeRangeError;
+
+    #[inline]
+    unsafe fn check_bytes<'a>(
+        value: *const Self,
+        _context: &mut C,
+    ) -> Result<&'a Self, Self::Error> {
+        // every bit pattern is a valid integer, so only the range is checked
+        let value = &*value;
+        NaiveDateTime::try_from(NaiveDateTimeTemplate {
+            secs: value.secs.value(),
+            nanos: value.nanos.value(),
+        })
+        .map(|_| value)
+    }
+}
+
+impl ArchivedNaiveDateTimeTemplate {
+    /// The seconds and the nanoseconds since the UNIX epoch.
+    pub(crate) fn timestamp(&self) -> (i64, u32) {
+        (self.secs.value(), self.nanos.value())
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct NaiveDateTimeRangeError {
+    pub secs: i64,
+    pub nanos: u32,
+}
+
+impl ::core::fmt::Display for NaiveDateTimeRangeError {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+        write!(f, "timestamp out of range: {}s {}ns", self.secs, self.nanos,)
+    }
+}
+
+impl ::std::error::Error for NaiveDateTimeRangeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_out_of_range() {
+        // create a malformed timestamp
+        let template = NaiveDateTimeTemplate {
+            secs: i64::MAX,
+            nanos: 0,
+        };
+
+        // serialize
+        let bytes = ::rkyv::to_bytes::<_, 64>(&template).unwrap();
+
+        // validate
+        assert!(::rkyv::check_archived_root::<NaiveDateTime>(&bytes).is_err());
+        assert!(crate::signed::from_bytes::<DateTime>(&bytes).is_err());
+        assert!(::rkyv::check_archived_root::<Duration>(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_rfc3339() {
+        let datetime: DateTime = "2020-01-01T09:00:00.5+09:00".parse().unwrap();
+        assert_eq!(datetime.to_string(), "2020-01-01T00:00:00.500Z");
+        assert_eq!(datetime.to_string().parse::<DateTime>().unwrap(), datetime);
+        assert!("2020-01-01 00:00:00".parse::<DateTime>().is_err());
+    }
+
+    #[test]
+    fn test_duration() {
+        let start: DateTime = "2020-01-01T00:00:00Z".parse().unwrap();
+        let ttl = Duration(::chrono::Duration::milliseconds(-1_500));
+        assert_eq!((start + ttl).to_string(), "2019-12-31T23:59:58.500Z");
+        assert_eq!(start + ttl - start, ttl);
+        assert_eq!(start - ttl + ttl, start);
+        assert!(DateTime::MAX_DATETIME.checked_add(-ttl).is_none());
+
+        // the negative durations keep non-negative nanoseconds
+        assert_eq!(ttl.as_secs_nanos(), (-2, 500_000_000));
+
+        // archive
+        for duration in [
+            ttl,
+            Duration(::chrono::Duration::max_value()),
+            Duration(::chrono::Duration::min_value()),
+        ] {
+            let bytes = ::rkyv::to_bytes::<_, 64>(&duration).unwrap();
+            let archived = ::rkyv::check_archived_root::<Duration>(&bytes).unwrap();
+            assert_eq!(archived, &duration);
+            let deserialized: Duration = crate::signed::from_bytes(&bytes).unwrap();
+            assert_eq!(deserialized, duration);
+        }
+    }
+}
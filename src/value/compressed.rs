@@ -0,0 +1,372 @@
+//! Compressed encodings of the array payloads.
+//!
+//! The elements are written in the logical order as little-endian bytes,
+//! optionally shuffled by their significance, and then compressed.
+//! Signatures cover the compressed bytes, so they do not depend on the memory layouts
+//! nor the byte order of the archives.
+//!
+//! `CompressedArray` is a separate type rather than an encoding flag of `ArrayRaw`, unlike its
+//! `Order`: the elements of `ArrayRaw` are the archived ones, which are viewed in place without
+//! decoding, while the compressed bytes are always decoded into a new array. A flag would make
+//! every `Array` fallible to view and change its schema, so a payload chooses the compression
+//! by its type instead.
+
+use std::{borrow::Cow, marker::PhantomData};
+
+use anyhow::{anyhow, bail, Result};
+use bytecheck::CheckBytes;
+use ndarray::{ArrayBase, Data, Dimension, IxDyn};
+use rkyv::{Archive, Deserialize, Infallible, Serialize};
+
+use super::array::Array;
+use crate::schema::HasSchema;
+
+/// The default limit of the decoded bytes, which bounds the allocations for untrusted shapes.
+pub const MAX_DECODED_SIZE: usize = 1 << 30;
+
+/// The zstd compression level, which balances the speed and the ratio.
+#[cfg(feature = "zstd")]
+const ZSTD_LEVEL: i32 = 3;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Archive, Serialize, Deserialize, HasSchema)]
+#[archive(bound(archive = "
+    <PhantomData<(A, D)> as Archive>::Archived: ::core::fmt::Debug + PartialEq,
+"))]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+pub struct CompressedArray<A, D> {
+    pub(crate) data: Vec<u8>,
+    pub(crate) shape: Vec<u64>,
+    pub(crate) encoding: Encoding,
+    #[schema(skip)]
+    pub(crate) _element: PhantomData<(A, D)>,
+}
+
+impl<A, D> CompressedArray<A, D>
+where
+    A: Element,
+    D: Dimension,
+{
+    /// Encodes the elements of the array in the logical order.
+    pub fn encode<S>(array: &ArrayBase<S, D>, encoding: Encoding) -> Result<Self>
+    where
+        S: Data<Elem = A>,
+    {
+        let mut bytes = Vec::with_capacity(array.len() * A::SIZE);
+        for item in array.iter() {
+            item.write_le(&mut bytes);
+        }
+        if encoding.shuffle {
+            bytes = shuffle(&bytes, A::SIZE);
+        }
+
+        Ok(Self {
+            data: encoding.compression.compress(bytes)?,
+            shape: array.shape().iter().map(|&dim| dim as u64).collect(),
+            encoding,
+            _element: PhantomData,
+        })
+    }
+
+    /// Decodes the elements into an array in the standard layout,
+    /// up to `MAX_DECODED_SIZE` bytes of elements.
+    pub fn decode(&self) -> Result<Array<A, D>> {
+        self.decode_with_limit(MAX_DECODED_SIZE)
+    }
+
+    /// Decodes the elements into an array in the standard layout,
+    /// failing before decompressing if they would take more than `max_size` bytes.
+    pub fn decode_with_limit(&self, max_size: usize) -> Result<Array<A, D>> {
+        decode(&self.data, &self.shape, self.encoding, max_size)
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    pub fn shape(&self) -> &[u64] {
+        &self.shape
+    }
+}
+
+impl<A, D> ArchivedCompressedArray<A, D>
+where
+    A: Element,
+    D: Dimension,
+{
+    /// Decodes the archived elements into an array in the standard layout,
+    /// up to `MAX_DECODED_SIZE` bytes of elements.
+    pub fn decode(&self) -> Result<Array<A, D>> {
+        self.decode_with_limit(MAX_DECODED_SIZE)
+    }
+
+    /// Decodes the archived elements into an array in the standard layout,
+    /// failing before decompressing if they would take more than `max_size` bytes.
+    pub fn decode_with_limit(&self, max_size: usize) -> Result<Array<A, D>> {
+        let shape: Vec<u64> = self.shape.deserialize(&mut Infallible)?;
+        let encoding: Encoding = self.encoding.deserialize(&mut Infallible)?;
+        decode(&self.data, &shape, encoding, max_size)
+    }
+}
+
+pub(crate) fn decode<A, D>(
+    data: &[u8],
+    shape: &[u64],
+    encoding: Encoding,
+    max_size: usize,
+) -> Result<Array<A, D>>
+where
+    A: Element,
+    D: Dimension,
+{
+    let shape = shape
+        .iter()
+        .map(|&dim| usize::try_from(dim))
+        .collect::<Result<Vec<_>, _>>()?;
+    let size = shape
+        .iter()
+        .try_fold(A::SIZE, |size, &dim| size.checked_mul(dim))
+        .ok_or_else(|| anyhow!("array too large: {shape:?}"))?;
+    if size > max_size {
+        bail!("array too large: {size} bytes of elements, exceeding {max_size} bytes");
+    }
+
+    let mut bytes = encoding.compression.decompress(data, size)?;
+    if bytes.len() != size {
+        bail!("expected {size} bytes of elements, found {}", bytes.len());
+    }
+    if encoding.shuffle {
+        bytes = Cow::Owned(unshuffle(&bytes, A::SIZE));
+    }
+
+    let data = bytes.chunks_exact(A::SIZE).map(A::read_le).collect();
+    ::ndarray::Array::from_shape_vec(IxDyn(&shape), data)?
+        .into_dimensionality()
+        .map(::ndarray::ArrayBase::into_shared)
+        .map(Array)
+        .map_err(Into::into)
+}
+
+/// Groups the `i`-th bytes of the elements together, which makes floats more compressible.
+fn shuffle(bytes: &[u8], size: usize) -> Vec<u8> {
+    let len = bytes.len() / size;
+    let mut shuffled = vec![0; bytes.len()];
+    for (index, element) in bytes.chunks_exact(size).enumerate() {
+        for (byte_index, &byte) in element.iter().enumerate() {
+            shuffled[byte_index * len + index] = byte;
+        }
+    }
+    shuffled
+}
+
+fn unshuffle(bytes: &[u8], size: usize) -> Vec<u8> {
+    let len = bytes.len() / size;
+    let mut unshuffled = vec![0; bytes.len()];
+    for (byte_index, group) in bytes.chunks_exact(len.max(1)).enumerate() {
+        for (index, &byte) in group.iter().enumerate() {
+            unshuffled[index * size + byte_index] = byte;
+        }
+    }
+    unshuffled
+}
+
+#[derive(
+    Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Archive, Serialize, Deserialize, HasSchema,
+)]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Copy, Clone, Debug, PartialEq, Eq, Hash))]
+pub struct Encoding {
+    pub compression: Compression,
+    /// Whether the bytes of the elements are grouped by their significance.
+    pub shuffle: bool,
+}
+
+#[derive(
+    Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Archive, Serialize, Deserialize, HasSchema,
+)]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Copy, Clone, Debug, PartialEq, Eq, Hash))]
+pub enum Compression {
+    #[default]
+    None,
+    /// Requires the `zstd` feature.
+    Zstd,
+    /// Requires the `lz4` feature.
+    Lz4,
+}
+
+impl Compression {
+    fn compress(self, bytes: Vec<u8>) -> Result<Vec<u8>> {
+        match self {
+            Self::None => Ok(bytes),
+            #[cfg(feature = "zstd")]
+            Self::Zstd => ::zstd::bulk::compress(&bytes, ZSTD_LEVEL).map_err(Into::into),
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => Ok(::lz4_flex::block::compress(&bytes)),
+            #[allow(unreachable_patterns)]
+            compression => bail!("{compression:?} compression is not enabled"),
+        }
+    }
+
+    /// Decompresses the bytes, up to the given size.
+    #[cfg_attr(not(any(feature = "zstd", feature = "lz4")), allow(unused_variables))]
+    fn decompress(self, bytes: &[u8], size: usize) -> Result<Cow<'_, [u8]>> {
+        match self {
+            Self::None => Ok(Cow::Borrowed(bytes)),
+            #[cfg(feature = "zstd")]
+            Self::Zstd => ::zstd::bulk::decompress(bytes, size)
+                .map(Cow::Owned)
+                .map_err(Into::into),
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => ::lz4_flex::block::decompress(bytes, size)
+                .map(Cow::Owned)
+                .map_err(Into::into),
+            #[allow(unreachable_patterns)]
+            compression => bail!("{compression:?} compression is not enabled"),
+        }
+    }
+}
+
+/// Elements which are encoded as little-endian bytes.
+pub trait Element: Copy {
+    const SIZE: usize;
+
+    fn write_le(&self, buf: &mut Vec<u8>);
+
+    /// Reads an element from exactly `SIZE` bytes.
+    fn read_le(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_element {
+    ( $( $ty:ty ),* ) => {
+        $(
+            impl Element for $ty {
+                const SIZE: usize = ::core::mem::size_of::<$ty>();
+
+                fn write_le(&self, buf: &mut Vec<u8>) {
+                    buf.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn read_le(bytes: &[u8]) -> Self {
+                    let mut buf = [0; ::core::mem::size_of::<$ty>()];
+                    buf.copy_from_slice(bytes);
+                    Self::from_le_bytes(buf)
+                }
+            }
+        )*
+    };
+}
+
+impl_element!(i8, i16, i32, i64, i128, u8, u16, u32, u64, u128, f32, f64);
+
+impl Element for bool {
+    const SIZE: usize = 1;
+
+    fn write_le(&self, buf: &mut Vec<u8>) {
+        buf.push(*self as u8);
+    }
+
+    fn read_le(bytes: &[u8]) -> Self {
+        bytes[0] != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{Ix2, ShapeBuilder};
+
+    use super::*;
+
+    #[test]
+    fn test_compressed_array() {
+        let array = ::ndarray::Array::from_shape_fn((64, 32).f(), |(i, j)| {
+            (i as f32 * 0.25).sin() + j as f32
+        });
+
+        #[allow(unused_mut)]
+        let mut encodings = vec![
+            Encoding::default(),
+            Encoding {
+                compression: Compression::None,
+                shuffle: true,
+            },
+        ];
+        #[cfg(feature = "zstd")]
+        encodings.push(Encoding {
+            compression: Compression::Zstd,
+            shuffle: true,
+        });
+        #[cfg(feature = "lz4")]
+        encodings.push(Encoding {
+            compression: Compression::Lz4,
+            shuffle: false,
+        });
+
+        for encoding in encodings {
+            let compressed = CompressedArray::encode(&array, encoding).unwrap();
+            assert_eq!(compressed.encoding(), encoding);
+            assert_eq!(compressed.shape(), &[64, 32]);
+            assert_eq!(compressed.decode().unwrap().0, array);
+
+            // the encoding is deterministic, regardless of the memory layout
+            let standard = CompressedArray::encode(&array.as_standard_layout(), encoding).unwrap();
+            assert_eq!(&compressed, &standard);
+
+            // archive
+            let bytes = ::rkyv::to_bytes::<_, 4096>(&compressed).unwrap();
+            let archived =
+                ::rkyv::check_archived_root::<CompressedArray<f32, Ix2>>(&bytes).unwrap();
+            assert_eq!(archived, &compressed);
+            assert_eq!(archived.decode().unwrap().0, array);
+
+            // the dimensionality is checked
+            let bytes = ::rkyv::to_bytes::<_, 4096>(&compressed).unwrap();
+            let archived =
+                ::rkyv::check_archived_root::<CompressedArray<f32, ndarray::Ix3>>(&bytes).unwrap();
+            assert!(archived.decode().is_err());
+        }
+
+        // malformed payloads
+        let mut compressed = CompressedArray::encode(&array, Encoding::default()).unwrap();
+        compressed.data.pop();
+        assert!(compressed.decode().is_err());
+
+        // the limit of the decoded bytes
+        let compressed = CompressedArray::encode(&array, Encoding::default()).unwrap();
+        assert!(compressed.decode_with_limit(64 * 32 * 4).is_ok());
+        assert!(compressed.decode_with_limit(64 * 32 * 4 - 1).is_err());
+    }
+
+    #[test]
+    fn test_decompression_bomb() {
+        // a tiny payload which declares a huge shape
+        for compression in [Compression::None, Compression::Zstd, Compression::Lz4] {
+            let bomb = CompressedArray::<u8, ndarray::Ix1> {
+                data: vec![0; 16],
+                shape: vec![1 << 40],
+                encoding: Encoding {
+                    compression,
+                    shuffle: false,
+                },
+                _element: PhantomData,
+            };
+            let error = bomb.decode().unwrap_err();
+            assert!(error.to_string().contains("array too large"));
+
+            let bytes = ::rkyv::to_bytes::<_, 4096>(&bomb).unwrap();
+            let archived =
+                ::rkyv::check_archived_root::<CompressedArray<u8, ndarray::Ix1>>(&bytes).unwrap();
+            let error = archived.decode().unwrap_err();
+            assert!(error.to_string().contains("array too large"));
+        }
+    }
+
+    #[test]
+    fn test_shuffle() {
+        let bytes: Vec<u8> = (0..12).collect();
+        let shuffled = shuffle(&bytes, 4);
+        assert_eq!(shuffled, [0, 4, 8, 1, 5, 9, 2, 6, 10, 3, 7, 11]);
+        assert_eq!(unshuffle(&shuffled, 4), bytes);
+        assert!(unshuffle(&[], 4).is_empty());
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+
+use ndarray::IxDyn;
+
+use super::{
+    array::Array, chrono::DateTime, dyn_array::DynArray, hash::Hash, series::TimeSeries,
+    text::Text, unit_interval::UnitInterval, uuid::Uuid, Field, Value, ValueType,
+};
+
+macro_rules! impl_convert {
+    ( $( $ty:ty => $variant:ident $( ( $param:ident ) )? , )* ) => {
+        $(
+            impl From<$ty> for Value {
+                fn from(value: $ty) -> Self {
+                    Self::$variant(value)
+                }
+            }
+
+            /// Converts the value with [`Value::cast`], so that any lossless conversion is accepted.
+            impl TryFrom<Value> for $ty {
+                type Error = ::anyhow::Error;
+
+                fn try_from(value: Value) -> Result<Self, Self::Error> {
+                    let ty = ValueType::$variant $( (Box::new(ValueType::$param)) )?;
+                    let value = match value {
+                        Value::$variant(value) => return Ok(value),
+                        value => value.cast(ty.clone())?,
+                    };
+                    match value {
+                        Value::$variant(value) => Ok(value),
+                        _ => unreachable!("the value should be casted into {ty:?}"),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_convert!(
+    bool => Bool,
+    i8 => I8,
+    i16 => I16,
+    i32 => I32,
+    i64 => I64,
+    u8 => U8,
+    u16 => U16,
+    u32 => U32,
+    u64 => U64,
+    f32 => F32,
+    f64 => F64,
+    Vec<u8> => Bytes,
+    String => String,
+    Text => Text,
+    i128 => I128,
+    u128 => U128,
+    DateTime => DateTime,
+    Uuid => Uuid,
+    Hash => Hash,
+    UnitInterval => UnitInterval,
+    Array<Value, IxDyn> => Array,
+    DynArray => Tensor(Dyn),
+    TimeSeries => TimeSeries,
+);
+
+impl From<()> for Value {
+    fn from((): ()) -> Self {
+        Self::None
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<&[u8]> for Value {
+    fn from(value: &[u8]) -> Self {
+        Self::Bytes(value.to_vec())
+    }
+}
+
+impl<T> From<Option<T>> for Value
+where
+    T: Into<Value>,
+{
+    fn from(value: Option<T>) -> Self {
+        value.map(Into::into).unwrap_or(Self::None)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(value: Vec<Value>) -> Self {
+        Self::List(value)
+    }
+}
+
+impl From<BTreeMap<String, Value>> for Value {
+    fn from(value: BTreeMap<String, Value>) -> Self {
+        Self::Map(value)
+    }
+}
+
+impl From<Vec<Field>> for Value {
+    fn from(value: Vec<Field>) -> Self {
+        Self::Record(value)
+    }
+}
+
+impl TryFrom<Value> for () {
+    type Error = ::anyhow::Error;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::None => Ok(()),
+            value => ::anyhow::bail!(
+                "cannot cast {:?} into {:?}",
+                value.value_type(),
+                ValueType::None
+            ),
+        }
+    }
+}
+
+impl TryFrom<Value> for Vec<Value> {
+    type Error = ::anyhow::Error;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::List(values) => Ok(values),
+            Value::Array(values) => Ok(values.iter().cloned().collect()),
+            value => ::anyhow::bail!(
+                "cannot cast {:?} into {:?}",
+                value.value_type(),
+                ValueType::List(Box::new(ValueType::Dyn)),
+            ),
+        }
+    }
+}
+
+impl TryFrom<Value> for BTreeMap<String, Value> {
+    type Error = ::anyhow::Error;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Map(values) => Ok(values),
+            Value::Record(fields) => Ok(fields
+                .into_iter()
+                .map(|field| (field.name, field.value))
+                .collect()),
+            value => ::anyhow::bail!(
+                "cannot cast {:?} into {:?}",
+                value.value_type(),
+                ValueType::Map(Box::new(ValueType::Dyn)),
+            ),
+        }
+    }
+}
+
+impl TryFrom<Value> for Vec<Field> {
+    type Error = ::anyhow::Error;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Record(fields) => Ok(fields),
+            Value::Map(values) => Ok(values
+                .into_iter()
+                .map(|(name, value)| Field { name, value })
+                .collect()),
+            value => ::anyhow::bail!(
+                "cannot cast {:?} into {:?}",
+                value.value_type(),
+                ValueType::Record(Vec::new()),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert() {
+        assert_eq!(Value::from(42i32), Value::I32(42));
+        assert_eq!(Value::from("hello"), Value::String("hello".into()));
+        assert_eq!(Value::from(None::<u8>), Value::None);
+        assert_eq!(Value::from(Some(true)), Value::Bool(true));
+
+        // lossless conversions are accepted
+        assert_eq!(i64::try_from(Value::I32(-42)).unwrap(), -42);
+        assert_eq!(u8::try_from(Value::String("7".into())).unwrap(), 7);
+        assert!(u8::try_from(Value::I32(-1)).is_err());
+        assert!(bool::try_from(Value::None).is_err());
+
+        let uuid = Uuid(::uuid::Uuid::new_v4());
+        assert_eq!(Uuid::try_from(Value::from(uuid)).unwrap(), uuid);
+
+        let fields = vec![Field {
+            name: "id".into(),
+            value: Value::U64(1),
+        }];
+        let map = BTreeMap::try_from(Value::from(fields.clone())).unwrap();
+        assert_eq!(Vec::<Field>::try_from(Value::from(map)).unwrap(), fields);
+    }
+}
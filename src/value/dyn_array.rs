@@ -0,0 +1,341 @@
+//! Arrays whose element types are known only at runtime.
+
+use anyhow::{anyhow, bail, Result};
+use bytecheck::CheckBytes;
+use ndarray::{Dimension, IntoDimension, IxDyn};
+use rkyv::{from_archived, Archive, Deserialize, Infallible, Serialize};
+
+use super::{
+    array::{ArchivedArrayRaw, Array},
+    hash::Hash,
+    Value, ValueType,
+};
+use crate::{canonical::Canonical, schema::HasSchema};
+
+/// An n-dimensional array of primitive elements, tagged by the element type.
+#[derive(Clone, Debug, PartialEq, Archive, Serialize, Deserialize, HasSchema)]
+#[archive(bound(
+    serialize = "__S: ::rkyv::ser::ScratchSpace + ::rkyv::ser::Serializer",
+    deserialize = "__D::Error: From<::ndarray::ShapeError>",
+))]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+pub enum DynArray {
+    Bool(Array<bool, IxDyn>),
+    I8(Array<i8, IxDyn>),
+    I16(Array<i16, IxDyn>),
+    I32(Array<i32, IxDyn>),
+    I64(Array<i64, IxDyn>),
+    U8(Array<u8, IxDyn>),
+    U16(Array<u16, IxDyn>),
+    U32(Array<u32, IxDyn>),
+    U64(Array<u64, IxDyn>),
+    F32(Array<f32, IxDyn>),
+    F64(Array<f64, IxDyn>),
+    I128(Array<i128, IxDyn>),
+    U128(Array<u128, IxDyn>),
+}
+
+/// Elements of `DynArray`s.
+pub trait DynElement:
+    Archive<Archived: ::core::fmt::Debug + PartialEq> + Clone + Into<Value>
+{
+    const TYPE: ValueType;
+
+    fn downcast_ref(array: &DynArray) -> Option<&Array<Self, IxDyn>>;
+
+    fn downcast(array: DynArray) -> Result<Array<Self, IxDyn>, DynArray>;
+
+    fn downcast_archived(array: &ArchivedDynArray) -> Option<&ArchivedArrayRaw<Self, Vec<usize>>>;
+}
+
+macro_rules! impl_dyn_array {
+    ( $( $variant:ident ( $ty:ty ) ),* $(,)? ) => {
+        $(
+            impl DynElement for $ty {
+                const TYPE: ValueType = ValueType::$variant;
+
+                fn downcast_ref(array: &DynArray) -> Option<&Array<Self, IxDyn>> {
+                    match array {
+                        DynArray::$variant(array) => Some(array),
+                        _ => None,
+                    }
+                }
+
+                fn downcast(array: DynArray) -> Result<Array<Self, IxDyn>, DynArray> {
+                    match array {
+                        DynArray::$variant(array) => Ok(array),
+                        array => Err(array),
+                    }
+                }
+
+                fn downcast_archived(
+                    array: &ArchivedDynArray,
+                ) -> Option<&ArchivedArrayRaw<Self, Vec<usize>>> {
+                    match array {
+                        ArchivedDynArray::$variant(array) => Some(array),
+                        _ => None,
+                    }
+                }
+            }
+
+            impl From<Array<$ty, IxDyn>> for DynArray {
+                fn from(array: Array<$ty, IxDyn>) -> Self {
+                    Self::$variant(array)
+                }
+            }
+        )*
+
+        impl DynArray {
+            /// Returns the type of the elements.
+            pub fn element_type(&self) -> ValueType {
+                match self {
+                    $( Self::$variant(_) => ValueType::$variant, )*
+                }
+            }
+
+            pub fn shape(&self) -> &[usize] {
+                match self {
+                    $( Self::$variant(array) => array.shape(), )*
+                }
+            }
+
+            /// Iterates the elements as `Value`s in the logical order.
+            pub fn values(&self) -> Box<dyn Iterator<Item = Value> + '_> {
+                match self {
+                    $( Self::$variant(array) => Box::new(array.iter().map(|&item| item.into())), )*
+                }
+            }
+
+            /// Hashes the array by its content. See [`Hash::with_tensor`] for the details.
+            pub fn content_hash(&self) -> Hash {
+                match self {
+                    $( Self::$variant(array) => array.content_hash(), )*
+                }
+            }
+
+            /// Converts the elements into `Value`s.
+            pub fn to_values(&self) -> Array<Value, IxDyn> {
+                match self {
+                    $( Self::$variant(array) => Array(array.map(|&item| item.into()).into_shared()), )*
+                }
+            }
+
+            /// Converts the values into an array of the given element type,
+            /// failing if any information would be lost.
+            pub fn from_values(ty: &ValueType, values: &Array<Value, IxDyn>) -> Result<Self> {
+                match ty {
+                    $(
+                        ValueType::$variant => values
+                            .iter()
+                            .map(|value| value.clone().try_into())
+                            .collect::<Result<_>>()
+                            .and_then(|data| {
+                                ::ndarray::ArcArray::from_shape_vec(values.raw_dim(), data)
+                                    .map_err(Into::into)
+                            })
+                            .map(Array)
+                            .map(Self::$variant),
+                    )*
+                    ty => bail!("unsupported element type: {ty:?}"),
+                }
+            }
+        }
+
+        impl ArchivedDynArray {
+            /// Returns the type of the elements.
+            pub fn element_type(&self) -> ValueType {
+                match self {
+                    $( Self::$variant(_) => ValueType::$variant, )*
+                }
+            }
+
+            pub fn shape(&self) -> Vec<usize> {
+                match self {
+                    $(
+                        Self::$variant(array) => array
+                            .dim
+                            .iter()
+                            .map(|dim| from_archived!(*dim) as usize)
+                            .collect(),
+                    )*
+                }
+            }
+
+            /// Iterates the elements as `Value`s in the logical order.
+            pub fn values(&self) -> Box<dyn Iterator<Item = Value> + '_> {
+                match self {
+                    $(
+                        Self::$variant(array) => Box::new(array.iter().map(|item| {
+                            let Ok(item): Result<$ty, _> = item.deserialize(&mut Infallible);
+                            item.into()
+                        })),
+                    )*
+                }
+            }
+
+            /// Hashes the archived array by its content, as the unarchived one.
+            pub fn content_hash(&self) -> Hash {
+                match self {
+                    $( Self::$variant(array) => array.content_hash(), )*
+                }
+            }
+        }
+    };
+}
+
+impl_dyn_array!(
+    Bool(bool),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    F32(f32),
+    F64(f64),
+    I128(i128),
+    U128(u128),
+);
+
+impl DynArray {
+    /// Borrows the array if the elements are of the given type.
+    pub fn downcast_ref<A: DynElement>(&self) -> Option<&Array<A, IxDyn>> {
+        A::downcast_ref(self)
+    }
+
+    /// Takes the array if the elements are of the given type.
+    pub fn downcast<A: DynElement>(self) -> Result<Array<A, IxDyn>> {
+        A::downcast(self).map_err(|array| {
+            anyhow!(
+                "element type mismatching: expected {:?}, found {:?}",
+                A::TYPE,
+                array.element_type(),
+            )
+        })
+    }
+
+    /// Converts the values into an array, inferring the element type from the first one.
+    pub fn try_from_values(values: &Array<Value, IxDyn>) -> Result<Self> {
+        match values.iter().next() {
+            Some(value) => Self::from_values(&value.value_type(), values),
+            None => bail!("cannot infer the element type of an empty array"),
+        }
+    }
+}
+
+impl ArchivedDynArray {
+    /// Borrows the archived array if the elements are of the given type.
+    ///
+    /// See also [`ArchivedArrayRaw::view`] to view the elements without copying them.
+    pub fn downcast_ref<A: DynElement>(&self) -> Option<&ArchivedArrayRaw<A, Vec<usize>>> {
+        A::downcast_archived(self)
+    }
+}
+
+impl<A, D> Array<A, D>
+where
+    A: DynElement + Canonical,
+    D: Dimension,
+{
+    /// Hashes the array by its content. See [`Hash::with_tensor`] for the details.
+    pub fn content_hash(&self) -> Hash {
+        Hash::with_tensor(&A::TYPE, self.shape(), self.iter())
+    }
+}
+
+impl<A, P> ArchivedArrayRaw<A, P>
+where
+    A: DynElement + Canonical,
+    <A as Archive>::Archived: Deserialize<A, Infallible>,
+    P: Archive + IntoDimension,
+    <P as Archive>::Archived: Deserialize<P, Infallible>,
+    <Vec<A> as Archive>::Archived: ::core::fmt::Debug + PartialEq,
+    <P as Archive>::Archived: ::core::fmt::Debug + PartialEq,
+{
+    /// Hashes the archived array by its content, as the unarchived one.
+    pub fn content_hash(&self) -> Hash {
+        let Ok(dim) = Deserialize::<P, _>::deserialize(&self.dim, &mut Infallible);
+        let dim = dim.into_dimension();
+        let elements = self.iter().map(|item| {
+            let Ok(item): Result<A, _> = item.deserialize(&mut Infallible);
+            item
+        });
+        Hash::with_tensor(&A::TYPE, dim.slice(), elements)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::array::is_native_byte_order, *};
+
+    #[test]
+    fn test_dyn_array() {
+        let matrix = ::ndarray::arr2(&[[1.5f32, 2.0], [3.0, 4.5]]).into_dyn();
+        let array = DynArray::from(Array(matrix.clone().into_shared()));
+        assert_eq!(array.element_type(), ValueType::F32);
+        assert_eq!(array.shape(), &[2, 2]);
+
+        // downcast
+        assert!(array.downcast_ref::<f64>().is_none());
+        assert_eq!(array.downcast_ref::<f32>().unwrap().0, matrix);
+        assert!(array.clone().downcast::<u8>().is_err());
+
+        // values
+        let values = array.to_values();
+        assert_eq!(values[[1, 0]], Value::F32(3.0));
+        assert_eq!(DynArray::try_from_values(&values).unwrap(), array);
+        assert!(DynArray::from_values(&ValueType::I32, &values).is_err());
+        assert_eq!(
+            DynArray::from_values(&ValueType::F64, &values)
+                .unwrap()
+                .element_type(),
+            ValueType::F64,
+        );
+        assert!(DynArray::from_values(&ValueType::String, &values).is_err());
+
+        // archive
+        let bytes = ::rkyv::to_bytes::<_, 4096>(&array).unwrap();
+        let archived = ::rkyv::check_archived_root::<DynArray>(&bytes).unwrap();
+        assert_eq!(archived, &array);
+        assert_eq!(archived.element_type(), ValueType::F32);
+        assert_eq!(archived.shape(), [2, 2]);
+        assert!(archived.values().eq(values.iter().cloned()));
+        assert!(archived.downcast_ref::<i32>().is_none());
+        let archived = archived.downcast_ref::<f32>().unwrap();
+        if is_native_byte_order() {
+            assert_eq!(archived.view().unwrap(), matrix);
+        } else {
+            assert!(archived.view().is_err());
+        }
+
+        // deserialize
+        let deserialized: DynArray = crate::signed::from_bytes(&bytes).unwrap();
+        assert_eq!(deserialized, array);
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let matrix = ::ndarray::arr2(&[[1i32, 2, 3], [4, 5, 6]]);
+        let hash = Array(matrix.clone().into_shared()).content_hash();
+
+        // the shape and the element type matter
+        let reshaped = matrix.clone().into_shape((3, 2)).unwrap();
+        assert_ne!(Array(reshaped.into_shared()).content_hash(), hash);
+        let casted = matrix.mapv(|item| item as u32);
+        assert_ne!(Array(casted.into_shared()).content_hash(), hash);
+
+        // but the memory layout does not
+        let transposed = matrix.t().to_owned().reversed_axes();
+        assert_eq!(Array(transposed.clone().into_shared()).content_hash(), hash);
+
+        // nor the archive
+        let array = DynArray::from(Array(transposed.into_dyn().into_shared()));
+        assert_eq!(array.content_hash(), hash);
+        let bytes = ::rkyv::to_bytes::<_, 4096>(&array).unwrap();
+        let archived = ::rkyv::check_archived_root::<DynArray>(&bytes).unwrap();
+        assert_eq!(archived.content_hash(), hash);
+    }
+}
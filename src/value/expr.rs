@@ -0,0 +1,960 @@
+//! Predicates over dynamic values, such as `score > 0.7 and "news" in tags`.
+//!
+//! The syntax, from the loosest binding:
+//!
+//! - `a or b`, `a and b`, `not a`
+//! - `x == y`, `x != y`, `x < y`, `x <= y`, `x > y`, `x >= y`
+//! - `x in [y, z]` where `x` equals one of the items, `x starts_with "prefix"`, `x between min and max` (inclusive)
+//! - fields: `name`, `user.name`, or `$."any name"` where `$` is the whole value
+//! - literals: `none`, `true`, `42`, `0.7`, `"string"`, `[1, 2]`,
+//!   and typed ones such as `u8:"7"` or `datetime:"2022-01-01T00:00:00Z"`
+//!
+//! Numbers are compared by their values regardless of the types.
+//! Comparisons between missing fields or values of different kinds are false,
+//! except `!=`, which is the negation of `==`.
+
+use std::{cmp::Ordering, fmt};
+
+use anyhow::{anyhow, bail, Result};
+use bytecheck::CheckBytes;
+use rkyv::{from_archived, Archive, Deserialize, Serialize};
+
+use super::{cast::Number, ArchivedValue, Value, ValueType};
+use crate::{schema::HasSchema, signed::MAX_DEPTH};
+
+#[derive(Clone, Debug, PartialEq, Eq, Archive, Serialize, Deserialize, HasSchema)]
+#[archive(bound(
+    serialize = "__S: ::rkyv::ser::ScratchSpace + ::rkyv::ser::Serializer",
+    deserialize = "
+        __D::Error: From<::ndarray::ShapeError>
+            + From<super::chrono::NaiveDateTimeRangeError>
+            + From<::language_tags::ParseError>,
+    ",
+))]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+#[archive_attr(check_bytes(
+    bound = "__C: ::rkyv::validation::ArchiveContext, <__C as ::rkyv::Fallible>::Error: ::std::error::Error"
+))]
+pub enum Expr {
+    Literal(Value),
+    /// The value at the path of fields, or nothing if missing.
+    Field(Vec<String>),
+    Not(
+        #[omit_bounds]
+        #[archive_attr(omit_bounds)]
+        Box<Expr>,
+    ),
+    /// True if all of the predicates are true, or if there is none.
+    And(
+        #[omit_bounds]
+        #[archive_attr(omit_bounds)]
+        Vec<Expr>,
+    ),
+    /// True if any of the predicates is true.
+    Or(
+        #[omit_bounds]
+        #[archive_attr(omit_bounds)]
+        Vec<Expr>,
+    ),
+    Compare {
+        op: CompareOp,
+        #[omit_bounds]
+        #[archive_attr(omit_bounds)]
+        lhs: Box<Expr>,
+        #[omit_bounds]
+        #[archive_attr(omit_bounds)]
+        rhs: Box<Expr>,
+    },
+    /// The value equals any element of the list or array.
+    In {
+        #[omit_bounds]
+        #[archive_attr(omit_bounds)]
+        value: Box<Expr>,
+        #[omit_bounds]
+        #[archive_attr(omit_bounds)]
+        list: Box<Expr>,
+    },
+    /// The string or bytes start with the prefix.
+    StartsWith {
+        #[omit_bounds]
+        #[archive_attr(omit_bounds)]
+        value: Box<Expr>,
+        #[omit_bounds]
+        #[archive_attr(omit_bounds)]
+        prefix: Box<Expr>,
+    },
+    /// The value lies between the bounds, inclusive.
+    Between {
+        #[omit_bounds]
+        #[archive_attr(omit_bounds)]
+        value: Box<Expr>,
+        #[omit_bounds]
+        #[archive_attr(omit_bounds)]
+        min: Box<Expr>,
+        #[omit_bounds]
+        #[archive_attr(omit_bounds)]
+        max: Box<Expr>,
+    },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Archive, Serialize, Deserialize, HasSchema)]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Copy, Clone, Debug, PartialEq, Eq, Hash))]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CompareOp {
+    const SYMBOLS: &'static [(Self, &'static str)] = &[
+        (Self::Eq, "=="),
+        (Self::Ne, "!="),
+        (Self::Lt, "<"),
+        (Self::Le, "<="),
+        (Self::Gt, ">"),
+        (Self::Ge, ">="),
+    ];
+
+    pub fn symbol(&self) -> &'static str {
+        Self::SYMBOLS
+            .iter()
+            .find(|(op, _)| op == self)
+            .map(|(_, symbol)| *symbol)
+            .unwrap()
+    }
+
+    fn test(&self, ordering: Option<Ordering>) -> bool {
+        match self {
+            Self::Eq => ordering == Some(Ordering::Equal),
+            Self::Ne => ordering != Some(Ordering::Equal),
+            Self::Lt => ordering == Some(Ordering::Less),
+            Self::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            Self::Gt => ordering == Some(Ordering::Greater),
+            Self::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+        }
+    }
+}
+
+impl Expr {
+    /// Tests the predicate against the value, such as a record.
+    pub fn eval(&self, value: &Value) -> bool {
+        self.test(value)
+    }
+
+    /// Tests the predicate against the archived value, without deserializing it.
+    pub fn eval_archived(&self, value: &ArchivedValue) -> bool {
+        self.test(value)
+    }
+
+    fn test<R: Operand>(&self, record: &R) -> bool {
+        match self {
+            Self::Literal(_) | Self::Field(_) => {
+                matches!(self.term(record).scalar(), Some(Scalar::Bool(true)))
+            }
+            Self::Not(expr) => !expr.test(record),
+            Self::And(exprs) => exprs.iter().all(|expr| expr.test(record)),
+            Self::Or(exprs) => exprs.iter().any(|expr| expr.test(record)),
+            Self::Compare { op, lhs, rhs } => op.test(lhs.term(record).compare(&rhs.term(record))),
+            Self::In { value, list } => {
+                let value = value.term(record);
+                match list.term(record).items() {
+                    Some(items) => items
+                        .iter()
+                        .any(|item| value.compare(item) == Some(Ordering::Equal)),
+                    None => false,
+                }
+            }
+            Self::StartsWith { value, prefix } => {
+                match (value.term(record).scalar(), prefix.term(record).scalar()) {
+                    (Some(Scalar::Str(value)), Some(Scalar::Str(prefix))) => {
+                        value.starts_with(prefix)
+                    }
+                    (Some(Scalar::Bytes(value)), Some(Scalar::Bytes(prefix))) => {
+                        value.starts_with(prefix)
+                    }
+                    _ => false,
+                }
+            }
+            Self::Between { value, min, max } => {
+                let value = value.term(record);
+                CompareOp::Ge.test(value.compare(&min.term(record)))
+                    && CompareOp::Le.test(value.compare(&max.term(record)))
+            }
+        }
+    }
+
+    fn term<'a, R: Operand>(&'a self, record: &'a R) -> Term<'a, R> {
+        match self {
+            Self::Literal(value) => Term::Literal(value),
+            Self::Field(path) => path
+                .iter()
+                .try_fold(record, |value, name| value.field(name))
+                .map(Term::Record)
+                .unwrap_or(Term::Missing),
+            expr => Term::Bool(expr.test(record)),
+        }
+    }
+}
+
+/// A value that predicates are tested against, either native or archived.
+trait Operand {
+    fn field(&self, name: &str) -> Option<&Self>;
+
+    /// The elements of a list or an array.
+    fn items(&self) -> Option<Vec<&Self>>;
+
+    fn scalar(&self) -> Option<Scalar<'_>>;
+}
+
+impl Operand for Value {
+    fn field(&self, name: &str) -> Option<&Self> {
+        match self {
+            Self::Map(values) => values.get(name),
+            Self::Record(fields) => fields
+                .iter()
+                .find(|field| field.name == name)
+                .map(|field| &field.value),
+            _ => None,
+        }
+    }
+
+    fn items(&self) -> Option<Vec<&Self>> {
+        match self {
+            Self::Array(values) => Some(values.iter().collect()),
+            Self::List(values) => Some(values.iter().collect()),
+            _ => None,
+        }
+    }
+
+    fn scalar(&self) -> Option<Scalar<'_>> {
+        Some(match self {
+            Self::None => Scalar::None,
+            Self::Bool(value) => Scalar::Bool(*value),
+            Self::Bytes(value) => Scalar::Bytes(value),
+            Self::String(value) => Scalar::Str(value),
+            Self::Text(value) => Scalar::Str(&value.msg),
+            Self::DateTime(value) => {
+                Scalar::DateTime(value.timestamp(), value.timestamp_subsec_nanos())
+            }
+            Self::Uuid(value) => Scalar::Uuid(value.as_u128()),
+            Self::Hash(value) => Scalar::Bytes(value.as_slice()),
+            value => Scalar::Number(value.to_number()?),
+        })
+    }
+}
+
+impl Operand for ArchivedValue {
+    fn field(&self, name: &str) -> Option<&Self> {
+        match self {
+            Self::Map(values) => values.get(name),
+            Self::Record(fields) => fields
+                .iter()
+                .find(|field| field.name.as_str() == name)
+                .map(|field| &field.value),
+            _ => None,
+        }
+    }
+
+    fn items(&self) -> Option<Vec<&Self>> {
+        match self {
+            Self::Array(values) => Some(values.iter().collect()),
+            Self::List(values) => Some(values.iter().collect()),
+            _ => None,
+        }
+    }
+
+    fn scalar(&self) -> Option<Scalar<'_>> {
+        Some(match self {
+            Self::None => Scalar::None,
+            Self::Bool(value) => Scalar::Bool(*value),
+            Self::Bytes(value) => Scalar::Bytes(value.as_slice()),
+            Self::String(value) => Scalar::Str(value.as_str()),
+            Self::Text(value) => Scalar::Str(value.msg.as_str()),
+            Self::DateTime(value) => {
+                let (secs, nanos) = value.timestamp();
+                Scalar::DateTime(secs, nanos)
+            }
+            Self::Uuid(value) => Scalar::Uuid(from_archived!(*value)),
+            Self::Hash(value) => Scalar::Bytes(&value[..]),
+            value => Scalar::Number(value.to_number()?),
+        })
+    }
+}
+
+/// An evaluated operand, borrowed from either the expression or the record.
+enum Term<'a, R> {
+    Literal(&'a Value),
+    Record(&'a R),
+    Bool(bool),
+    Missing,
+}
+
+impl<'a, R: Operand> Term<'a, R> {
+    fn scalar(&self) -> Option<Scalar<'a>> {
+        match self {
+            Self::Literal(value) => value.scalar(),
+            Self::Record(value) => value.scalar(),
+            Self::Bool(value) => Some(Scalar::Bool(*value)),
+            Self::Missing => None,
+        }
+    }
+
+    fn items(&self) -> Option<Vec<Self>> {
+        match self {
+            Self::Literal(value) => value
+                .items()
+                .map(|items| items.into_iter().map(Self::Literal).collect()),
+            Self::Record(value) => value
+                .items()
+                .map(|items| items.into_iter().map(Self::Record).collect()),
+            Self::Bool(_) | Self::Missing => None,
+        }
+    }
+
+    fn compare(&self, other: &Self) -> Option<Ordering> {
+        if let (Some(a), Some(b)) = (self.scalar(), other.scalar()) {
+            return a.compare(&b);
+        }
+
+        // lists are compared lexicographically
+        let (a, b) = (self.items()?, other.items()?);
+        for (a, b) in a.iter().zip(&b) {
+            match a.compare(b)? {
+                Ordering::Equal => continue,
+                ordering => return Some(ordering),
+            }
+        }
+        Some(a.len().cmp(&b.len()))
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+enum Scalar<'a> {
+    None,
+    Bool(bool),
+    Number(Number),
+    Str(&'a str),
+    Bytes(&'a [u8]),
+    DateTime(i64, u32),
+    Uuid(u128),
+}
+
+impl<'a> Scalar<'a> {
+    fn compare(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Self::None, Self::None) => Some(Ordering::Equal),
+            (Self::Bool(a), Self::Bool(b)) => a.partial_cmp(b),
+            (Self::Number(a), Self::Number(b)) => a.compare(*b),
+            (Self::Str(a), Self::Str(b)) => a.partial_cmp(b),
+            (Self::Bytes(a), Self::Bytes(b)) => a.partial_cmp(b),
+            (Self::DateTime(a_secs, a_nanos), Self::DateTime(b_secs, b_nanos)) => {
+                (a_secs, a_nanos).partial_cmp(&(b_secs, b_nanos))
+            }
+            (Self::Uuid(a), Self::Uuid(b)) => a.partial_cmp(b),
+            _ => None,
+        }
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "and",
+    "between",
+    "false",
+    "in",
+    "none",
+    "not",
+    "or",
+    "starts_with",
+    "true",
+];
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Literal(value) => fmt_literal(value, f),
+            Self::Field(path) => fmt_path(path, f),
+            Self::Not(expr) => {
+                f.write_str("not ")?;
+                expr.fmt_child(f, matches!(**expr, Self::And(_) | Self::Or(_)))
+            }
+            Self::And(exprs) => fmt_joined(exprs, " and ", "true", f, |expr| {
+                matches!(expr, Self::And(_) | Self::Or(_))
+            }),
+            Self::Or(exprs) => fmt_joined(exprs, " or ", "false", f, |expr| {
+                matches!(expr, Self::Or(_))
+            }),
+            Self::Compare { op, lhs, rhs } => {
+                lhs.fmt_operand(f)?;
+                write!(f, " {} ", op.symbol())?;
+                rhs.fmt_operand(f)
+            }
+            Self::In { value, list } => {
+                value.fmt_operand(f)?;
+                f.write_str(" in ")?;
+                list.fmt_operand(f)
+            }
+            Self::StartsWith { value, prefix } => {
+                value.fmt_operand(f)?;
+                f.write_str(" starts_with ")?;
+                prefix.fmt_operand(f)
+            }
+            Self::Between { value, min, max } => {
+                value.fmt_operand(f)?;
+                f.write_str(" between ")?;
+                min.fmt_operand(f)?;
+                f.write_str(" and ")?;
+                max.fmt_operand(f)
+            }
+        }
+    }
+}
+
+impl Expr {
+    fn fmt_child(&self, f: &mut fmt::Formatter<'_>, parenthesized: bool) -> fmt::Result {
+        if parenthesized {
+            write!(f, "({self})")
+        } else {
+            write!(f, "{self}")
+        }
+    }
+
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_child(f, !matches!(self, Self::Literal(_) | Self::Field(_)))
+    }
+}
+
+fn fmt_joined(
+    exprs: &[Expr],
+    separator: &str,
+    empty: &str,
+    f: &mut fmt::Formatter<'_>,
+    parenthesized: impl Fn(&Expr) -> bool,
+) -> fmt::Result {
+    match exprs {
+        [] => f.write_str(empty),
+        [expr] => write!(f, "{expr}"),
+        exprs => {
+            for (index, expr) in exprs.iter().enumerate() {
+                if index > 0 {
+                    f.write_str(separator)?;
+                }
+                expr.fmt_child(f, parenthesized(expr))?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn fmt_path(path: &[String], f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match path.first() {
+        Some(name) if is_ident(name) => f.write_str(name)?,
+        _ => {
+            f.write_str("$")?;
+            if let Some(name) = path.first() {
+                f.write_str(".")?;
+                fmt_quoted(name, f)?;
+            }
+        }
+    }
+    for name in path.iter().skip(1) {
+        f.write_str(".")?;
+        if is_ident(name) {
+            f.write_str(name)?;
+        } else {
+            fmt_quoted(name, f)?;
+        }
+    }
+    Ok(())
+}
+
+fn fmt_literal(value: &Value, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match value {
+        Value::None => f.write_str("none"),
+        Value::Bool(value) => write!(f, "{value}"),
+        Value::I64(value) => write!(f, "{value}"),
+        Value::F64(value) if value.is_finite() => write!(f, "{value:?}"),
+        Value::String(value) => fmt_quoted(value, f),
+        Value::List(values) => {
+            f.write_str("[")?;
+            for (index, value) in values.iter().enumerate() {
+                if index > 0 {
+                    f.write_str(", ")?;
+                }
+                fmt_literal(value, f)?;
+            }
+            f.write_str("]")
+        }
+        Value::Dyn | Value::Array(_) | Value::Map(_) | Value::Record(_) => {
+            // the composite values are written as typed elements
+            let ty = value.value_type();
+            write!(f, "{}:", ValueType::Dyn.name())?;
+            fmt_quoted(&format!("{ty}:{value}"), f)
+        }
+        value => {
+            write!(f, "{}:", value.value_type().name())?;
+            fmt_quoted(&value.to_string(), f)
+        }
+    }
+}
+
+fn fmt_quoted(literal: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in literal.chars() {
+        if matches!(c, '"' | '\\') {
+            f.write_str("\\")?;
+        }
+        write!(f, "{c}")?;
+    }
+    f.write_str("\"")
+}
+
+fn is_ident(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !KEYWORDS.contains(&name)
+}
+
+impl ::core::str::FromStr for Expr {
+    type Err = ::anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            pos: 0,
+            depth: 0,
+        };
+        let expr = parser.or()?;
+        match parser.next() {
+            Some(token) => bail!("failed to parse Expr: unexpected {token:?}"),
+            None => Ok(expr),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(String),
+    Quoted(String),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: &[&str] = &[
+    "==", "!=", "<=", ">=", "<", ">", "(", ")", "[", "]", ",", ".", ":", "$",
+];
+
+fn tokenize(s: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = s.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let len = if c.is_ascii_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..len].to_string()));
+            len
+        } else if c.is_ascii_digit()
+            || (c == '-' && rest[1..].starts_with(|c: char| c.is_ascii_digit()))
+        {
+            let mut len = 1;
+            let mut prev = c;
+            for c in rest[1..].chars() {
+                let exponent_sign = matches!(c, '+' | '-') && matches!(prev, 'e' | 'E');
+                if !(c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E') || exponent_sign) {
+                    break;
+                }
+                len += 1;
+                prev = c;
+            }
+            tokens.push(Token::Number(rest[..len].to_string()));
+            len
+        } else if c == '"' {
+            let mut literal = String::new();
+            let mut chars = rest.char_indices().skip(1);
+            let len = loop {
+                match chars.next() {
+                    Some((index, '"')) => break index + 1,
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, c)) => literal.push(c),
+                        None => bail!("failed to parse Expr: unterminated quotes"),
+                    },
+                    Some((_, c)) => literal.push(c),
+                    None => bail!("failed to parse Expr: unterminated quotes"),
+                }
+            };
+            tokens.push(Token::Quoted(literal));
+            len
+        } else {
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| rest.starts_with(*symbol))
+                .ok_or_else(|| anyhow!("failed to parse Expr: unexpected {c:?}"))?;
+            tokens.push(Token::Symbol(symbol));
+            symbol.len()
+        };
+        rest = rest[len..].trim_start();
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// The number of the enclosing `not`s, parentheses and brackets, up to `MAX_DEPTH`.
+    depth: usize,
+}
+
+impl Parser {
+    /// Parses the nested expression, failing instead of overflowing the stack if it is too deep.
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.depth >= MAX_DEPTH {
+            bail!("failed to parse Expr: nested deeper than {MAX_DEPTH}");
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Ident(ident)) if ident == keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<()> {
+        if self.eat_symbol(symbol) {
+            Ok(())
+        } else {
+            bail!(
+                "failed to parse Expr: expected {symbol:?}, found {:?}",
+                self.peek()
+            )
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut exprs = vec![self.and()?];
+        while self.eat_keyword("or") {
+            exprs.push(self.and()?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.pop().unwrap()
+        } else {
+            Expr::Or(exprs)
+        })
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut exprs = vec![self.not()?];
+        while self.eat_keyword("and") {
+            exprs.push(self.not()?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.pop().unwrap()
+        } else {
+            Expr::And(exprs)
+        })
+    }
+
+    fn not(&mut self) -> Result<Expr> {
+        if self.eat_keyword("not") {
+            self.nested(Self::not).map(Box::new).map(Expr::Not)
+        } else {
+            self.predicate()
+        }
+    }
+
+    fn predicate(&mut self) -> Result<Expr> {
+        let lhs = Box::new(self.operand()?);
+
+        if let Some(Token::Symbol(symbol)) = self.peek() {
+            if let Some((op, _)) = CompareOp::SYMBOLS.iter().find(|(_, s)| s == symbol) {
+                self.pos += 1;
+                let rhs = Box::new(self.operand()?);
+                return Ok(Expr::Compare { op: *op, lhs, rhs });
+            }
+        }
+        if self.eat_keyword("in") {
+            let list = Box::new(self.operand()?);
+            return Ok(Expr::In { value: lhs, list });
+        }
+        if self.eat_keyword("starts_with") {
+            let prefix = Box::new(self.operand()?);
+            return Ok(Expr::StartsWith { value: lhs, prefix });
+        }
+        if self.eat_keyword("between") {
+            let min = Box::new(self.operand()?);
+            if !self.eat_keyword("and") {
+                bail!(
+                    "failed to parse Expr: expected \"and\", found {:?}",
+                    self.peek()
+                );
+            }
+            let max = Box::new(self.operand()?);
+            return Ok(Expr::Between {
+                value: lhs,
+                min,
+                max,
+            });
+        }
+        Ok(*lhs)
+    }
+
+    fn operand(&mut self) -> Result<Expr> {
+        match self.peek() {
+            Some(Token::Symbol("(")) => {
+                self.pos += 1;
+                let expr = self.nested(Self::or)?;
+                self.expect_symbol(")")?;
+                Ok(expr)
+            }
+            Some(Token::Symbol("$")) => {
+                self.pos += 1;
+                self.path(Vec::new())
+            }
+            Some(Token::Ident(ident))
+                if !KEYWORDS.contains(&ident.as_str())
+                    && self.tokens.get(self.pos + 1) != Some(&Token::Symbol(":")) =>
+            {
+                let name = ident.clone();
+                self.pos += 1;
+                self.path(vec![name])
+            }
+            _ => self.literal().map(Expr::Literal),
+        }
+    }
+
+    fn path(&mut self, mut path: Vec<String>) -> Result<Expr> {
+        while self.eat_symbol(".") {
+            match self.next() {
+                Some(Token::Ident(name) | Token::Quoted(name)) => path.push(name),
+                token => bail!("failed to parse Expr: expected a field name, found {token:?}"),
+            }
+        }
+        Ok(Expr::Field(path))
+    }
+
+    fn literal(&mut self) -> Result<Value> {
+        match self.next() {
+            Some(Token::Symbol("[")) => self.nested(|parser| {
+                let mut values = Vec::new();
+                if !parser.eat_symbol("]") {
+                    loop {
+                        values.push(parser.literal()?);
+                        if parser.eat_symbol("]") {
+                            break;
+                        }
+                        parser.expect_symbol(",")?;
+                    }
+                }
+                Ok(Value::List(values))
+            }),
+            Some(Token::Quoted(literal)) => Ok(Value::String(literal)),
+            Some(Token::Number(literal)) => parse_number(&literal),
+            Some(Token::Ident(ident)) => match ident.as_str() {
+                "none" => Ok(Value::None),
+                "true" => Ok(Value::Bool(true)),
+                "false" => Ok(Value::Bool(false)),
+                name => {
+                    let ty: ValueType = name.parse()?;
+                    self.expect_symbol(":")?;
+                    let literal = match self.next() {
+                        Some(Token::Quoted(literal) | Token::Number(literal)) => literal,
+                        token => bail!("failed to parse Expr: expected a literal, found {token:?}"),
+                    };
+                    Value::parse(&literal, ty)
+                }
+            },
+            token => bail!("failed to parse Expr: expected an operand, found {token:?}"),
+        }
+    }
+}
+
+fn parse_number(literal: &str) -> Result<Value> {
+    if literal.contains(['.', 'e', 'E']) {
+        return literal
+            .parse()
+            .map(Value::F64)
+            .map_err(|_| anyhow!("failed to parse Expr: invalid number {literal:?}"));
+    }
+    literal
+        .parse()
+        .map(Value::I64)
+        .or_else(|_| literal.parse().map(Value::U64))
+        .or_else(|_| literal.parse().map(Value::I128))
+        .or_else(|_| literal.parse().map(Value::U128))
+        .map_err(|_| anyhow!("failed to parse Expr: invalid number {literal:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{chrono::DateTime, Field},
+        *,
+    };
+
+    fn record() -> Value {
+        Value::Record(vec![
+            Field {
+                name: "score".into(),
+                value: Value::F32(0.75),
+            },
+            Field {
+                name: "count".into(),
+                value: Value::U8(3),
+            },
+            Field {
+                name: "title".into(),
+                value: Value::String("ipi: hello".into()),
+            },
+            Field {
+                name: "tags".into(),
+                value: Value::List(vec![Value::String("news".into())]),
+            },
+            Field {
+                name: "user".into(),
+                value: Value::Map([("name".to_string(), Value::String("alice".into()))].into()),
+            },
+            Field {
+                name: "created at".into(),
+                value: Value::DateTime(DateTime(
+                    ::chrono::DateTime::parse_from_rfc3339("2022-06-01T00:00:00Z")
+                        .unwrap()
+                        .into(),
+                )),
+            },
+            Field {
+                name: "active".into(),
+                value: Value::Bool(true),
+            },
+        ])
+    }
+
+    #[test]
+    fn test_expr_eval() {
+        let record = record();
+        let bytes = ::rkyv::to_bytes::<_, 4096>(&record).unwrap();
+        let archived = ::rkyv::check_archived_root::<Value>(&bytes).unwrap();
+
+        let cases = [
+            ("score > 0.7", true),
+            ("score > 0.8", false),
+            ("count == 3 and count >= u64:\"3\"", true),
+            ("count between 1 and 2", false),
+            ("count between -1 and 3.5", true),
+            ("title starts_with \"ipi:\"", true),
+            ("\"news\" in tags", true),
+            // the list itself is not one of the items
+            ("tags in [\"news\", \"sports\"]", false),
+            ("count in [1, 2, 3]", true),
+            ("user.name == \"alice\"", true),
+            ("user.name != \"bob\" and not (user.age > 20)", true),
+            ("missing == none or missing != 1", true),
+            ("missing == none", false),
+            ("$.\"created at\" < datetime:\"2023-01-01T00:00:00Z\"", true),
+            ("active and (score < 0.5 or count > 2)", true),
+            ("title > 3", false),
+            ("(count > 1) == true", true),
+        ];
+        for (expr, expected) in cases {
+            let parsed: Expr = expr.parse().unwrap();
+            assert_eq!(parsed.eval(&record), expected, "{expr}");
+            assert_eq!(parsed.eval_archived(archived), expected, "{expr}");
+
+            // the displayed expression is parsed back
+            assert_eq!(
+                parsed.to_string().parse::<Expr>().unwrap(),
+                parsed,
+                "{expr}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_expr_parse() {
+        let expr: Expr = "a.b >= -1 and (c or not d)".parse().unwrap();
+        assert_eq!(
+            expr,
+            Expr::And(vec![
+                Expr::Compare {
+                    op: CompareOp::Ge,
+                    lhs: Box::new(Expr::Field(vec!["a".into(), "b".into()])),
+                    rhs: Box::new(Expr::Literal(Value::I64(-1))),
+                },
+                Expr::Or(vec![
+                    Expr::Field(vec!["c".into()]),
+                    Expr::Not(Box::new(Expr::Field(vec!["d".into()]))),
+                ]),
+            ]),
+        );
+        assert_eq!(expr.to_string(), "a.b >= -1 and (c or not d)");
+
+        let expr = Expr::In {
+            value: Box::new(Expr::Literal(Value::Map(
+                [("k".to_string(), Value::U8(1))].into(),
+            ))),
+            list: Box::new(Expr::Literal(Value::List(vec![Value::F64(f64::NAN)]))),
+        };
+        assert_eq!(expr.to_string().parse::<Expr>().unwrap(), expr);
+
+        assert!("a ==".parse::<Expr>().is_err());
+        assert!("a between 1".parse::<Expr>().is_err());
+        assert!("(a".parse::<Expr>().is_err());
+        assert!("a b".parse::<Expr>().is_err());
+
+        // too deep expressions fail instead of overflowing the stack
+        let nested = |depth| format!("{}a", "not ".repeat(depth));
+        assert!(nested(MAX_DEPTH).parse::<Expr>().is_ok());
+        assert!(nested(MAX_DEPTH + 1).parse::<Expr>().is_err());
+        assert!(nested(1_000_000).parse::<Expr>().is_err());
+        assert!(format!("{}a", "(".repeat(1_000_000))
+            .parse::<Expr>()
+            .is_err());
+        assert!(format!("a in {}", "[".repeat(1_000_000))
+            .parse::<Expr>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_expr_rkyv() {
+        let expr: Expr = "score > 0.7 and \"news\" in tags".parse().unwrap();
+
+        // serialize
+        let bytes = ::rkyv::to_bytes::<_, 4096>(&expr).unwrap();
+
+        // compare the archived expression
+        let archived = ::rkyv::check_archived_root::<Expr>(&bytes).unwrap();
+        assert_eq!(archived, &expr);
+
+        // deserialize
+        let deserialized: Expr = crate::signed::from_bytes(&bytes).unwrap();
+        assert_eq!(&expr, &deserialized);
+    }
+}
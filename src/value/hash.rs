@@ -1,9 +1,14 @@
+use std::io::{self, Read, Write};
+
 use anyhow::anyhow;
 use base58::{FromBase58, ToBase58};
 use generic_array::GenericArray;
 use rkyv::{Archive, Deserialize, Fallible, Serialize};
 use sha2::{digest::OutputSizeUser, Digest, Sha256, Sha256VarCore};
 
+use super::{array::Order, ValueType};
+use crate::canonical::{write_variant, Canonical};
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Hash(pub GenericArray<u8, <Sha256VarCore as OutputSizeUser>::OutputSize>);
 
@@ -88,3 +93,85 @@ impl Hash {
         Self::with_bytes(msg.as_bytes())
     }
 }
+
+impl Hash {
+    pub fn with_reader(reader: impl Read) -> io::Result<Self> {
+        let mut hasher = Hasher::default();
+        hasher.update_reader(reader)?;
+        Ok(hasher.finalize())
+    }
+
+    /// Hashes a tensor by its content: the element type, the shape, the layout and the elements.
+    ///
+    /// The elements should be given in the logical (row-major) order, and are hashed in their
+    /// canonical form piece by piece, so the hash depends on neither the memory layout of the
+    /// array nor the archive format.
+    pub fn with_tensor<A>(
+        ty: &ValueType,
+        shape: &[usize],
+        elements: impl IntoIterator<Item = A>,
+    ) -> Self
+    where
+        A: Canonical,
+    {
+        const BUFFER_SIZE: usize = 4096;
+
+        let mut buf = Vec::with_capacity(BUFFER_SIZE);
+        write_variant("Tensor", &mut buf);
+        ty.write_canonical(&mut buf);
+        shape.write_canonical(&mut buf);
+        Order::RowMajor.write_canonical(&mut buf);
+
+        let mut hasher = Hasher::default();
+        for element in elements {
+            if buf.len() >= BUFFER_SIZE {
+                hasher.update(&buf);
+                buf.clear();
+            }
+            element.write_canonical(&mut buf);
+        }
+        hasher.update(&buf);
+        hasher.finalize()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Hasher {
+    inner: Sha256,
+    len: u64,
+}
+
+impl Write for Hasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Hasher {
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.inner.update(bytes);
+        self.len += bytes.len() as u64;
+    }
+
+    pub fn update_reader(&mut self, mut reader: impl Read) -> io::Result<u64> {
+        ::std::io::copy(&mut reader, self)
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn finalize(self) -> Hash {
+        Hash(self.inner.finalize())
+    }
+}
@@ -1,18 +1,49 @@
+pub mod aggregate;
+mod arith;
 pub mod array;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+mod cast;
 pub mod chrono;
+pub mod compressed;
+mod convert;
+pub mod dyn_array;
+pub mod expr;
 pub mod hash;
 pub mod nonce;
+#[cfg(feature = "npy")]
+pub mod npy;
+mod ord;
+mod parse;
 pub mod primitives;
+pub mod schema;
+pub mod serde;
+pub mod series;
 pub mod text;
 pub mod unit_interval;
 pub mod uuid;
 
+use std::collections::BTreeMap;
+
 use bytecheck::CheckBytes;
+use ndarray::IxDyn;
 use rkyv::{Archive, Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, Archive, Serialize, Deserialize)]
-#[archive(compare(PartialEq))]
-#[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+use crate::schema::HasSchema;
+
+#[derive(Clone, Debug, Archive, Serialize, Deserialize, HasSchema)]
+#[archive(bound(
+    serialize = "__S: ::rkyv::ser::ScratchSpace + ::rkyv::ser::Serializer",
+    deserialize = "
+        __D::Error: From<::ndarray::ShapeError>
+            + From<self::chrono::NaiveDateTimeRangeError>
+            + From<::language_tags::ParseError>,
+    ",
+))]
+#[archive_attr(derive(CheckBytes, Debug))]
+#[archive_attr(check_bytes(
+    bound = "__C: ::rkyv::validation::ArchiveContext, <__C as ::rkyv::Fallible>::Error: ::std::error::Error"
+))]
 pub enum Value {
     None,
     Dyn,
@@ -30,13 +61,72 @@ pub enum Value {
     Bytes(Vec<u8>),
     String(String),
     Text(self::text::Text),
+    I128(i128),
+    U128(u128),
+    DateTime(self::chrono::DateTime),
+    Uuid(self::uuid::Uuid),
+    Hash(self::hash::Hash),
+    UnitInterval(self::unit_interval::UnitInterval),
+    Array(
+        #[omit_bounds]
+        #[archive_attr(omit_bounds)]
+        self::array::Array<Value, IxDyn>,
+    ),
+    List(
+        #[omit_bounds]
+        #[archive_attr(omit_bounds)]
+        Vec<Value>,
+    ),
+    Map(
+        #[omit_bounds]
+        #[archive_attr(omit_bounds)]
+        BTreeMap<String, Value>,
+    ),
+    Record(
+        #[omit_bounds]
+        #[archive_attr(omit_bounds)]
+        Vec<Field>,
+    ),
+    /// An array of primitive elements, which keeps the element type at runtime.
+    Tensor(self::dyn_array::DynArray),
+    /// A compressed series of timestamped samples.
+    TimeSeries(self::series::TimeSeries),
+}
+
+/// A named value of a `Record`, which keeps its fields in order.
+#[derive(
+    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Archive, Serialize, Deserialize, HasSchema,
+)]
+#[archive(bound(
+    serialize = "__S: ::rkyv::ser::ScratchSpace + ::rkyv::ser::Serializer",
+    deserialize = "
+        __D::Error: From<::ndarray::ShapeError>
+            + From<self::chrono::NaiveDateTimeRangeError>
+            + From<::language_tags::ParseError>,
+    ",
+))]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq, Eq, PartialOrd, Ord, Hash))]
+#[archive_attr(check_bytes(
+    bound = "__C: ::rkyv::validation::ArchiveContext, <__C as ::rkyv::Fallible>::Error: ::std::error::Error"
+))]
+pub struct Field {
+    pub name: String,
+    #[omit_bounds]
+    #[archive_attr(omit_bounds)]
+    #[schema(name = "Value")]
+    pub value: Value,
 }
 
 #[derive(
-    Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Archive, Serialize, Deserialize,
+    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Archive, Serialize, Deserialize, HasSchema,
 )]
-#[archive(compare(PartialEq, PartialOrd))]
-#[archive_attr(derive(CheckBytes, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash))]
+#[archive(bound(serialize = "__S: ::rkyv::ser::ScratchSpace + ::rkyv::ser::Serializer"))]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq, Eq, Hash))]
+#[archive_attr(check_bytes(
+    bound = "__C: ::rkyv::validation::ArchiveContext, <__C as ::rkyv::Fallible>::Error: ::std::error::Error"
+))]
 pub enum ValueType {
     None,
     Dyn,
@@ -54,4 +144,255 @@ pub enum ValueType {
     Bytes,
     String,
     Text,
+    I128,
+    U128,
+    DateTime,
+    Uuid,
+    Hash,
+    UnitInterval,
+    Array,
+    /// A list of the elements of the given type.
+    List(
+        #[omit_bounds]
+        #[archive_attr(omit_bounds)]
+        Box<ValueType>,
+    ),
+    /// A map from strings to the values of the given type.
+    Map(
+        #[omit_bounds]
+        #[archive_attr(omit_bounds)]
+        Box<ValueType>,
+    ),
+    /// A record of the given fields, in order.
+    Record(
+        #[omit_bounds]
+        #[archive_attr(omit_bounds)]
+        Vec<FieldType>,
+    ),
+    /// A tensor of the elements of the given type, or of any primitive type if `Dyn`.
+    Tensor(
+        #[omit_bounds]
+        #[archive_attr(omit_bounds)]
+        Box<ValueType>,
+    ),
+    TimeSeries,
+}
+
+#[derive(
+    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Archive, Serialize, Deserialize, HasSchema,
+)]
+#[archive(bound(serialize = "__S: ::rkyv::ser::ScratchSpace + ::rkyv::ser::Serializer"))]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq, Eq, Hash))]
+#[archive_attr(check_bytes(
+    bound = "__C: ::rkyv::validation::ArchiveContext, <__C as ::rkyv::Fallible>::Error: ::std::error::Error"
+))]
+pub struct FieldType {
+    pub name: String,
+    #[omit_bounds]
+    #[archive_attr(omit_bounds)]
+    #[schema(name = "ValueType")]
+    pub ty: ValueType,
+}
+
+impl Value {
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            Self::None => ValueType::None,
+            Self::Dyn => ValueType::Dyn,
+            Self::Bool(_) => ValueType::Bool,
+            Self::I8(_) => ValueType::I8,
+            Self::I16(_) => ValueType::I16,
+            Self::I32(_) => ValueType::I32,
+            Self::I64(_) => ValueType::I64,
+            Self::U8(_) => ValueType::U8,
+            Self::U16(_) => ValueType::U16,
+            Self::U32(_) => ValueType::U32,
+            Self::U64(_) => ValueType::U64,
+            Self::F32(_) => ValueType::F32,
+            Self::F64(_) => ValueType::F64,
+            Self::Bytes(_) => ValueType::Bytes,
+            Self::String(_) => ValueType::String,
+            Self::Text(_) => ValueType::Text,
+            Self::I128(_) => ValueType::I128,
+            Self::U128(_) => ValueType::U128,
+            Self::DateTime(_) => ValueType::DateTime,
+            Self::Uuid(_) => ValueType::Uuid,
+            Self::Hash(_) => ValueType::Hash,
+            Self::UnitInterval(_) => ValueType::UnitInterval,
+            Self::Array(_) => ValueType::Array,
+            Self::List(values) => ValueType::List(Box::new(Self::common_type(values.iter()))),
+            Self::Map(values) => ValueType::Map(Box::new(Self::common_type(values.values()))),
+            Self::Record(fields) => ValueType::Record(
+                fields
+                    .iter()
+                    .map(|field| FieldType {
+                        name: field.name.clone(),
+                        ty: field.value.value_type(),
+                    })
+                    .collect(),
+            ),
+            Self::Tensor(value) => ValueType::Tensor(Box::new(value.element_type())),
+            Self::TimeSeries(_) => ValueType::TimeSeries,
+        }
+    }
+
+    /// Returns the type shared by all the values, or `Dyn` if there is none.
+    fn common_type<'a>(mut values: impl Iterator<Item = &'a Self>) -> ValueType {
+        let ty = match values.next() {
+            Some(value) => value.value_type(),
+            None => return ValueType::Dyn,
+        };
+        if values.all(|value| value.value_type() == ty) {
+            ty
+        } else {
+            ValueType::Dyn
+        }
+    }
+}
+
+impl ValueType {
+    /// Returns the zero value of the type, such as `0`, an empty string or an empty tensor.
+    ///
+    /// Fails if the type is a tensor of unsupported elements, such as `tensor<string>`.
+    pub fn default_value(&self) -> ::anyhow::Result<Value> {
+        Ok(match self {
+            Self::None => Value::None,
+            Self::Dyn => Value::Dyn,
+            Self::Bool => Value::Bool(false),
+            Self::I8 => Value::I8(0),
+            Self::I16 => Value::I16(0),
+            Self::I32 => Value::I32(0),
+            Self::I64 => Value::I64(0),
+            Self::U8 => Value::U8(0),
+            Self::U16 => Value::U16(0),
+            Self::U32 => Value::U32(0),
+            Self::U64 => Value::U64(0),
+            Self::F32 => Value::F32(0.0),
+            Self::F64 => Value::F64(0.0),
+            Self::Bytes => Value::Bytes(Vec::new()),
+            Self::String => Value::String(String::new()),
+            Self::Text => Value::Text(Default::default()),
+            Self::I128 => Value::I128(0),
+            Self::U128 => Value::U128(0),
+            Self::DateTime => Value::DateTime(self::chrono::DateTime(::chrono::DateTime::<
+                ::chrono::Utc,
+            >::from_utc(
+                ::chrono::NaiveDateTime::from_timestamp(0, 0),
+                ::chrono::Utc,
+            ))),
+            Self::Uuid => Value::Uuid(self::uuid::Uuid(::uuid::Uuid::nil())),
+            Self::Hash => Value::Hash(self::hash::Hash(Default::default())),
+            Self::UnitInterval => {
+                Value::UnitInterval(self::unit_interval::UnitInterval(Default::default()))
+            }
+            Self::Array => Value::Array(self::array::Array(
+                ::ndarray::ArcArray::from_shape_vec(IxDyn(&[0]), Vec::new()).unwrap(),
+            )),
+            Self::List(_) => Value::List(Vec::new()),
+            Self::Map(_) => Value::Map(BTreeMap::new()),
+            Self::Record(fields) => Value::Record(
+                fields
+                    .iter()
+                    .map(|field| {
+                        Ok(Field {
+                            name: field.name.clone(),
+                            value: field.ty.default_value()?,
+                        })
+                    })
+                    .collect::<::anyhow::Result<_>>()?,
+            ),
+            Self::Tensor(ty) => {
+                let values = self::array::Array(
+                    ::ndarray::ArcArray::from_shape_vec(IxDyn(&[0]), Vec::new()).unwrap(),
+                );
+                Value::Tensor(self::dyn_array::DynArray::from_values(ty, &values)?)
+            }
+            Self::TimeSeries => Value::TimeSeries(Default::default()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value_rkyv() {
+        let matrix = ::ndarray::arr2(&[
+            [Value::I128(-1), Value::U128(2)],
+            [Value::None, Value::Bool(true)],
+        ]);
+
+        let values = [
+            Value::I128(i128::MIN),
+            Value::U128(u128::MAX),
+            Value::DateTime(self::chrono::DateTime(::chrono::Utc::now())),
+            Value::Uuid(self::uuid::Uuid(::uuid::Uuid::new_v4())),
+            Value::Hash(self::hash::Hash::with_str("hello")),
+            Value::UnitInterval(self::unit_interval::UnitInterval(
+                ::fixed::types::U0F32::from_bits(1 << 31),
+            )),
+            Value::Array(self::array::Array(matrix.into_dyn().into_shared())),
+            Value::List(vec![Value::I8(1), Value::List(vec![])]),
+            Value::Map([("key".to_string(), Value::Bool(true))].into()),
+            Value::Record(vec![Field {
+                name: "id".into(),
+                value: Value::U64(42),
+            }]),
+            Value::Tensor(self::dyn_array::DynArray::from(self::array::Array(
+                ::ndarray::arr2(&[[1u16, 2], [3, 4]])
+                    .into_dyn()
+                    .into_shared(),
+            ))),
+            Value::TimeSeries(
+                self::series::TimeSeries::try_from_samples([
+                    (self::chrono::DateTime(::chrono::Utc::now()), 1.5),
+                    (self::chrono::DateTime(::chrono::Utc::now()), f64::NAN),
+                ])
+                .unwrap(),
+            ),
+        ];
+
+        for value in values {
+            // serialize
+            let bytes = ::rkyv::to_bytes::<_, 4096>(&value).unwrap();
+
+            // compare the archived value
+            let archived = ::rkyv::check_archived_root::<Value>(&bytes).unwrap();
+            assert_eq!(archived, &value);
+
+            // deserialize
+            let deserialized: Value = crate::signed::from_bytes(&bytes).unwrap();
+            assert_eq!(&value, &deserialized);
+        }
+    }
+
+    #[test]
+    fn test_value_depth_limit() {
+        fn nested(depth: usize) -> Value {
+            (0..depth).fold(Value::None, |value, _| Value::List(vec![value]))
+        }
+
+        // building and dropping the deep values need a larger stack than checking them
+        let archive = |depth| {
+            ::std::thread::Builder::new()
+                .stack_size(1 << 30)
+                .spawn(move || ::rkyv::to_bytes::<_, 4096>(&nested(depth)).unwrap())
+                .unwrap()
+                .join()
+                .unwrap()
+        };
+
+        // the root takes a level
+        let depth = crate::signed::MAX_DEPTH - 1;
+        let value: Value = crate::signed::from_bytes(&archive(depth)).unwrap();
+        assert_eq!(value, nested(depth));
+
+        for depth in [crate::signed::MAX_DEPTH, 100_000] {
+            let bytes = archive(depth);
+            assert!(crate::signed::check_archived_root::<Value>(&bytes).is_err());
+            assert!(crate::signed::from_bytes::<Value>(&bytes).is_err());
+        }
+    }
 }
@@ -0,0 +1,493 @@
+//! Imports and exports of NumPy `.npy` files and `.npz` archives.
+//!
+//! The arrays are written in little-endian, and in the Fortran order if they are column-major,
+//! so that NumPy loads them as they are.
+//! Both byte orders and memory orders are accepted when reading.
+
+use std::{
+    collections::BTreeMap,
+    io::{Read, Seek, Write},
+};
+
+use anyhow::{anyhow, bail, Result};
+use ndarray::{ArrayBase, Data, Dimension, IxDyn, ShapeBuilder};
+
+use super::{array::Array, compressed::Element, dyn_array::DynArray};
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+/// The alignment of the elements, which NumPy expects for memory mapping.
+const ALIGNMENT: usize = 64;
+
+/// Elements which have NumPy `dtype`s.
+pub trait NpyElement: Element {
+    /// The kind of the `dtype`, such as `f` of `<f4`.
+    const KIND: char;
+}
+
+macro_rules! impl_npy_element {
+    ( $( $ty:ty => $kind:expr ),* ) => {
+        $(
+            impl NpyElement for $ty {
+                const KIND: char = $kind;
+            }
+        )*
+    };
+}
+
+impl_npy_element!(
+    bool => 'b',
+    i8 => 'i',
+    i16 => 'i',
+    i32 => 'i',
+    i64 => 'i',
+    u8 => 'u',
+    u16 => 'u',
+    u32 => 'u',
+    u64 => 'u',
+    f32 => 'f',
+    f64 => 'f'
+);
+
+impl<A, D> Array<A, D>
+where
+    A: NpyElement,
+    D: Dimension,
+{
+    /// Reads an array from a `.npy` file, failing if the `dtype` or the dimensionality mismatches.
+    pub fn read_npy(mut reader: impl Read) -> Result<Self> {
+        Header::read(&mut reader)?.read_data(reader)
+    }
+
+    pub fn write_npy(&self, writer: impl Write) -> Result<()> {
+        write_npy(&self.0, writer)
+    }
+}
+
+impl DynArray {
+    /// Reads an array of any supported `dtype` from a `.npy` file.
+    pub fn read_npy(mut reader: impl Read) -> Result<Self> {
+        let header = Header::read(&mut reader)?;
+
+        macro_rules! read_data {
+            ( $( $ty:ty ),* ) => {
+                $(
+                    if header.matches::<$ty>() {
+                        return header.read_data::<$ty, IxDyn>(reader).map(Into::into);
+                    }
+                )*
+            };
+        }
+        read_data!(bool, i8, i16, i32, i64, u8, u16, u32, u64, f32, f64);
+
+        bail!("unsupported dtype: {:?}", header.descr())
+    }
+
+    /// Writes the array into a `.npy` file, failing if NumPy has no such `dtype`, like `i128`.
+    pub fn write_npy(&self, writer: impl Write) -> Result<()> {
+        match self {
+            Self::Bool(array) => array.write_npy(writer),
+            Self::I8(array) => array.write_npy(writer),
+            Self::I16(array) => array.write_npy(writer),
+            Self::I32(array) => array.write_npy(writer),
+            Self::I64(array) => array.write_npy(writer),
+            Self::U8(array) => array.write_npy(writer),
+            Self::U16(array) => array.write_npy(writer),
+            Self::U32(array) => array.write_npy(writer),
+            Self::U64(array) => array.write_npy(writer),
+            Self::F32(array) => array.write_npy(writer),
+            Self::F64(array) => array.write_npy(writer),
+            Self::I128(_) | Self::U128(_) => {
+                bail!("unsupported dtype: {:?}", self.element_type())
+            }
+        }
+    }
+}
+
+/// Reads the arrays of a `.npz` archive, by their names without the `.npy` extensions.
+pub fn read_npz(reader: impl Read + Seek) -> Result<BTreeMap<String, DynArray>> {
+    let mut archive = ::zip::ZipArchive::new(reader)?;
+    (0..archive.len())
+        .map(|index| {
+            let file = archive.by_index(index)?;
+            let name = file.name();
+            let name = name.strip_suffix(".npy").unwrap_or(name).to_string();
+            DynArray::read_npy(file).map(|array| (name, array))
+        })
+        .collect()
+}
+
+/// Writes the arrays into a `.npz` archive, deflating them like `numpy.savez_compressed` if `compressed`.
+pub fn write_npz<'a>(
+    writer: impl Write + Seek,
+    arrays: impl IntoIterator<Item = (&'a str, &'a DynArray)>,
+    compressed: bool,
+) -> Result<()> {
+    let method = if compressed {
+        ::zip::CompressionMethod::Deflated
+    } else {
+        ::zip::CompressionMethod::Stored
+    };
+    let options = ::zip::write::FileOptions::default().compression_method(method);
+
+    let mut archive = ::zip::ZipWriter::new(writer);
+    for (name, array) in arrays {
+        archive.start_file(format!("{name}.npy"), options)?;
+        array.write_npy(&mut archive)?;
+    }
+    archive.finish().map(|_| ()).map_err(Into::into)
+}
+
+fn write_npy<A, S, D>(array: &ArrayBase<S, D>, mut writer: impl Write) -> Result<()>
+where
+    A: NpyElement,
+    S: Data<Elem = A>,
+    D: Dimension,
+{
+    // the column-major arrays are written as they are in memory
+    let fortran_order = !array.is_standard_layout() && array.t().is_standard_layout();
+
+    let shape = match array.shape() {
+        [dim] => format!("({dim},)"),
+        shape => {
+            let dims: Vec<_> = shape.iter().map(ToString::to_string).collect();
+            format!("({})", dims.join(", "))
+        }
+    };
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': {}, 'shape': {shape}, }}",
+        Header::descr_of::<A>(),
+        if fortran_order { "True" } else { "False" },
+    );
+
+    // the version 2.0 is used only if the header is too long for the version 1.0
+    let (version, prefix) = if header.len() + ALIGNMENT < u16::MAX as usize {
+        (1, MAGIC.len() + 4)
+    } else {
+        (2, MAGIC.len() + 6)
+    };
+    let padding = ALIGNMENT - (prefix + header.len() + 1) % ALIGNMENT;
+    header.extend(::core::iter::repeat_n(' ', padding % ALIGNMENT));
+    header.push('\n');
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[version, 0])?;
+    match version {
+        1 => writer.write_all(&(header.len() as u16).to_le_bytes())?,
+        _ => writer.write_all(&u32::try_from(header.len())?.to_le_bytes())?,
+    }
+    writer.write_all(header.as_bytes())?;
+
+    let transposed = array.t();
+    let items = if fortran_order {
+        transposed.iter()
+    } else {
+        array.iter()
+    };
+    let mut bytes = Vec::with_capacity(array.len() * A::SIZE);
+    for item in items {
+        item.write_le(&mut bytes);
+    }
+    writer.write_all(&bytes).map_err(Into::into)
+}
+
+/// The header of a `.npy` file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Header {
+    little_endian: bool,
+    kind: char,
+    size: usize,
+    fortran_order: bool,
+    shape: Vec<usize>,
+}
+
+impl Header {
+    fn read(reader: &mut impl Read) -> Result<Self> {
+        let mut prefix = [0; 8];
+        reader.read_exact(&mut prefix)?;
+        if &prefix[..MAGIC.len()] != MAGIC {
+            bail!("not a .npy file");
+        }
+
+        let len = match prefix[6] {
+            1 => {
+                let mut len = [0; 2];
+                reader.read_exact(&mut len)?;
+                u16::from_le_bytes(len) as usize
+            }
+            2 | 3 => {
+                let mut len = [0; 4];
+                reader.read_exact(&mut len)?;
+                u32::from_le_bytes(len) as usize
+            }
+            major => bail!("unsupported .npy version: {major}"),
+        };
+
+        let mut header = Vec::new();
+        reader.take(len as u64).read_to_end(&mut header)?;
+        if header.len() != len {
+            bail!(
+                "expected {len} bytes of the .npy header, found {}",
+                header.len()
+            );
+        }
+        Self::parse(::core::str::from_utf8(&header)?)
+    }
+
+    /// Parses the Python dictionary literal, such as
+    /// `{'descr': '<f4', 'fortran_order': False, 'shape': (2, 3), }`.
+    fn parse(header: &str) -> Result<Self> {
+        let descr = field(header, "descr")?;
+        let descr = descr
+            .strip_prefix('\'')
+            .and_then(|descr| descr.split_once('\''))
+            .map(|(descr, _)| descr)
+            .ok_or_else(|| anyhow!("unsupported dtype: {descr:?}"))?;
+        let mut chars = descr.chars();
+        let little_endian = match chars.next() {
+            Some('<') => true,
+            Some('>') => false,
+            Some('|' | '=') => cfg!(target_endian = "little"),
+            _ => bail!("unsupported dtype: {descr:?}"),
+        };
+        let kind = chars
+            .next()
+            .ok_or_else(|| anyhow!("unsupported dtype: {descr:?}"))?;
+        let size = chars
+            .as_str()
+            .parse()
+            .map_err(|_| anyhow!("unsupported dtype: {descr:?}"))?;
+
+        let fortran_order = match field(header, "fortran_order")? {
+            rest if rest.starts_with("True") => true,
+            rest if rest.starts_with("False") => false,
+            rest => bail!("invalid fortran_order: {rest:?}"),
+        };
+
+        let shape = field(header, "shape")?;
+        let shape = shape
+            .strip_prefix('(')
+            .and_then(|shape| shape.split_once(')'))
+            .map(|(shape, _)| shape)
+            .ok_or_else(|| anyhow!("invalid shape: {shape:?}"))?
+            .split(',')
+            .map(str::trim)
+            .filter(|dim| !dim.is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            little_endian,
+            kind,
+            size,
+            fortran_order,
+            shape,
+        })
+    }
+
+    fn descr(&self) -> String {
+        let order = match (self.size, self.little_endian) {
+            (1, _) => '|',
+            (_, true) => '<',
+            (_, false) => '>',
+        };
+        format!("{order}{}{}", self.kind, self.size)
+    }
+
+    fn descr_of<A: NpyElement>() -> String {
+        Self {
+            little_endian: true,
+            kind: A::KIND,
+            size: A::SIZE,
+            fortran_order: false,
+            shape: vec![],
+        }
+        .descr()
+    }
+
+    fn matches<A: NpyElement>(&self) -> bool {
+        self.kind == A::KIND && self.size == A::SIZE
+    }
+
+    fn read_data<A, D>(&self, reader: impl Read) -> Result<Array<A, D>>
+    where
+        A: NpyElement,
+        D: Dimension,
+    {
+        if !self.matches::<A>() {
+            bail!(
+                "dtype mismatching: expected {}, found {}",
+                Self::descr_of::<A>(),
+                self.descr(),
+            );
+        }
+
+        let size = self
+            .shape
+            .iter()
+            .try_fold(A::SIZE, |size, &dim| size.checked_mul(dim))
+            .ok_or_else(|| anyhow!("array too large: {:?}", &self.shape))?;
+        let mut bytes = Vec::new();
+        reader.take(size as u64).read_to_end(&mut bytes)?;
+        if bytes.len() != size {
+            bail!("expected {size} bytes of elements, found {}", bytes.len());
+        }
+
+        let data = bytes
+            .chunks_exact_mut(A::SIZE)
+            .map(|item| {
+                if !self.little_endian {
+                    item.reverse();
+                }
+                A::read_le(item)
+            })
+            .collect();
+        let shape = IxDyn(&self.shape).set_f(self.fortran_order);
+        ::ndarray::ArcArray::from_shape_vec(shape, data)?
+            .into_dimensionality()
+            .map(Array)
+            .map_err(Into::into)
+    }
+}
+
+/// Returns the literal after the key of the Python dictionary.
+fn field<'a>(header: &'a str, key: &str) -> Result<&'a str> {
+    header
+        .find(&format!("'{key}'"))
+        .map(|index| &header[index + key.len() + 2..])
+        .and_then(|rest| rest.trim_start().strip_prefix(':'))
+        .map(str::trim_start)
+        .ok_or_else(|| anyhow!("missing {key:?} in the .npy header"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use ndarray::{Ix2, Ix3};
+
+    use super::*;
+
+    #[test]
+    fn test_npy() {
+        let matrix = ::ndarray::arr2(&[[1.5f32, 2.0, -3.0], [4.0, f32::NAN, 6.25]]);
+        let layouts = [
+            matrix.clone().into_shared(),
+            matrix.t().to_owned().reversed_axes().into_shared(),
+        ];
+
+        for array in layouts {
+            let fortran_order = !array.is_standard_layout();
+
+            let mut bytes = Vec::new();
+            Array(array.clone()).write_npy(&mut bytes).unwrap();
+            assert_eq!(&bytes[..MAGIC.len()], MAGIC);
+            assert_eq!((bytes.len() - 6 * 4) % ALIGNMENT, 0);
+
+            let header = Header::read(&mut bytes.as_slice()).unwrap();
+            assert_eq!(header.descr(), "<f4");
+            assert_eq!(header.fortran_order, fortran_order);
+            assert_eq!(header.shape, [2, 3]);
+
+            // fixed dimensions
+            let read = Array::<f32, Ix2>::read_npy(bytes.as_slice()).unwrap();
+            assert_eq!(read.0.is_standard_layout(), !fortran_order);
+            assert_eq!(read.0.shape(), matrix.shape());
+            assert!(read
+                .0
+                .iter()
+                .zip(matrix.iter())
+                .all(|(a, b)| a.to_bits() == b.to_bits()));
+
+            // dynamic dimensions
+            let read = Array::<f32, IxDyn>::read_npy(bytes.as_slice()).unwrap();
+            assert_eq!(read.0.shape(), [2, 3]);
+            let read = DynArray::read_npy(bytes.as_slice()).unwrap();
+            assert_eq!(read.shape(), [2, 3]);
+            let mut written = Vec::new();
+            read.write_npy(&mut written).unwrap();
+            assert_eq!(written, bytes);
+
+            // mismatching types
+            assert!(Array::<f64, Ix2>::read_npy(bytes.as_slice()).is_err());
+            assert!(Array::<f32, Ix3>::read_npy(bytes.as_slice()).is_err());
+
+            // truncated data
+            assert!(Array::<f32, Ix2>::read_npy(&bytes[..bytes.len() - 1]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_npy_big_endian() {
+        let header = "{'descr': '>i2', 'fortran_order': False, 'shape': (3,), }";
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend([1, 0]);
+        bytes.extend((header.len() as u16).to_le_bytes());
+        bytes.extend(header.as_bytes());
+        bytes.extend([0x00, 0x01, 0xff, 0xfe, 0x01, 0x00]);
+
+        let array = DynArray::read_npy(bytes.as_slice()).unwrap();
+        assert_eq!(
+            array.downcast_ref::<i16>().unwrap().0,
+            ::ndarray::arr1(&[1, -2, 256]).into_dyn(),
+        );
+
+        let header = Header::parse("{'descr': '<U8', 'fortran_order': False, 'shape': (), }");
+        assert_eq!(header.unwrap().descr(), "<U8");
+        assert!(Header::parse("{'descr': [('a', '<f4')], 'shape': (), }").is_err());
+    }
+
+    #[test]
+    fn test_npy_malformed_header() {
+        for descr in ["'é4'", "'<é'", "'<'", "''", "'<f'", "'<f4"] {
+            let header = format!("{{'descr': {descr}, 'fortran_order': False, 'shape': (1,), }}");
+            assert!(Header::parse(&header).is_err(), "{header}");
+
+            let mut bytes = MAGIC.to_vec();
+            bytes.extend([1, 0]);
+            bytes.extend((header.len() as u16).to_le_bytes());
+            bytes.extend(header.as_bytes());
+            bytes.extend([0; 4]);
+            assert!(DynArray::read_npy(bytes.as_slice()).is_err(), "{header}");
+        }
+    }
+
+    #[test]
+    fn test_npz() {
+        let arrays: BTreeMap<_, _> = [
+            (
+                "x",
+                DynArray::from(Array(
+                    ::ndarray::arr2(&[[true, false], [false, true]])
+                        .into_dyn()
+                        .into_shared(),
+                )),
+            ),
+            (
+                "y",
+                DynArray::from(Array(::ndarray::arr0(42u64).into_dyn().into_shared())),
+            ),
+        ]
+        .into();
+
+        for compressed in [false, true] {
+            let mut cursor = Cursor::new(Vec::new());
+            write_npz(
+                &mut cursor,
+                arrays.iter().map(|(name, array)| (*name, array)),
+                compressed,
+            )
+            .unwrap();
+
+            cursor.set_position(0);
+            let read = read_npz(cursor).unwrap();
+            assert_eq!(read.len(), 2);
+            assert_eq!(&read["x"], &arrays["x"]);
+            assert_eq!(&read["y"], &arrays["y"]);
+        }
+
+        // NumPy has no 128-bit integers
+        let array = DynArray::from(Array(::ndarray::arr1(&[1i128]).into_dyn().into_shared()));
+        assert!(write_npz(Cursor::new(Vec::new()), [("z", &array)], false).is_err());
+    }
+}
@@ -0,0 +1,520 @@
+use std::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+};
+
+use ordered_float::OrderedFloat;
+use rkyv::from_archived;
+
+use super::{cast::Number, chrono::DateTime, series::Samples, ArchivedValue, Value};
+
+/// The position of the numbers in the order, which is the one of `I8`.
+const NUMBER_RANK: u8 = 3;
+
+impl Value {
+    /// The position of the variant in the declaration order.
+    pub(super) fn rank(&self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Dyn => 1,
+            Self::Bool(_) => 2,
+            Self::I8(_) => 3,
+            Self::I16(_) => 4,
+            Self::I32(_) => 5,
+            Self::I64(_) => 6,
+            Self::U8(_) => 7,
+            Self::U16(_) => 8,
+            Self::U32(_) => 9,
+            Self::U64(_) => 10,
+            Self::F32(_) => 11,
+            Self::F64(_) => 12,
+            Self::Bytes(_) => 13,
+            Self::String(_) => 14,
+            Self::Text(_) => 15,
+            Self::I128(_) => 16,
+            Self::U128(_) => 17,
+            Self::DateTime(_) => 18,
+            Self::Uuid(_) => 19,
+            Self::Hash(_) => 20,
+            Self::UnitInterval(_) => 21,
+            Self::Array(_) => 22,
+            Self::List(_) => 23,
+            Self::Map(_) => 24,
+            Self::Record(_) => 25,
+            Self::Tensor(_) => 26,
+            Self::TimeSeries(_) => 27,
+        }
+    }
+
+    /// The position of the variant in the order,
+    /// where the numbers share the position of the first numeric variant.
+    fn class(&self) -> u8 {
+        match self.number() {
+            Some(_) => NUMBER_RANK,
+            None => self.rank(),
+        }
+    }
+
+    /// The number of the value, excluding `Bool`.
+    fn number(&self) -> Option<Number> {
+        match self {
+            Self::Bool(_) => None,
+            value => value.to_number(),
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Value {}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Numbers, excluding `Bool`, are ordered by their values as `Value::compare` does,
+/// then by the declaration order of the variants, so `I8(1)` comes before `U64(1)`
+/// and after `U64(0)`. `NaN`s are equal to each other and greater than any other number.
+///
+/// Values of the other variants are ordered by the declaration order of the variants,
+/// where the numbers take the place of `I8`.
+///
+/// Values of the same variant are ordered by:
+///
+/// - `Text`: the message, then the language tag as a string
+/// - `Array`: the shape, then the elements in the logical order
+/// - `Tensor`: the shape, then the elements as values in the logical order
+/// - `TimeSeries`: the samples in order, by the timestamp and then the value
+/// - `Map`: the entries in the order of the keys
+/// - `Record`: the fields in order, by the name and then the value
+/// - the others: their natural order, where sequences are compared lexicographically
+///
+/// The archived values are ordered the same way.
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if let (Some(a), Some(b)) = (self.number(), other.number()) {
+            return a.total_cmp(b).then_with(|| self.rank().cmp(&other.rank()));
+        }
+        match (self, other) {
+            (Self::None, Self::None) | (Self::Dyn, Self::Dyn) => Ordering::Equal,
+            (Self::Bool(a), Self::Bool(b)) => a.cmp(b),
+            (Self::Bytes(a), Self::Bytes(b)) => a.cmp(b),
+            (Self::String(a), Self::String(b)) => a.cmp(b),
+            (Self::Text(a), Self::Text(b)) => {
+                (&a.msg, a.lang.as_str()).cmp(&(&b.msg, b.lang.as_str()))
+            }
+            (Self::DateTime(a), Self::DateTime(b)) => a.cmp(b),
+            (Self::Uuid(a), Self::Uuid(b)) => a.cmp(b),
+            (Self::Hash(a), Self::Hash(b)) => a.cmp(b),
+            (Self::Array(a), Self::Array(b)) => a
+                .shape()
+                .cmp(b.shape())
+                .then_with(|| a.iter().cmp(b.iter())),
+            (Self::List(a), Self::List(b)) => a.cmp(b),
+            (Self::Map(a), Self::Map(b)) => a.cmp(b),
+            (Self::Record(a), Self::Record(b)) => a.cmp(b),
+            (Self::Tensor(a), Self::Tensor(b)) => a
+                .shape()
+                .cmp(b.shape())
+                .then_with(|| a.values().cmp(b.values())),
+            (Self::TimeSeries(a), Self::TimeSeries(b)) => ordered(a.iter()).cmp(ordered(b.iter())),
+            (a, b) => a.class().cmp(&b.class()),
+        }
+    }
+}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rank().hash(state);
+        match self {
+            Self::None | Self::Dyn => {}
+            Self::Bool(value) => value.hash(state),
+            Self::I8(value) => value.hash(state),
+            Self::I16(value) => value.hash(state),
+            Self::I32(value) => value.hash(state),
+            Self::I64(value) => value.hash(state),
+            Self::U8(value) => value.hash(state),
+            Self::U16(value) => value.hash(state),
+            Self::U32(value) => value.hash(state),
+            Self::U64(value) => value.hash(state),
+            Self::F32(value) => OrderedFloat(*value).hash(state),
+            Self::F64(value) => OrderedFloat(*value).hash(state),
+            Self::Bytes(value) => value.hash(state),
+            Self::String(value) => value.hash(state),
+            Self::Text(value) => (&value.msg, value.lang.as_str()).hash(state),
+            Self::I128(value) => value.hash(state),
+            Self::U128(value) => value.hash(state),
+            Self::DateTime(value) => value.hash(state),
+            Self::Uuid(value) => value.hash(state),
+            Self::Hash(value) => value.hash(state),
+            Self::UnitInterval(value) => value.hash(state),
+            Self::Array(value) => {
+                value.shape().hash(state);
+                value.iter().for_each(|value| value.hash(state));
+            }
+            Self::List(value) => value.hash(state),
+            Self::Map(value) => value.hash(state),
+            Self::Record(value) => value.hash(state),
+            Self::Tensor(value) => {
+                value.shape().hash(state);
+                value.values().for_each(|value| value.hash(state));
+            }
+            Self::TimeSeries(value) => ordered(value.iter()).for_each(|sample| sample.hash(state)),
+        }
+    }
+}
+
+impl ArchivedValue {
+    /// The position of the variant in the declaration order.
+    fn rank(&self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Dyn => 1,
+            Self::Bool(_) => 2,
+            Self::I8(_) => 3,
+            Self::I16(_) => 4,
+            Self::I32(_) => 5,
+            Self::I64(_) => 6,
+            Self::U8(_) => 7,
+            Self::U16(_) => 8,
+            Self::U32(_) => 9,
+            Self::U64(_) => 10,
+            Self::F32(_) => 11,
+            Self::F64(_) => 12,
+            Self::Bytes(_) => 13,
+            Self::String(_) => 14,
+            Self::Text(_) => 15,
+            Self::I128(_) => 16,
+            Self::U128(_) => 17,
+            Self::DateTime(_) => 18,
+            Self::Uuid(_) => 19,
+            Self::Hash(_) => 20,
+            Self::UnitInterval(_) => 21,
+            Self::Array(_) => 22,
+            Self::List(_) => 23,
+            Self::Map(_) => 24,
+            Self::Record(_) => 25,
+            Self::Tensor(_) => 26,
+            Self::TimeSeries(_) => 27,
+        }
+    }
+
+    /// The position of the variant in the order,
+    /// where the numbers share the position of the first numeric variant.
+    fn class(&self) -> u8 {
+        match self.number() {
+            Some(_) => NUMBER_RANK,
+            None => self.rank(),
+        }
+    }
+
+    /// The number of the value, excluding `Bool`.
+    fn number(&self) -> Option<Number> {
+        match self {
+            Self::Bool(_) => None,
+            value => value.to_number(),
+        }
+    }
+}
+
+impl PartialEq for ArchivedValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for ArchivedValue {}
+
+impl PartialOrd for ArchivedValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ArchivedValue {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if let (Some(a), Some(b)) = (self.number(), other.number()) {
+            return a.total_cmp(b).then_with(|| self.rank().cmp(&other.rank()));
+        }
+        match (self, other) {
+            (Self::None, Self::None) | (Self::Dyn, Self::Dyn) => Ordering::Equal,
+            (Self::Bool(a), Self::Bool(b)) => a.cmp(b),
+            (Self::Bytes(a), Self::Bytes(b)) => a.as_slice().cmp(b.as_slice()),
+            (Self::String(a), Self::String(b)) => a.as_str().cmp(b.as_str()),
+            (Self::Text(a), Self::Text(b)) => {
+                (a.msg.as_str(), a.lang.as_str()).cmp(&(b.msg.as_str(), b.lang.as_str()))
+            }
+            (Self::DateTime(a), Self::DateTime(b)) => a.cmp(b),
+            (Self::Uuid(a), Self::Uuid(b)) => a.cmp(b),
+            (Self::Hash(a), Self::Hash(b)) => a.cmp(b),
+            (Self::Array(a), Self::Array(b)) => a
+                .dim
+                .as_slice()
+                .cmp(b.dim.as_slice())
+                .then_with(|| a.iter().cmp(b.iter())),
+            (Self::List(a), Self::List(b)) => a.as_slice().cmp(b.as_slice()),
+            (Self::Map(a), Self::Map(b)) => a
+                .iter()
+                .map(|(key, value)| (key.as_str(), value))
+                .cmp(b.iter().map(|(key, value)| (key.as_str(), value))),
+            (Self::Record(a), Self::Record(b)) => a.as_slice().cmp(b.as_slice()),
+            (Self::Tensor(a), Self::Tensor(b)) => a
+                .shape()
+                .cmp(&b.shape())
+                .then_with(|| a.values().cmp(b.values())),
+            (Self::TimeSeries(a), Self::TimeSeries(b)) => ordered(a.iter()).cmp(ordered(b.iter())),
+            (a, b) => a.class().cmp(&b.class()),
+        }
+    }
+}
+
+impl ArchivedValue {
+    fn cmp_value(&self, other: &Value) -> Ordering {
+        if let (Some(a), Some(b)) = (self.number(), other.number()) {
+            return a.total_cmp(b).then_with(|| self.rank().cmp(&other.rank()));
+        }
+        match (self, other) {
+            (Self::None, Value::None) | (Self::Dyn, Value::Dyn) => Ordering::Equal,
+            (Self::Bool(a), Value::Bool(b)) => a.cmp(b),
+            (Self::Bytes(a), Value::Bytes(b)) => a.as_slice().cmp(b.as_slice()),
+            (Self::String(a), Value::String(b)) => a.as_str().cmp(b.as_str()),
+            (Self::Text(a), Value::Text(b)) => {
+                (a.msg.as_str(), a.lang.as_str()).cmp(&(b.msg.as_str(), b.lang.as_str()))
+            }
+            (Self::DateTime(a), Value::DateTime(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+            (Self::Uuid(a), Value::Uuid(b)) => from_archived!(*a).cmp(&b.as_u128()),
+            (Self::Hash(a), Value::Hash(b)) => a.as_slice().cmp(b.as_slice()),
+            (Self::Array(a), Value::Array(b)) => a
+                .dim
+                .iter()
+                .map(|dim| from_archived!(*dim) as usize)
+                .cmp(b.shape().iter().copied())
+                .then_with(|| cmp_iter(a.iter(), b.iter())),
+            (Self::List(a), Value::List(b)) => cmp_iter(a.iter(), b.iter()),
+            (Self::Map(a), Value::Map(b)) => {
+                let mut b = b.iter();
+                for (key, value) in a.iter() {
+                    let ordering = match b.next() {
+                        Some((other_key, other_value)) => key
+                            .as_str()
+                            .cmp(other_key.as_str())
+                            .then_with(|| value.cmp_value(other_value)),
+                        None => Ordering::Greater,
+                    };
+                    if ordering != Ordering::Equal {
+                        return ordering;
+                    }
+                }
+                if b.next().is_some() {
+                    Ordering::Less
+                } else {
+                    Ordering::Equal
+                }
+            }
+            (Self::Record(a), Value::Record(b)) => {
+                let mut b = b.iter();
+                for field in a.iter() {
+                    let ordering = match b.next() {
+                        Some(other) => field
+                            .name
+                            .as_str()
+                            .cmp(other.name.as_str())
+                            .then_with(|| field.value.cmp_value(&other.value)),
+                        None => Ordering::Greater,
+                    };
+                    if ordering != Ordering::Equal {
+                        return ordering;
+                    }
+                }
+                if b.next().is_some() {
+                    Ordering::Less
+                } else {
+                    Ordering::Equal
+                }
+            }
+            (Self::Tensor(a), Value::Tensor(b)) => a
+                .shape()
+                .as_slice()
+                .cmp(b.shape())
+                .then_with(|| a.values().cmp(b.values())),
+            (Self::TimeSeries(a), Value::TimeSeries(b)) => ordered(a.iter()).cmp(ordered(b.iter())),
+            (a, b) => a.class().cmp(&b.class()),
+        }
+    }
+}
+
+/// Orders the values of the samples by `ordered_float::OrderedFloat`.
+fn ordered(samples: Samples<'_>) -> impl Iterator<Item = (DateTime, OrderedFloat<f64>)> + '_ {
+    samples.map(|(time, value)| (time, OrderedFloat(value)))
+}
+
+/// Compares the archived values with the unarchived ones lexicographically.
+fn cmp_iter<'a>(
+    mut a: impl Iterator<Item = &'a ArchivedValue>,
+    mut b: impl Iterator<Item = &'a Value>,
+) -> Ordering {
+    loop {
+        match (a.next(), b.next()) {
+            (Some(a), Some(b)) => match a.cmp_value(b) {
+                Ordering::Equal => continue,
+                ordering => return ordering,
+            },
+            (Some(_), None) => return Ordering::Greater,
+            (None, Some(_)) => return Ordering::Less,
+            (None, None) => return Ordering::Equal,
+        }
+    }
+}
+
+impl PartialEq<Value> for ArchivedValue {
+    fn eq(&self, other: &Value) -> bool {
+        self.cmp_value(other) == Ordering::Equal
+    }
+}
+
+impl PartialOrd<Value> for ArchivedValue {
+    fn partial_cmp(&self, other: &Value) -> Option<Ordering> {
+        Some(self.cmp_value(other))
+    }
+}
+
+impl Hash for ArchivedValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rank().hash(state);
+        match self {
+            Self::None | Self::Dyn => {}
+            Self::Bool(value) => value.hash(state),
+            Self::I8(value) => value.hash(state),
+            Self::I16(value) => value.hash(state),
+            Self::I32(value) => value.hash(state),
+            Self::I64(value) => value.hash(state),
+            Self::U8(value) => value.hash(state),
+            Self::U16(value) => value.hash(state),
+            Self::U32(value) => value.hash(state),
+            Self::U64(value) => value.hash(state),
+            Self::F32(value) => OrderedFloat(from_archived!(*value)).hash(state),
+            Self::F64(value) => OrderedFloat(from_archived!(*value)).hash(state),
+            Self::Bytes(value) => value.as_slice().hash(state),
+            Self::String(value) => value.as_str().hash(state),
+            Self::Text(value) => (value.msg.as_str(), value.lang.as_str()).hash(state),
+            Self::I128(value) => value.hash(state),
+            Self::U128(value) => value.hash(state),
+            Self::DateTime(value) => value.hash(state),
+            Self::Uuid(value) => value.hash(state),
+            Self::Hash(value) => value.hash(state),
+            Self::UnitInterval(value) => value.hash(state),
+            Self::Array(value) => {
+                value.dim.as_slice().hash(state);
+                value.iter().for_each(|value| value.hash(state));
+            }
+            Self::List(value) => value.as_slice().hash(state),
+            Self::Map(value) => value
+                .iter()
+                .for_each(|(key, value)| (key.as_str(), value).hash(state)),
+            Self::Record(value) => value.as_slice().hash(state),
+            Self::Tensor(value) => {
+                value.shape().hash(state);
+                value.values().for_each(|value| value.hash(state));
+            }
+            Self::TimeSeries(value) => ordered(value.iter()).for_each(|sample| sample.hash(state)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::{super::Field, *};
+
+    #[test]
+    fn test_value_ord() {
+        let values = vec![
+            Value::Record(vec![Field {
+                name: "a".into(),
+                value: Value::None,
+            }]),
+            Value::F64(f64::NAN),
+            Value::String("b".into()),
+            Value::I64(-1),
+            Value::F64(-0.0),
+            Value::U8(0),
+            Value::F64(f64::NEG_INFINITY),
+            Value::String("a".into()),
+            Value::I64(-2),
+            Value::List(vec![Value::I8(1)]),
+            Value::List(vec![]),
+            Value::None,
+            Value::F64(0.0),
+            Value::U64(1),
+            Value::I8(1),
+        ];
+
+        // a total order, where -0.0 equals to 0.0, and the numbers are ordered by their values
+        let set: BTreeSet<_> = values.iter().cloned().collect();
+        assert_eq!(set.len(), values.len() - 1);
+        assert_eq!(Value::F64(f64::NAN), Value::F64(f64::NAN));
+
+        let mut sorted = values.clone();
+        sorted.sort();
+        assert_eq!(
+            sorted,
+            [
+                Value::None,
+                Value::F64(f64::NEG_INFINITY),
+                Value::I64(-2),
+                Value::I64(-1),
+                Value::U8(0),
+                Value::F64(-0.0),
+                Value::F64(0.0),
+                Value::I8(1),
+                Value::U64(1),
+                Value::F64(f64::NAN),
+                Value::String("a".into()),
+                Value::String("b".into()),
+                Value::List(vec![]),
+                Value::List(vec![Value::I8(1)]),
+                Value::Record(vec![Field {
+                    name: "a".into(),
+                    value: Value::None,
+                }]),
+            ],
+        );
+
+        // the archived values are sorted the same way
+        let bytes: Vec<_> = values
+            .iter()
+            .map(|value| ::rkyv::to_bytes::<_, 4096>(value).unwrap())
+            .collect();
+        let mut archived: Vec<_> = bytes
+            .iter()
+            .map(|bytes| ::rkyv::check_archived_root::<Value>(bytes).unwrap())
+            .collect();
+        archived.sort();
+        for (archived, value) in archived.into_iter().zip(&sorted) {
+            assert_eq!(archived, value);
+        }
+    }
+
+    #[test]
+    fn test_value_hash() {
+        fn hash(value: &impl Hash) -> u64 {
+            let mut hasher = ::std::collections::hash_map::DefaultHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        assert_eq!(hash(&Value::F32(0.0)), hash(&Value::F32(-0.0)));
+        assert_ne!(hash(&Value::I8(1)), hash(&Value::U8(1)));
+
+        let set: ::std::collections::HashSet<_> =
+            [Value::F32(f32::NAN), Value::F32(f32::NAN), Value::I8(1)].into();
+        assert_eq!(set.len(), 2);
+    }
+}
@@ -0,0 +1,734 @@
+use std::{collections::BTreeMap, fmt};
+
+use anyhow::{anyhow, bail, Result};
+use base58::FromBase58;
+use ndarray::{ArrayViewD, Axis, IxDyn};
+
+use super::{
+    array::Array, dyn_array::DynArray, series::TimeSeries, text::Text, Field, FieldType, Value,
+    ValueType,
+};
+use crate::signed::MAX_DEPTH;
+
+impl ValueType {
+    const NAMES: &'static [(Self, &'static str)] = &[
+        (Self::None, "none"),
+        (Self::Dyn, "dyn"),
+        (Self::Bool, "bool"),
+        (Self::I8, "i8"),
+        (Self::I16, "i16"),
+        (Self::I32, "i32"),
+        (Self::I64, "i64"),
+        (Self::U8, "u8"),
+        (Self::U16, "u16"),
+        (Self::U32, "u32"),
+        (Self::U64, "u64"),
+        (Self::F32, "f32"),
+        (Self::F64, "f64"),
+        (Self::Bytes, "bytes"),
+        (Self::String, "string"),
+        (Self::Text, "text"),
+        (Self::I128, "i128"),
+        (Self::U128, "u128"),
+        (Self::DateTime, "datetime"),
+        (Self::Uuid, "uuid"),
+        (Self::Hash, "hash"),
+        (Self::UnitInterval, "unit_interval"),
+        (Self::Array, "array"),
+        (Self::TimeSeries, "time_series"),
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::List(_) => "list",
+            Self::Map(_) => "map",
+            Self::Record(_) => "record",
+            Self::Tensor(_) => "tensor",
+            ty => Self::NAMES
+                .iter()
+                .find(|(name_ty, _)| name_ty == ty)
+                .map(|(_, name)| *name)
+                .unwrap(),
+        }
+    }
+}
+
+/// Formats the type with its parameters, such as `list<i32>`, `tensor<f32>` or `record{id:u64,tags:list<string>}`.
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::List(ty) | Self::Map(ty) | Self::Tensor(ty) => write!(f, "{}<{ty}>", self.name()),
+            Self::Record(fields) => {
+                write!(f, "{}{{", self.name())?;
+                for (index, field) in fields.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(",")?;
+                    }
+                    fmt_key(&field.name, f)?;
+                    write!(f, ":{}", &field.ty)?;
+                }
+                f.write_str("}")
+            }
+            _ => f.write_str(self.name()),
+        }
+    }
+}
+
+impl ::core::str::FromStr for ValueType {
+    type Err = ::anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser::new(s);
+        let ty = parser.value_type()?;
+        parser.finish().map(|()| ty)
+    }
+}
+
+/// Formats the value so that `Value::parse` with its type gives it back.
+///
+/// - `Bytes` are written in hex with a `0x` prefix
+/// - `Text` is written as `message@language-tag`
+/// - `Array`s and `List`s are written as lists of typed elements, such as `[[i32:1, i32:2]]`
+/// - `Map`s and `Record`s are written as typed entries, such as `{id: u64:1, name: string:foo}`
+/// - `Tensor`s are written as lists of the elements after their type, such as `f32:[[1, 2.5]]`
+/// - `TimeSeries` are written as lists of the samples, such as `[[2020-01-01T00:00:00Z, 1.5]]`
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None | Self::Dyn => f.write_str(self.value_type().name()),
+            Self::Bool(value) => value.fmt(f),
+            Self::I8(value) => value.fmt(f),
+            Self::I16(value) => value.fmt(f),
+            Self::I32(value) => value.fmt(f),
+            Self::I64(value) => value.fmt(f),
+            Self::I128(value) => value.fmt(f),
+            Self::U8(value) => value.fmt(f),
+            Self::U16(value) => value.fmt(f),
+            Self::U32(value) => value.fmt(f),
+            Self::U64(value) => value.fmt(f),
+            Self::U128(value) => value.fmt(f),
+            Self::F32(value) => value.fmt(f),
+            Self::F64(value) => value.fmt(f),
+            Self::Bytes(value) => {
+                f.write_str("0x")?;
+                value.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+            }
+            Self::String(value) => f.write_str(value),
+            Self::Text(value) => write!(f, "{}@{}", &value.msg, value.lang.as_str()),
+            Self::DateTime(value) => value.fmt(f),
+            Self::Uuid(value) => value.0.fmt(f),
+            Self::Hash(value) => f.write_str(&value.to_string()),
+            Self::UnitInterval(value) => value.0.fmt(f),
+            Self::Array(value) => fmt_array(value.view().into_dyn(), fmt_element, f),
+            Self::List(values) => {
+                f.write_str("[")?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    fmt_element(value, f)?;
+                }
+                f.write_str("]")
+            }
+            Self::Map(values) => fmt_entries(values.iter(), f),
+            Self::Record(fields) => {
+                fmt_entries(fields.iter().map(|field| (&field.name, &field.value)), f)
+            }
+            Self::Tensor(value) => {
+                write!(f, "{}:", value.element_type())?;
+                fmt_array(value.to_values().view(), fmt::Display::fmt, f)
+            }
+            Self::TimeSeries(value) => {
+                f.write_str("[")?;
+                for (index, (time, value)) in value.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "[{time}, {value}]")?;
+                }
+                f.write_str("]")
+            }
+        }
+    }
+}
+
+fn fmt_array(
+    array: ArrayViewD<Value>,
+    fmt_item: fn(&Value, &mut fmt::Formatter<'_>) -> fmt::Result,
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result {
+    if array.ndim() == 0 {
+        return fmt_item(&array[IxDyn(&[])], f);
+    }
+
+    f.write_str("[")?;
+    for (index, item) in array.axis_iter(Axis(0)).enumerate() {
+        if index > 0 {
+            f.write_str(", ")?;
+        }
+        fmt_array(item, fmt_item, f)?;
+    }
+    f.write_str("]")
+}
+
+fn fmt_entries<'a>(
+    entries: impl Iterator<Item = (&'a String, &'a Value)>,
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result {
+    f.write_str("{")?;
+    for (index, (key, value)) in entries.enumerate() {
+        if index > 0 {
+            f.write_str(", ")?;
+        }
+        fmt_key(key, f)?;
+        f.write_str(": ")?;
+        fmt_element(value, f)?;
+    }
+    f.write_str("}")
+}
+
+fn fmt_element(value: &Value, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}:", value.value_type())?;
+
+    // the composite values are self-delimiting
+    let literal = value.to_string();
+    match value {
+        Value::Array(_)
+        | Value::List(_)
+        | Value::Map(_)
+        | Value::Record(_)
+        | Value::Tensor(_)
+        | Value::TimeSeries(_) => f.write_str(&literal),
+        _ if needs_quotes(&literal) => fmt_quoted(&literal, f),
+        _ => f.write_str(&literal),
+    }
+}
+
+fn fmt_key(key: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if needs_quotes(key) || key.contains(':') {
+        fmt_quoted(key, f)
+    } else {
+        f.write_str(key)
+    }
+}
+
+fn fmt_quoted(literal: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in literal.chars() {
+        if matches!(c, '"' | '\\') {
+            f.write_str("\\")?;
+        }
+        write!(f, "{c}")?;
+    }
+    f.write_str("\"")
+}
+
+fn needs_quotes(literal: &str) -> bool {
+    literal.is_empty()
+        || literal.trim() != literal
+        || literal.contains([',', '[', ']', '{', '}', '"', '\\'])
+}
+
+impl Value {
+    /// Parses a value of the given type, as written by its `Display` impl.
+    ///
+    /// `Bytes` are also accepted in base58, and `Dyn` accepts a typed element such as `i32:42`.
+    /// The elements of composite values are cast into their declared types.
+    pub fn parse(s: &str, ty: ValueType) -> Result<Self> {
+        match ty {
+            ValueType::None if s == ty.name() => Ok(Self::None),
+            ValueType::Dyn if s == ty.name() => Ok(Self::Dyn),
+            ValueType::None => bail!("failed to parse {ty:?}: {s:?}"),
+            ValueType::Dyn => {
+                let mut parser = Parser::new(s);
+                let value = parser.element()?;
+                parser.finish().map(|()| value)
+            }
+            ValueType::Bytes => parse_bytes(s).map(Self::Bytes),
+            ValueType::Text => {
+                let (msg, lang) = s
+                    .rsplit_once('@')
+                    .ok_or_else(|| anyhow!("failed to parse {ty:?}: missing language tag"))?;
+                Ok(Self::Text(Text {
+                    msg: msg.to_string(),
+                    lang: lang.parse()?,
+                }))
+            }
+            ValueType::Array
+            | ValueType::List(_)
+            | ValueType::Map(_)
+            | ValueType::Record(_)
+            | ValueType::Tensor(_)
+            | ValueType::TimeSeries => {
+                let mut parser = Parser::new(s);
+                let value = parser.literal(ty)?;
+                parser.finish().map(|()| value)
+            }
+            _ => Self::parse_str(s, &ty),
+        }
+    }
+}
+
+fn parse_bytes(s: &str) -> Result<Vec<u8>> {
+    match s.strip_prefix("0x") {
+        Some(hex) => {
+            if hex.len() % 2 != 0 {
+                bail!("failed to parse Bytes: odd number of hex digits");
+            }
+            (0..hex.len())
+                .step_by(2)
+                .map(|index| {
+                    hex.get(index..index + 2)
+                        .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                        .ok_or_else(|| anyhow!("failed to parse Bytes: invalid hex digits"))
+                })
+                .collect()
+        }
+        None => s
+            .from_base58()
+            .map_err(|_| anyhow!("failed to parse Bytes: invalid base58 digits")),
+    }
+}
+
+/// A recursive descent parser for the types and the composite literals.
+struct Parser<'a> {
+    s: &'a str,
+    pos: usize,
+    /// The number of the enclosing delimiters, up to `MAX_DEPTH`.
+    depth: usize,
+}
+
+enum Node {
+    Leaf(Value),
+    List(Vec<Node>),
+}
+
+impl<'a> Parser<'a> {
+    fn new(s: &'a str) -> Self {
+        Self {
+            s,
+            pos: 0,
+            depth: 0,
+        }
+    }
+
+    /// Parses the nested items, failing instead of overflowing the stack if they are too deep.
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.depth >= MAX_DEPTH {
+            bail!(
+                "failed to parse Value: nested deeper than {MAX_DEPTH} at {}",
+                self.pos
+            );
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.s[self.pos..]
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespaces();
+        self.rest().chars().next()
+    }
+
+    fn skip_whitespaces(&mut self) {
+        self.pos = self.s.len() - self.rest().trim_start().len();
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            Ok(())
+        } else {
+            bail!("failed to parse Value: expected {c:?} at {}", self.pos)
+        }
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if self.peek().is_none() {
+            Ok(())
+        } else {
+            bail!("failed to parse Value: trailing characters at {}", self.pos)
+        }
+    }
+
+    /// Parses the comma-separated items until the closing delimiter.
+    fn items<T>(
+        &mut self,
+        open: char,
+        close: char,
+        mut item: impl FnMut(&mut Self) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        self.expect(open)?;
+        let items = self.nested(|parser| {
+            let mut items = Vec::new();
+            while !(items.is_empty() && parser.peek() == Some(close)) {
+                items.push(item(parser)?);
+                if parser.peek() == Some(',') {
+                    parser.pos += 1;
+                } else {
+                    break;
+                }
+            }
+            Ok(items)
+        })?;
+        self.expect(close)?;
+        Ok(items)
+    }
+
+    fn value_type(&mut self) -> Result<ValueType> {
+        self.skip_whitespaces();
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        self.pos += len;
+
+        match &rest[..len] {
+            "list" => self.type_parameter().map(ValueType::List),
+            "map" => self.type_parameter().map(ValueType::Map),
+            "tensor" => self.type_parameter().map(ValueType::Tensor),
+            "record" => self
+                .items('{', '}', |parser| {
+                    let name = parser.key()?;
+                    parser.expect(':')?;
+                    let ty = parser.value_type()?;
+                    Ok(FieldType { name, ty })
+                })
+                .map(ValueType::Record),
+            name => ValueType::NAMES
+                .iter()
+                .find(|(_, ty_name)| *ty_name == name)
+                .map(|(ty, _)| ty.clone())
+                .ok_or_else(|| anyhow!("failed to parse ValueType: {name:?}")),
+        }
+    }
+
+    /// Parses the type of the elements, such as `<i32>`.
+    fn type_parameter(&mut self) -> Result<Box<ValueType>> {
+        self.expect('<')?;
+        let ty = self.nested(Self::value_type)?;
+        self.expect('>')?;
+        Ok(Box::new(ty))
+    }
+
+    fn key(&mut self) -> Result<String> {
+        if self.peek() == Some('"') {
+            return self.quoted();
+        }
+
+        let rest = self.rest();
+        let len = rest.find(':').unwrap_or(rest.len());
+        self.pos += len;
+        Ok(rest[..len].trim_end().to_string())
+    }
+
+    fn element(&mut self) -> Result<Value> {
+        let ty = self.value_type()?;
+        self.expect(':')?;
+        self.literal(ty)
+    }
+
+    fn literal(&mut self, ty: ValueType) -> Result<Value> {
+        match ty {
+            ValueType::Array => self.array(&Self::element).map(Value::Array),
+            ValueType::Tensor(_) => {
+                let element = self.value_type()?;
+                self.expect(':')?;
+                let values = self.array(&|parser| parser.literal(element.clone()))?;
+                DynArray::from_values(&element, &values)
+                    .map(Value::Tensor)?
+                    .cast(ty)
+            }
+            ValueType::TimeSeries => {
+                let samples = self.items('[', ']', |parser| {
+                    parser.expect('[')?;
+                    let time = parser.literal(ValueType::DateTime)?.try_into()?;
+                    parser.expect(',')?;
+                    let value = parser.literal(ValueType::F64)?.try_into()?;
+                    parser.expect(']')?;
+                    Ok((time, value))
+                })?;
+                TimeSeries::try_from_samples(samples).map(Value::TimeSeries)
+            }
+            ValueType::List(_) => Value::List(self.items('[', ']', Self::element)?).cast(ty),
+            ValueType::Map(_) => {
+                let mut values = BTreeMap::new();
+                for (key, value) in self.entries()? {
+                    if values.insert(key, value).is_some() {
+                        bail!("failed to parse Value: duplicated keys");
+                    }
+                }
+                Value::Map(values).cast(ty)
+            }
+            ValueType::Record(_) => Value::Record(
+                self.entries()?
+                    .into_iter()
+                    .map(|(name, value)| Field { name, value })
+                    .collect(),
+            )
+            .cast(ty),
+            ty => {
+                let literal = if self.peek() == Some('"') {
+                    self.quoted()?
+                } else {
+                    let rest = self.rest();
+                    let len = rest.find([',', ']', '}']).unwrap_or(rest.len());
+                    self.pos += len;
+                    rest[..len].trim_end().to_string()
+                };
+                Value::parse(&literal, ty)
+            }
+        }
+    }
+
+    fn entries(&mut self) -> Result<Vec<(String, Value)>> {
+        self.items('{', '}', |parser| {
+            let key = parser.key()?;
+            parser.expect(':')?;
+            Ok((key, parser.element()?))
+        })
+    }
+
+    /// Parses the nested lists of the leaves into an array.
+    fn array(&mut self, leaf: &dyn Fn(&mut Self) -> Result<Value>) -> Result<Array<Value, IxDyn>> {
+        let node = self.node(leaf)?;
+
+        let shape = node.shape()?;
+        let mut data = Vec::new();
+        node.flatten(&mut data);
+
+        ::ndarray::ArcArray::from_shape_vec(IxDyn(&shape), data)
+            .map(Array)
+            .map_err(Into::into)
+    }
+
+    fn node(&mut self, leaf: &dyn Fn(&mut Self) -> Result<Value>) -> Result<Node> {
+        if self.peek() == Some('[') {
+            self.items('[', ']', |parser| parser.node(leaf))
+                .map(Node::List)
+        } else {
+            leaf(self).map(Node::Leaf)
+        }
+    }
+
+    fn quoted(&mut self) -> Result<String> {
+        self.expect('"')?;
+
+        let mut literal = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((index, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += index + 1;
+                    return Ok(literal);
+                }
+                '\\' => match chars.next() {
+                    Some((_, c)) => literal.push(c),
+                    None => break,
+                },
+                c => literal.push(c),
+            }
+        }
+        bail!("failed to parse Value: unterminated quotes")
+    }
+}
+
+impl Node {
+    fn shape(&self) -> Result<Vec<usize>> {
+        match self {
+            Self::Leaf(_) => Ok(vec![]),
+            Self::List(items) => {
+                let mut shape = vec![items.len()];
+                if let Some((first, rest)) = items.split_first() {
+                    let inner = first.shape()?;
+                    for item in rest {
+                        if item.shape()? != inner {
+                            bail!("failed to parse Array: jagged lists");
+                        }
+                    }
+                    shape.extend(inner);
+                }
+                Ok(shape)
+            }
+        }
+    }
+
+    fn flatten(self, data: &mut Vec<Value>) {
+        match self {
+            Self::Leaf(value) => data.push(value),
+            Self::List(items) => items.into_iter().for_each(|item| item.flatten(data)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::{super::chrono::DateTime, *};
+
+    #[test]
+    fn test_value_type_names() {
+        for (ty, _) in ValueType::NAMES {
+            assert_eq!(&ty.to_string().parse::<ValueType>().unwrap(), ty);
+        }
+        assert_eq!(ValueType::UnitInterval.to_string(), "unit_interval");
+        assert!("I32".parse::<ValueType>().is_err());
+
+        let ty = ValueType::Record(vec![
+            FieldType {
+                name: "id".into(),
+                ty: ValueType::U64,
+            },
+            FieldType {
+                name: "tags".into(),
+                ty: ValueType::Map(Box::new(ValueType::List(Box::new(ValueType::String)))),
+            },
+        ]);
+        assert_eq!(ty.to_string(), "record{id:u64,tags:map<list<string>>}");
+        assert_eq!(ty.to_string().parse::<ValueType>().unwrap(), ty);
+        assert_eq!(
+            " list < record { } > ".parse::<ValueType>().unwrap(),
+            ValueType::List(Box::new(ValueType::Record(vec![]))),
+        );
+        assert!("list<i32".parse::<ValueType>().is_err());
+    }
+
+    #[test]
+    fn test_parse_display() {
+        let matrix = ::ndarray::arr2(&[
+            [Value::I32(1), Value::String("a, \"b\"".into())],
+            [Value::Text(Text::with_en_us("c")), Value::Bytes(vec![])],
+        ]);
+        let nested =
+            ::ndarray::arr1(&[Value::Array(Array(matrix.clone().into_dyn().into_shared()))]);
+
+        let values = [
+            Value::None,
+            Value::Bool(true),
+            Value::F64(-0.25),
+            Value::Bytes(vec![0, 1, 254, 255]),
+            Value::String(" spaces ".into()),
+            Value::Text(Text::with_en_us("hello@world")),
+            Value::Array(Array(matrix.into_dyn().into_shared())),
+            Value::Array(Array(nested.into_dyn().into_shared())),
+            Value::Array(Array(
+                ::ndarray::arr0(Value::U8(7)).into_dyn().into_shared(),
+            )),
+            Value::Array(Array(
+                ::ndarray::Array::from_shape_vec(IxDyn(&[2, 0]), vec![])
+                    .unwrap()
+                    .into_shared(),
+            )),
+            Value::List(vec![]),
+            Value::List(vec![
+                Value::Tensor(DynArray::from(Array(
+                    ::ndarray::arr2(&[[1.5f32, -2.0], [f32::INFINITY, 0.1]])
+                        .into_dyn()
+                        .into_shared(),
+                ))),
+                Value::String("}".into()),
+                Value::List(vec![Value::I8(1)]),
+                Value::TimeSeries(
+                    TimeSeries::try_from_samples([
+                        (DateTime(::chrono::Utc.timestamp(1_600_000_000, 5)), 1.5),
+                        (
+                            DateTime(::chrono::Utc.timestamp(1_600_000_060, 0)),
+                            f64::NAN,
+                        ),
+                    ])
+                    .unwrap(),
+                ),
+            ]),
+            Value::Map(
+                [
+                    ("a: b".to_string(), Value::Map(Default::default())),
+                    ("c".to_string(), Value::U8(2)),
+                ]
+                .into(),
+            ),
+            Value::Record(vec![
+                Field {
+                    name: "z".into(),
+                    value: Value::Text(Text::with_en_us("{x}")),
+                },
+                Field {
+                    name: "a".into(),
+                    value: Value::Record(vec![]),
+                },
+            ]),
+        ];
+        for value in values {
+            let s = value.to_string();
+            assert_eq!(Value::parse(&s, value.value_type()).unwrap(), value, "{s}");
+        }
+
+        assert_eq!(Value::Bytes(vec![0xab, 0x01]).to_string(), "0xab01");
+        assert_eq!(
+            Value::parse("2NEpo7TZRRrLZSi2U", ValueType::Bytes).unwrap(),
+            Value::Bytes(b"Hello World!".to_vec()),
+        );
+        assert_eq!(
+            Value::parse("i64:-3", ValueType::Dyn).unwrap(),
+            Value::I64(-3),
+        );
+        assert!(Value::parse("0xabc", ValueType::Bytes).is_err());
+        assert!(Value::parse("hello", ValueType::Text).is_err());
+        assert!(Value::parse("[[i8:1], []]", ValueType::Array).is_err());
+        assert!(Value::parse("[i8:1", ValueType::Array).is_err());
+
+        // the elements are cast into the declared types
+        assert_eq!(
+            Value::parse(
+                "{id: string:42, name: string:foo}",
+                "record{id:u64,name:string}".parse().unwrap(),
+            )
+            .unwrap()
+            .to_string(),
+            "{id: u64:42, name: string:foo}",
+        );
+        assert!(Value::parse("{a: i8:1, a: i8:2}", "map<i8>".parse().unwrap()).is_err());
+        assert!(Value::parse("[i8:-1]", "list<u8>".parse().unwrap()).is_err());
+
+        // tensors
+        let tensor = ValueType::Tensor(Box::new(ValueType::Dyn));
+        assert_eq!(
+            Value::parse("u8:[[1, 2], [3, 4]]", tensor.clone()).unwrap(),
+            Value::Tensor(DynArray::from(Array(
+                ::ndarray::arr2(&[[1u8, 2], [3, 4]])
+                    .into_dyn()
+                    .into_shared(),
+            ))),
+        );
+        assert_eq!(
+            Value::parse("bool:true", tensor.clone())
+                .unwrap()
+                .to_string(),
+            "bool:true",
+        );
+        assert!(Value::parse("u8:[1, 256]", tensor.clone()).is_err());
+        assert!(Value::parse("string:[a]", tensor).is_err());
+
+        // the elements of tensors are cast into the declared types
+        let tensor: ValueType = "tensor<f32>".parse().unwrap();
+        assert_eq!(tensor, ValueType::Tensor(Box::new(ValueType::F32)));
+        assert_eq!(tensor.to_string(), "tensor<f32>");
+        let value = Value::parse("u8:[[1, 2]]", tensor.clone()).unwrap();
+        assert_eq!(value.value_type(), tensor);
+        assert_eq!(value.to_string(), "f32:[[1, 2]]");
+        assert!(Value::parse("f32:[1.5]", "tensor<u8>".parse().unwrap()).is_err());
+        assert!("tensor".parse::<ValueType>().is_err());
+
+        // the nesting is limited instead of overflowing the stack
+        let nested = |depth| format!("{}i8:1{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(Value::parse(&nested(MAX_DEPTH), ValueType::Array).is_ok());
+        assert!(Value::parse(&nested(MAX_DEPTH + 1), ValueType::Array).is_err());
+        assert!(Value::parse(&"list<dyn>:[".repeat(1_000_000), ValueType::Dyn).is_err());
+        assert!("list<".repeat(1_000_000).parse::<ValueType>().is_err());
+    }
+}
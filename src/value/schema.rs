@@ -0,0 +1,561 @@
+use std::{cmp::Ordering, fmt};
+
+use bytecheck::CheckBytes;
+use ndarray::Dimension;
+use regex::Regex;
+use rkyv::{Archive, Deserialize, Serialize};
+
+use super::{array::Array, text::LanguageTag, Field, FieldType, Value, ValueType};
+use crate::schema::HasSchema;
+
+/// The declared fields of a record, which dynamic values are validated against.
+#[derive(Clone, Debug, Default, PartialEq, Archive, Serialize, Deserialize, HasSchema)]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+pub struct RecordSchema {
+    pub fields: Vec<FieldSchema>,
+}
+
+#[derive(Clone, Debug, PartialEq, Archive, Serialize, Deserialize, HasSchema)]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+pub struct FieldSchema {
+    pub name: String,
+    pub ty: ValueType,
+    /// Whether the field may be missing or `None`.
+    pub optional: bool,
+    /// The value of the field when it is missing or `None`.
+    pub default: Option<Value>,
+    pub constraints: Vec<Constraint>,
+}
+
+#[derive(Clone, Debug, PartialEq, Archive, Serialize, Deserialize, HasSchema)]
+#[archive(bound(
+    serialize = "__S: ::rkyv::ser::ScratchSpace + ::rkyv::ser::Serializer",
+    deserialize = "
+        __D::Error: From<::ndarray::ShapeError>
+            + From<super::chrono::NaiveDateTimeRangeError>
+            + From<::language_tags::ParseError>,
+    ",
+))]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+#[archive_attr(check_bytes(
+    bound = "__C: ::rkyv::validation::ArchiveContext, <__C as ::rkyv::Fallible>::Error: ::std::error::Error"
+))]
+pub enum Constraint {
+    /// The value lies between the bounds, inclusive.
+    Range {
+        min: Option<Value>,
+        max: Option<Value>,
+    },
+    /// The length of a string, text, bytes, list, map or array lies between the bounds, inclusive.
+    Length { min: Option<u64>, max: Option<u64> },
+    /// The string or text contains a match of the regular expression.
+    Regex(String),
+    /// The language of the text matches the language range.
+    Language(LanguageTag),
+    /// Each element of the list, map or array satisfies the constraints.
+    Items(
+        #[omit_bounds]
+        #[archive_attr(omit_bounds)]
+        Vec<Constraint>,
+    ),
+    /// The record satisfies the nested schema.
+    Record(
+        #[omit_bounds]
+        #[archive_attr(omit_bounds)]
+        #[schema(name = "RecordSchema")]
+        RecordSchema,
+    ),
+}
+
+impl RecordSchema {
+    pub fn value_type(&self) -> ValueType {
+        ValueType::Record(
+            self.fields
+                .iter()
+                .map(|field| FieldType {
+                    name: field.name.clone(),
+                    ty: field.ty.clone(),
+                })
+                .collect(),
+        )
+    }
+
+    pub fn validate(&self, value: &Value) -> Result<(), ValidationErrors> {
+        self.normalize(value).map(drop)
+    }
+
+    /// Converts a record or a map into a record of the declared fields, in order.
+    ///
+    /// The values are cast into the declared types, and the missing ones are replaced by the defaults.
+    pub fn normalize(&self, value: &Value) -> Result<Value, ValidationErrors> {
+        let mut errors = Vec::new();
+        let value = self.normalize_at("$", value, &mut errors);
+
+        match value {
+            Some(value) if errors.is_empty() => Ok(value),
+            _ => Err(ValidationErrors(errors)),
+        }
+    }
+
+    fn normalize_at(
+        &self,
+        path: &str,
+        value: &Value,
+        errors: &mut Vec<ValidationError>,
+    ) -> Option<Value> {
+        let entries: Vec<_> = match value {
+            Value::Record(fields) => fields
+                .iter()
+                .map(|field| (&field.name, &field.value))
+                .collect(),
+            Value::Map(values) => values.iter().collect(),
+            _ => {
+                errors.push(ValidationError::new(
+                    path,
+                    format!("expected a record, found {}", value.value_type()),
+                ));
+                return None;
+            }
+        };
+
+        for (index, (name, _)) in entries.iter().enumerate() {
+            let path = format!("{path}.{name}");
+            if !self.fields.iter().any(|field| &field.name == *name) {
+                errors.push(ValidationError::new(&path, "unexpected field"));
+            } else if entries[..index].iter().any(|(other, _)| other == name) {
+                errors.push(ValidationError::new(&path, "duplicated field"));
+            }
+        }
+
+        let fields = self
+            .fields
+            .iter()
+            .filter_map(|field| {
+                let value = entries
+                    .iter()
+                    .find(|(name, _)| **name == field.name)
+                    .map(|(_, value)| *value);
+                let path = format!("{path}.{}", &field.name);
+
+                field.normalize_at(&path, value, errors).map(|value| Field {
+                    name: field.name.clone(),
+                    value,
+                })
+            })
+            .collect();
+        Some(Value::Record(fields))
+    }
+}
+
+impl FieldSchema {
+    fn normalize_at(
+        &self,
+        path: &str,
+        value: Option<&Value>,
+        errors: &mut Vec<ValidationError>,
+    ) -> Option<Value> {
+        let value = match (value, &self.default) {
+            (Some(Value::None) | None, Some(default)) => default,
+            (Some(Value::None) | None, None) if self.optional => return Some(Value::None),
+            (None, None) => {
+                errors.push(ValidationError::new(path, "missing field"));
+                return None;
+            }
+            (Some(value), _) => value,
+        };
+
+        let mut value = match value.cast(self.ty.clone()) {
+            Ok(value) => value,
+            Err(error) => {
+                errors.push(ValidationError::new(path, error));
+                return None;
+            }
+        };
+        for constraint in &self.constraints {
+            value = constraint.apply(path, value, errors);
+        }
+        Some(value)
+    }
+}
+
+impl Constraint {
+    fn apply(&self, path: &str, value: Value, errors: &mut Vec<ValidationError>) -> Value {
+        let mut error = |message: String| errors.push(ValidationError::new(path, message));
+
+        match self {
+            Self::Range { min, max } => {
+                if let Some(min) = min {
+                    match compare(&value, min) {
+                        Some(Ordering::Less) => error(format!("{value} is less than {min}")),
+                        Some(_) => {}
+                        None => error(format!("{value} is not comparable with {min}")),
+                    }
+                }
+                if let Some(max) = max {
+                    match compare(&value, max) {
+                        Some(Ordering::Greater) => error(format!("{value} is greater than {max}")),
+                        Some(_) => {}
+                        None => error(format!("{value} is not comparable with {max}")),
+                    }
+                }
+            }
+            Self::Length { min, max } => {
+                let len = match &value {
+                    Value::Bytes(value) => value.len(),
+                    Value::String(value) => value.chars().count(),
+                    Value::Text(value) => value.msg.chars().count(),
+                    Value::Array(value) => value.len(),
+                    Value::List(value) => value.len(),
+                    Value::Map(value) => value.len(),
+                    Value::Tensor(value) => value.shape().iter().product(),
+                    Value::TimeSeries(value) => value.len(),
+                    value => {
+                        error(format!("{} has no length", value.value_type()));
+                        return value.clone();
+                    }
+                } as u64;
+
+                if min.map(|min| len < min).unwrap_or_default() {
+                    error(format!("length {len} is less than {}", min.unwrap()));
+                }
+                if max.map(|max| len > max).unwrap_or_default() {
+                    error(format!("length {len} is greater than {}", max.unwrap()));
+                }
+            }
+            Self::Regex(pattern) => {
+                let msg = match &value {
+                    Value::String(value) => value,
+                    Value::Text(value) => &value.msg,
+                    value => {
+                        error(format!("{} cannot be matched", value.value_type()));
+                        return value.clone();
+                    }
+                };
+                match Regex::new(pattern) {
+                    Ok(regex) if regex.is_match(msg) => {}
+                    Ok(_) => error(format!("{msg:?} does not match {pattern:?}")),
+                    Err(e) => error(format!("invalid regex {pattern:?}: {e}")),
+                }
+            }
+            Self::Language(range) => match &value {
+                Value::Text(text) => {
+                    let matches = if range.is_language_range() {
+                        range.matches(&text.lang)
+                    } else {
+                        range == &text.lang
+                    };
+                    if !matches {
+                        error(format!(
+                            "language {} does not match {}",
+                            text.lang.as_str(),
+                            range.as_str(),
+                        ));
+                    }
+                }
+                value => error(format!("{} has no language", value.value_type())),
+            },
+            Self::Items(constraints) => {
+                let apply = |path: String, mut value: Value, errors: &mut Vec<_>| {
+                    for constraint in constraints {
+                        value = constraint.apply(&path, value, errors);
+                    }
+                    value
+                };
+
+                return match value {
+                    Value::Array(values) => {
+                        let items = values
+                            .indexed_iter()
+                            .map(|(index, value)| {
+                                let path = format!("{path}{:?}", index.slice());
+                                apply(path, value.clone(), errors)
+                            })
+                            .collect();
+                        // the items are collected in the logical order
+                        ::ndarray::ArcArray::from_shape_vec(values.raw_dim(), items)
+                            .map(Array)
+                            .map(Value::Array)
+                            .unwrap()
+                    }
+                    Value::List(values) => Value::List(
+                        values
+                            .into_iter()
+                            .enumerate()
+                            .map(|(index, value)| apply(format!("{path}[{index}]"), value, errors))
+                            .collect(),
+                    ),
+                    Value::Map(values) => Value::Map(
+                        values
+                            .into_iter()
+                            .map(|(key, value)| {
+                                let value = apply(format!("{path}.{key}"), value, errors);
+                                (key, value)
+                            })
+                            .collect(),
+                    ),
+                    value => {
+                        error(format!("{} has no items", value.value_type()));
+                        value
+                    }
+                };
+            }
+            Self::Record(schema) => {
+                return schema.normalize_at(path, &value, errors).unwrap_or(value);
+            }
+        }
+        value
+    }
+}
+
+/// Compares the values after casting one into the type of the other.
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    if let Ok(b) = b.cast(a.value_type()) {
+        return compare_same(a, &b);
+    }
+    let a = a.cast(b.value_type()).ok()?;
+    compare_same(&a, b)
+}
+
+fn compare_same(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Bool(a), Value::Bool(b)) => a.partial_cmp(b),
+        (Value::I8(a), Value::I8(b)) => a.partial_cmp(b),
+        (Value::I16(a), Value::I16(b)) => a.partial_cmp(b),
+        (Value::I32(a), Value::I32(b)) => a.partial_cmp(b),
+        (Value::I64(a), Value::I64(b)) => a.partial_cmp(b),
+        (Value::I128(a), Value::I128(b)) => a.partial_cmp(b),
+        (Value::U8(a), Value::U8(b)) => a.partial_cmp(b),
+        (Value::U16(a), Value::U16(b)) => a.partial_cmp(b),
+        (Value::U32(a), Value::U32(b)) => a.partial_cmp(b),
+        (Value::U64(a), Value::U64(b)) => a.partial_cmp(b),
+        (Value::U128(a), Value::U128(b)) => a.partial_cmp(b),
+        (Value::F32(a), Value::F32(b)) => a.partial_cmp(b),
+        (Value::F64(a), Value::F64(b)) => a.partial_cmp(b),
+        (Value::Bytes(a), Value::Bytes(b)) => a.partial_cmp(b),
+        (Value::String(a), Value::String(b)) => a.partial_cmp(b),
+        (Value::DateTime(a), Value::DateTime(b)) => a.partial_cmp(b),
+        (Value::Uuid(a), Value::Uuid(b)) => a.partial_cmp(b),
+        (Value::Hash(a), Value::Hash(b)) => a.partial_cmp(b),
+        (Value::UnitInterval(a), Value::UnitInterval(b)) => a.partial_cmp(b),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationError {
+    /// The location of the value, such as `$.items[3].name`.
+    pub path: String,
+    pub message: String,
+}
+
+impl ValidationError {
+    fn new(path: &str, message: impl ToString) -> Self {
+        Self {
+            path: path.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", &self.path, &self.message)
+    }
+}
+
+impl ::std::error::Error for ValidationError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationErrors(pub Vec<ValidationError>);
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, error) in self.0.iter().enumerate() {
+            if index > 0 {
+                f.write_str("; ")?;
+            }
+            error.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl ::std::error::Error for ValidationErrors {}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{dyn_array::DynArray, text::Text},
+        *,
+    };
+
+    fn field(name: &str, ty: ValueType) -> FieldSchema {
+        FieldSchema {
+            name: name.into(),
+            ty,
+            optional: false,
+            default: None,
+            constraints: vec![],
+        }
+    }
+
+    fn schema() -> RecordSchema {
+        RecordSchema {
+            fields: vec![
+                FieldSchema {
+                    constraints: vec![Constraint::Range {
+                        min: Some(Value::I8(1)),
+                        max: Some(Value::U64(100)),
+                    }],
+                    ..field("age", ValueType::U8)
+                },
+                FieldSchema {
+                    constraints: vec![
+                        Constraint::Length {
+                            min: Some(1),
+                            max: Some(8),
+                        },
+                        Constraint::Regex("^[a-z]+$".into()),
+                    ],
+                    ..field("name", ValueType::String)
+                },
+                FieldSchema {
+                    optional: true,
+                    constraints: vec![Constraint::Language("en".parse().unwrap())],
+                    ..field("bio", ValueType::Text)
+                },
+                FieldSchema {
+                    default: Some(Value::List(vec![])),
+                    constraints: vec![Constraint::Items(vec![Constraint::Record(RecordSchema {
+                        fields: vec![field("id", ValueType::U64)],
+                    })])],
+                    ..field("friends", ValueType::List(Box::new(ValueType::Dyn)))
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_normalize() {
+        let value = Value::Map(
+            [
+                ("name".to_string(), Value::String("alice".into())),
+                ("age".to_string(), Value::String("42".into())),
+            ]
+            .into(),
+        );
+
+        let normalized = schema().normalize(&value).unwrap();
+        assert_eq!(
+            normalized,
+            Value::Record(vec![
+                Field {
+                    name: "age".into(),
+                    value: Value::U8(42),
+                },
+                Field {
+                    name: "name".into(),
+                    value: Value::String("alice".into()),
+                },
+                Field {
+                    name: "bio".into(),
+                    value: Value::None,
+                },
+                Field {
+                    name: "friends".into(),
+                    value: Value::List(vec![]),
+                },
+            ]),
+        );
+        schema().validate(&normalized).unwrap();
+    }
+
+    #[test]
+    fn test_validate_errors() {
+        let value = Value::Map(
+            [
+                ("age".to_string(), Value::I32(0)),
+                ("name".to_string(), Value::String("Alice".into())),
+                (
+                    "bio".to_string(),
+                    Value::Text(Text {
+                        msg: "bonjour".into(),
+                        lang: "fr".parse().unwrap(),
+                    }),
+                ),
+                (
+                    "friends".to_string(),
+                    Value::List(vec![
+                        Value::Map([("id".to_string(), Value::U64(1))].into()),
+                        Value::Map(Default::default()),
+                    ]),
+                ),
+                ("extra".to_string(), Value::None),
+            ]
+            .into(),
+        );
+
+        let errors = schema().validate(&value).unwrap_err();
+        let paths: Vec<_> = errors.0.iter().map(|error| error.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            ["$.extra", "$.age", "$.name", "$.bio", "$.friends[1].id",],
+        );
+        assert_eq!(errors.0[1].to_string(), "$.age: 0 is less than 1");
+        assert_eq!(errors.0[4].message, "missing field");
+
+        let errors = schema().validate(&Value::I32(1)).unwrap_err();
+        assert_eq!(errors.to_string(), "$: expected a record, found i32");
+    }
+
+    #[test]
+    fn test_validate_tensor() {
+        let schema = RecordSchema {
+            fields: vec![field(
+                "weights",
+                ValueType::Tensor(Box::new(ValueType::F32)),
+            )],
+        };
+        let record = |value| Value::Map([("weights".to_string(), value)].into());
+        let tensor = |array: ::ndarray::ArrayD<u8>| {
+            Value::Tensor(DynArray::from(Array(array.into_shared())))
+        };
+
+        // the elements are cast into the declared type
+        let value = schema
+            .normalize(&record(tensor(::ndarray::arr2(&[[1, 2]]).into_dyn())))
+            .unwrap();
+        assert_eq!(value.to_string(), "{weights: tensor<f32>:f32:[[1, 2]]}",);
+
+        // the other values are rejected
+        let value = Value::Tensor(DynArray::from(Array(
+            ::ndarray::arr1(&[u64::MAX]).into_dyn().into_shared(),
+        )));
+        let errors = schema.validate(&record(value)).unwrap_err();
+        assert_eq!(errors.0[0].path, "$.weights");
+        assert!(schema.validate(&record(Value::F32(1.0))).is_err());
+    }
+
+    #[test]
+    fn test_schema_rkyv() {
+        let schema = schema();
+
+        // serialize
+        let bytes = ::rkyv::to_bytes::<_, 4096>(&schema).unwrap();
+
+        // deserialize
+        let deserialized: RecordSchema = crate::signed::from_bytes(&bytes).unwrap();
+        assert_eq!(&schema, &deserialized);
+
+        // the nesting of the archived constraints is limited
+        let nested = (0..crate::signed::MAX_DEPTH)
+            .fold(Constraint::Regex(".".into()), |constraint, _| {
+                Constraint::Items(vec![constraint])
+            });
+        let bytes = ::rkyv::to_bytes::<_, 4096>(&nested).unwrap();
+        assert!(crate::signed::from_bytes::<Constraint>(&bytes).is_err());
+    }
+}
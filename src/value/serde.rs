@@ -0,0 +1,635 @@
+//! A bridge between `serde` and [`Value`], so that any serializable type can be passed around dynamically.
+//!
+//! Structs are converted into records, keeping the order of the fields,
+//! and enum variants with data into single-entry maps keyed by the variant name.
+//! Values without a counterpart in the serde data model are deserialized as strings
+//! (`DateTime` in RFC 3339, `Uuid`, `Hash` in base58, and the message of `Text`),
+//! `UnitInterval` as `f64`, and arrays as sequences of the elements in the logical order.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use serde::{
+    de::{
+        self, value::MapDeserializer, value::SeqDeserializer, DeserializeOwned, DeserializeSeed,
+        EnumAccess, IntoDeserializer, VariantAccess, Visitor,
+    },
+    forward_to_deserialize_any, ser, Serialize,
+};
+
+use super::{Field, Value};
+
+/// Converts the serializable value into a `Value`.
+pub fn to_value<T>(value: &T) -> Result<Value>
+where
+    T: ?Sized + Serialize,
+{
+    value.serialize(ValueSerializer).map_err(Into::into)
+}
+
+/// Converts the `Value` into a deserializable value.
+pub fn from_value<T>(value: Value) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    T::deserialize(value).map_err(Into::into)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Error(String);
+
+impl ::core::fmt::Display for Error {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+        ::core::fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl ::std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: ::core::fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: ::core::fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+/// Wraps the value of an enum variant into a single-entry map.
+fn wrap_variant(variant: Option<&'static str>, value: Value) -> Value {
+    match variant {
+        Some(variant) => Value::Map([(variant.to_string(), value)].into()),
+        None => value,
+    }
+}
+
+struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    type SerializeSeq = SerializeList;
+    type SerializeTuple = SerializeList;
+    type SerializeTupleStruct = SerializeList;
+    type SerializeTupleVariant = SerializeList;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeRecord;
+    type SerializeStructVariant = SerializeRecord;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, Error> {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, Error> {
+        Ok(Value::I8(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, Error> {
+        Ok(Value::I16(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, Error> {
+        Ok(Value::I32(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, Error> {
+        Ok(Value::I64(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Value, Error> {
+        Ok(Value::I128(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, Error> {
+        Ok(Value::U8(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, Error> {
+        Ok(Value::U16(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, Error> {
+        Ok(Value::U32(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, Error> {
+        Ok(Value::U64(v))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Value, Error> {
+        Ok(Value::U128(v))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, Error> {
+        Ok(Value::F32(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, Error> {
+        Ok(Value::F64(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, Error> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, Error> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, Error> {
+        Ok(Value::Bytes(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<Value, Error> {
+        Ok(Value::None)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, Error> {
+        Ok(Value::None)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, Error> {
+        Ok(Value::None)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Value, Error> {
+        Ok(Value::String(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value, Error> {
+        value
+            .serialize(self)
+            .map(|value| wrap_variant(Some(variant), value))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeList, Error> {
+        Ok(SerializeList {
+            values: Vec::with_capacity(len.unwrap_or_default()),
+            variant: None,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeList, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeList, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeList, Error> {
+        Ok(SerializeList {
+            values: Vec::with_capacity(len),
+            variant: Some(variant),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeMap, Error> {
+        Ok(SerializeMap {
+            values: BTreeMap::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeRecord, Error> {
+        Ok(SerializeRecord {
+            fields: Vec::with_capacity(len),
+            variant: None,
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeRecord, Error> {
+        Ok(SerializeRecord {
+            fields: Vec::with_capacity(len),
+            variant: Some(variant),
+        })
+    }
+}
+
+struct SerializeList {
+    values: Vec<Value>,
+    variant: Option<&'static str>,
+}
+
+impl SerializeList {
+    fn push<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.values.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value, Error> {
+        Ok(wrap_variant(self.variant, Value::List(self.values)))
+    }
+}
+
+impl ser::SerializeSeq for SerializeList {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SerializeList {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeList {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeList {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+struct SerializeMap {
+    values: BTreeMap<String, Value>,
+    key: Option<String>,
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Error> {
+        match key.serialize(ValueSerializer)? {
+            Value::String(key) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            key => Err(Error(format!(
+                "map keys should be strings: {:?}",
+                key.value_type(),
+            ))),
+        }
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error("map value without a key".into()))?;
+        self.values.insert(key, value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        Ok(Value::Map(self.values))
+    }
+}
+
+struct SerializeRecord {
+    fields: Vec<Field>,
+    variant: Option<&'static str>,
+}
+
+impl SerializeRecord {
+    fn push<T: ?Sized + Serialize>(&mut self, name: &'static str, value: &T) -> Result<(), Error> {
+        self.fields.push(Field {
+            name: name.to_string(),
+            value: value.serialize(ValueSerializer)?,
+        });
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value, Error> {
+        Ok(wrap_variant(self.variant, Value::Record(self.fields)))
+    }
+}
+
+impl ser::SerializeStruct for SerializeRecord {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeRecord {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Value {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Self::None | Self::Dyn => visitor.visit_unit(),
+            Self::Bool(value) => visitor.visit_bool(value),
+            Self::I8(value) => visitor.visit_i8(value),
+            Self::I16(value) => visitor.visit_i16(value),
+            Self::I32(value) => visitor.visit_i32(value),
+            Self::I64(value) => visitor.visit_i64(value),
+            Self::U8(value) => visitor.visit_u8(value),
+            Self::U16(value) => visitor.visit_u16(value),
+            Self::U32(value) => visitor.visit_u32(value),
+            Self::U64(value) => visitor.visit_u64(value),
+            Self::F32(value) => visitor.visit_f32(value),
+            Self::F64(value) => visitor.visit_f64(value),
+            Self::Bytes(value) => visitor.visit_byte_buf(value),
+            Self::String(value) => visitor.visit_string(value),
+            Self::Text(value) => visitor.visit_string(value.msg),
+            Self::I128(value) => visitor.visit_i128(value),
+            Self::U128(value) => visitor.visit_u128(value),
+            Self::DateTime(value) => {
+                visitor.visit_string(value.to_rfc3339_opts(::chrono::SecondsFormat::AutoSi, true))
+            }
+            Self::Uuid(value) => visitor.visit_string(value.to_string()),
+            Self::Hash(value) => visitor.visit_string(value.to_string()),
+            Self::UnitInterval(value) => visitor.visit_f64(value.to_num()),
+            Self::Array(values) => visit_seq(values.iter().cloned().collect(), visitor),
+            Self::Tensor(values) => visit_seq(values.values().collect(), visitor),
+            Self::TimeSeries(series) => visit_seq(
+                series
+                    .iter()
+                    .map(|(time, value)| {
+                        Value::List(vec![Value::DateTime(time), Value::F64(value)])
+                    })
+                    .collect(),
+                visitor,
+            ),
+            Self::List(values) => visit_seq(values, visitor),
+            Self::Map(values) => {
+                let mut map = MapDeserializer::new(values.into_iter());
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+            Self::Record(fields) => {
+                let mut map =
+                    MapDeserializer::new(fields.into_iter().map(|field| (field.name, field.value)));
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Self::None => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let (variant, value) = match self {
+            Self::String(variant) => (variant, None),
+            Self::Map(values) if values.len() == 1 => {
+                let (variant, value) = values.into_iter().next().unwrap();
+                (variant, Some(value))
+            }
+            Self::Record(mut fields) if fields.len() == 1 => {
+                let field = fields.pop().unwrap();
+                (field.name, Some(field.value))
+            }
+            value => {
+                return Err(Error(format!(
+                    "enums should be strings or single-entry maps: {:?}",
+                    value.value_type(),
+                )))
+            }
+        };
+        visitor.visit_enum(VariantDeserializer { variant, value })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+fn visit_seq<'de, V: Visitor<'de>>(values: Vec<Value>, visitor: V) -> Result<V::Value, Error> {
+    let mut seq = SeqDeserializer::new(values.into_iter());
+    let value = visitor.visit_seq(&mut seq)?;
+    seq.end()?;
+    Ok(value)
+}
+
+struct VariantDeserializer {
+    variant: String,
+    value: Option<Value>,
+}
+
+impl<'de> EnumAccess<'de> for VariantDeserializer {
+    type Error = Error;
+    type Variant = VariantValue;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Error> {
+        let variant = seed.deserialize(self.variant.into_deserializer())?;
+        Ok((variant, VariantValue(self.value)))
+    }
+}
+
+struct VariantValue(Option<Value>);
+
+impl<'de> VariantAccess<'de> for VariantValue {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        match self.0 {
+            None | Some(Value::None) => Ok(()),
+            Some(value) => Err(Error(format!(
+                "unit variants should have no value: {:?}",
+                value.value_type(),
+            ))),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self.0.unwrap_or(Value::None))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_any(self.0.unwrap_or(Value::None), visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_any(self.0.unwrap_or(Value::None), visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[test]
+    fn test_serde_bridge() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        enum Shape {
+            Empty,
+            Circle(f64),
+            Rect { w: u32, h: u32 },
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Plugin {
+            name: String,
+            version: (u8, u8),
+            tags: Vec<String>,
+            limits: BTreeMap<String, i64>,
+            parent: Option<Box<Plugin>>,
+            shapes: Vec<Shape>,
+        }
+
+        let plugin = Plugin {
+            name: "resize".into(),
+            version: (1, 2),
+            tags: vec!["image".into()],
+            limits: [("width".to_string(), 1024)].into(),
+            parent: None,
+            shapes: vec![Shape::Empty, Shape::Circle(0.5), Shape::Rect { w: 2, h: 3 }],
+        };
+
+        let value = to_value(&plugin).unwrap();
+        match &value {
+            Value::Record(fields) => {
+                let names: Vec<_> = fields.iter().map(|field| field.name.as_str()).collect();
+                assert_eq!(
+                    names,
+                    ["name", "version", "tags", "limits", "parent", "shapes"],
+                );
+                assert_eq!(fields[0].value, Value::String("resize".into()));
+                assert_eq!(fields[4].value, Value::None);
+            }
+            value => panic!("expected a record: {value:?}"),
+        }
+        assert_eq!(from_value::<Plugin>(value).unwrap(), plugin);
+
+        // values are deserialized into their serde counterparts
+        let uuid = ::uuid::Uuid::new_v4();
+        let value = Value::Uuid(super::super::uuid::Uuid(uuid));
+        assert_eq!(from_value::<::uuid::Uuid>(value).unwrap(), uuid);
+
+        // map keys should be strings
+        assert!(to_value(&BTreeMap::from([(1u8, 2u8)])).is_err());
+    }
+}
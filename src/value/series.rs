@@ -0,0 +1,672 @@
+//! Time series of `f64` samples, compressed in the style of Gorilla.
+//!
+//! The samples are written as a stream of bits, starting from the most significant bits:
+//!
+//! - the first timestamp and value are written as they are, in 64 bits each
+//! - the later timestamps are written as the changes of their deltas, after zigzag encoding:
+//!   `0` for no changes, `10`, `110`, `1110`, `11110` and `11111` followed by 7, 9, 12, 32 and 64 bits
+//! - the later values are XORed with the previous ones: `0` for the same values,
+//!   `10` followed by the meaningful bits within the previous window,
+//!   or `11` followed by 5 bits of the leading zeros, 6 bits of the meaningful bits less one,
+//!   and the meaningful bits, which opens a new window (the first changed value always does)
+//!
+//! The timestamps are stored in nanoseconds, so they range from 1677 to 2262.
+
+use std::ops::{Bound, RangeBounds};
+
+use anyhow::{anyhow, bail, Result};
+use bytecheck::CheckBytes;
+use rkyv::{from_archived, Archive, Deserialize, Serialize};
+
+use super::chrono::DateTime;
+use crate::schema::HasSchema;
+
+const NANOS_PER_SEC: i64 = 1_000_000_000;
+
+/// The prefixes and the bits of the changes of the timestamp deltas.
+const DELTA_BUCKETS: &[(u64, u32, u32)] = &[
+    (0b10, 2, 7),
+    (0b110, 3, 9),
+    (0b1110, 4, 12),
+    (0b11110, 5, 32),
+    (0b11111, 5, 64),
+];
+
+/// A series of `f64` samples, sorted by their timestamps.
+#[derive(
+    Clone, Debug, Default, PartialEq, Eq, Hash, Archive, Serialize, Deserialize, HasSchema,
+)]
+#[archive(compare(PartialEq))]
+#[archive_attr(derive(CheckBytes, Debug, PartialEq, Eq, Hash))]
+pub struct TimeSeries {
+    pub(crate) len: u64,
+    /// The compressed samples, padded with zeros.
+    pub(crate) data: Vec<u8>,
+}
+
+/// The ways to aggregate the samples in each interval when resampling.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Aggregate {
+    First,
+    Last,
+    /// Ignores `NaN`s, unless all the values are `NaN`s.
+    Min,
+    /// Ignores `NaN`s, unless all the values are `NaN`s.
+    Max,
+    Sum,
+    Mean,
+    Count,
+}
+
+impl TimeSeries {
+    /// Compresses the samples, failing if they are not sorted by their timestamps.
+    pub fn try_from_samples(samples: impl IntoIterator<Item = (DateTime, f64)>) -> Result<Self> {
+        let mut builder = Self::builder();
+        builder.extend(samples)?;
+        Ok(builder.build())
+    }
+
+    /// Compresses the samples as they come, in a constant time per sample.
+    pub fn builder() -> TimeSeriesBuilder {
+        TimeSeriesBuilder::default()
+    }
+
+    /// Restores the builder from the last sample, to append the samples to the series.
+    pub fn into_builder(self) -> Result<TimeSeriesBuilder> {
+        let mut decoded = self.iter();
+        if decoded.by_ref().count() != self.len() {
+            bail!("malformed time series");
+        }
+        let (len, state) = (decoded.reader.pos, decoded.state);
+
+        Ok(TimeSeriesBuilder {
+            len: self.len,
+            writer: BitWriter {
+                data: self.data,
+                len,
+            },
+            state,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Appends a sample, failing if it is earlier than the last one.
+    ///
+    /// The whole series is decoded to restore the encoder; see `TimeSeriesBuilder`
+    /// to append many samples one by one.
+    pub fn push(&mut self, time: DateTime, value: f64) -> Result<()> {
+        self.extend([(time, value)])
+    }
+
+    /// Appends the samples, failing if they are not sorted by their timestamps.
+    ///
+    /// The series is left as it was on failure.
+    pub fn extend(&mut self, samples: impl IntoIterator<Item = (DateTime, f64)>) -> Result<()> {
+        let mut builder = self.clone().into_builder()?;
+        builder.extend(samples)?;
+        *self = builder.build();
+        Ok(())
+    }
+
+    /// Iterates the samples in order.
+    pub fn iter(&self) -> Samples<'_> {
+        Samples::new(&self.data, self.len)
+    }
+
+    /// Iterates the samples within the range of timestamps.
+    pub fn range(
+        &self,
+        range: impl RangeBounds<DateTime>,
+    ) -> impl Iterator<Item = (DateTime, f64)> + '_ {
+        self.iter().range(range)
+    }
+
+    /// Aggregates the samples in each interval, which starts from a multiple of it since the epoch.
+    ///
+    /// The samples are timestamped at the starts of the intervals, and the empty intervals are skipped.
+    pub fn resample(&self, interval: ::chrono::Duration, aggregate: Aggregate) -> Result<Self> {
+        let interval = interval
+            .num_nanoseconds()
+            .filter(|&interval| interval > 0)
+            .ok_or_else(|| anyhow!("invalid interval: {interval}"))?;
+
+        let mut buckets: Vec<(i64, Vec<f64>)> = Vec::new();
+        for (time, value) in self.iter() {
+            let time = to_nanos(&time)?;
+            let start = time.checked_sub(time.rem_euclid(interval)).ok_or_else(|| {
+                anyhow!("interval out of range: {}", from_nanos(time).to_rfc3339())
+            })?;
+            match buckets.last_mut() {
+                Some((last, values)) if *last == start => values.push(value),
+                _ => buckets.push((start, vec![value])),
+            }
+        }
+
+        Self::try_from_samples(
+            buckets
+                .into_iter()
+                .map(|(start, values)| (from_nanos(start), aggregate.apply(&values))),
+        )
+    }
+}
+
+impl ArchivedTimeSeries {
+    pub fn len(&self) -> usize {
+        from_archived!(self.len) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterates the samples in order, reading the archived bits in place.
+    pub fn iter(&self) -> Samples<'_> {
+        Samples::new(&self.data, from_archived!(self.len))
+    }
+
+    /// Iterates the samples within the range of timestamps.
+    pub fn range(
+        &self,
+        range: impl RangeBounds<DateTime>,
+    ) -> impl Iterator<Item = (DateTime, f64)> + '_ {
+        self.iter().range(range)
+    }
+}
+
+impl Aggregate {
+    fn apply(self, values: &[f64]) -> f64 {
+        match self {
+            Self::First => values[0],
+            Self::Last => values[values.len() - 1],
+            Self::Min => values.iter().copied().fold(f64::NAN, f64::min),
+            Self::Max => values.iter().copied().fold(f64::NAN, f64::max),
+            Self::Sum => values.iter().sum(),
+            Self::Mean => values.iter().sum::<f64>() / values.len() as f64,
+            Self::Count => values.len() as f64,
+        }
+    }
+}
+
+/// An iterator of the samples, which stops early if the bits are malformed.
+#[derive(Clone, Debug)]
+pub struct Samples<'a> {
+    reader: BitReader<'a>,
+    remaining: u64,
+    state: Option<State>,
+}
+
+impl<'a> Samples<'a> {
+    fn new(data: &'a [u8], len: u64) -> Self {
+        Self {
+            reader: BitReader { data, pos: 0 },
+            remaining: len,
+            state: None,
+        }
+    }
+
+    /// Skips the samples before the range, and stops after it.
+    pub fn range(
+        self,
+        range: impl RangeBounds<DateTime>,
+    ) -> impl Iterator<Item = (DateTime, f64)> + 'a {
+        let start = range.start_bound().cloned();
+        let end = range.end_bound().cloned();
+        self.skip_while(move |(time, _)| match &start {
+            Bound::Included(start) => time < start,
+            Bound::Excluded(start) => time <= start,
+            Bound::Unbounded => false,
+        })
+        .take_while(move |(time, _)| match &end {
+            Bound::Included(end) => time <= end,
+            Bound::Excluded(end) => time < end,
+            Bound::Unbounded => true,
+        })
+    }
+
+    fn read(&mut self) -> Option<State> {
+        let reader = &mut self.reader;
+        let state = match self.state {
+            None => State {
+                time: reader.read(64)? as i64,
+                delta: 0,
+                value: reader.read(64)?,
+                window: None,
+            },
+            Some(state) => {
+                let change = if reader.read(1)? == 0 {
+                    0
+                } else {
+                    // count the ones of the prefix, which ends with a zero unless it is the longest
+                    let mut ones = 1;
+                    while ones < DELTA_BUCKETS.len() && reader.read(1)? == 1 {
+                        ones += 1;
+                    }
+                    let (_, _, bits) = DELTA_BUCKETS[ones - 1];
+                    unzigzag(reader.read(bits)?)
+                };
+                let delta = state.delta.wrapping_add(change);
+
+                let (value, window) = if reader.read(1)? == 0 {
+                    (state.value, state.window)
+                } else if reader.read(1)? == 0 {
+                    // there is no window to reuse before the first changed value
+                    let (leading, trailing) = state.window?;
+                    let bits = 64 - leading - trailing;
+                    (state.value ^ (reader.read(bits)? << trailing), state.window)
+                } else {
+                    let leading = reader.read(5)? as u32;
+                    let bits = reader.read(6)? as u32 + 1;
+                    let trailing = 64u32.checked_sub(leading + bits)?;
+                    (
+                        state.value ^ (reader.read(bits)? << trailing),
+                        Some((leading, trailing)),
+                    )
+                };
+
+                State {
+                    time: state.time.wrapping_add(delta),
+                    delta,
+                    value,
+                    window,
+                }
+            }
+        };
+        self.state = Some(state);
+        Some(state)
+    }
+}
+
+impl Iterator for Samples<'_> {
+    type Item = (DateTime, f64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        match self.read() {
+            Some(state) => {
+                self.remaining -= 1;
+                Some((from_nanos(state.time), f64::from_bits(state.value)))
+            }
+            None => {
+                self.remaining = 0;
+                None
+            }
+        }
+    }
+}
+
+/// The last sample, and the window of its meaningful bits.
+#[derive(Copy, Clone, Debug, Default)]
+struct State {
+    time: i64,
+    delta: i64,
+    value: u64,
+    /// The leading and trailing zeros of the last written XOR, if any.
+    window: Option<(u32, u32)>,
+}
+
+/// Compresses the samples of a `TimeSeries` as they come.
+#[derive(Debug, Default)]
+pub struct TimeSeriesBuilder {
+    len: u64,
+    writer: BitWriter,
+    state: Option<State>,
+}
+
+impl TimeSeriesBuilder {
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Appends a sample, failing if it is earlier than the last one.
+    ///
+    /// The builder is left as it was on failure.
+    pub fn push(&mut self, time: DateTime, value: f64) -> Result<()> {
+        let (time, value) = (to_nanos(&time)?, value.to_bits());
+        self.state = Some(match self.state {
+            None => {
+                self.writer.write(time as u64, 64);
+                self.writer.write(value, 64);
+                State {
+                    time,
+                    value,
+                    ..Default::default()
+                }
+            }
+            Some(state) if time < state.time => {
+                bail!("unsorted timestamps at the sample {}", self.len)
+            }
+            Some(state) => self.write(state, time, value),
+        });
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Appends the samples, failing if they are not sorted by their timestamps.
+    ///
+    /// The samples before the failed one are kept.
+    pub fn extend(&mut self, samples: impl IntoIterator<Item = (DateTime, f64)>) -> Result<()> {
+        samples
+            .into_iter()
+            .try_for_each(|(time, value)| self.push(time, value))
+    }
+
+    pub fn build(self) -> TimeSeries {
+        TimeSeries {
+            len: self.len,
+            data: self.writer.data,
+        }
+    }
+
+    fn write(&mut self, state: State, time: i64, value: u64) -> State {
+        let writer = &mut self.writer;
+
+        let delta = time.wrapping_sub(state.time);
+        let change = zigzag(delta.wrapping_sub(state.delta));
+        if change == 0 {
+            writer.write(0, 1);
+        } else {
+            let &(prefix, prefix_bits, bits) = DELTA_BUCKETS
+                .iter()
+                .find(|(_, _, bits)| *bits == 64 || change < 1 << bits)
+                .unwrap();
+            writer.write(prefix, prefix_bits);
+            writer.write(change, bits);
+        }
+
+        let xor = value ^ state.value;
+        let window = if xor == 0 {
+            writer.write(0, 1);
+            state.window
+        } else {
+            let leading = xor.leading_zeros().min(31);
+            let trailing = xor.trailing_zeros();
+            match state.window {
+                Some((window_leading, window_trailing))
+                    if leading >= window_leading && trailing >= window_trailing =>
+                {
+                    writer.write(0b10, 2);
+                    writer.write(
+                        xor >> window_trailing,
+                        64 - window_leading - window_trailing,
+                    );
+                    state.window
+                }
+                _ => {
+                    let bits = 64 - leading - trailing;
+                    writer.write(0b11, 2);
+                    writer.write(leading as u64, 5);
+                    writer.write(bits as u64 - 1, 6);
+                    writer.write(xor >> trailing, bits);
+                    Some((leading, trailing))
+                }
+            }
+        };
+
+        State {
+            time,
+            delta,
+            value,
+            window,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct BitWriter {
+    data: Vec<u8>,
+    /// The number of the written bits.
+    len: u64,
+}
+
+impl BitWriter {
+    /// Writes the lowest bits of the value, from the most significant one.
+    fn write(&mut self, value: u64, bits: u32) {
+        for index in (0..bits).rev() {
+            if self.len.is_multiple_of(8) {
+                self.data.push(0);
+            }
+            if (value >> index) & 1 == 1 {
+                *self.data.last_mut().unwrap() |= 0x80 >> (self.len % 8);
+            }
+            self.len += 1;
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct BitReader<'a> {
+    data: &'a [u8],
+    /// The number of the read bits.
+    pos: u64,
+}
+
+impl BitReader<'_> {
+    fn read(&mut self, bits: u32) -> Option<u64> {
+        let mut value = 0;
+        for _ in 0..bits {
+            let byte = self.data.get((self.pos / 8) as usize)?;
+            value = (value << 1) | ((byte >> (7 - self.pos % 8)) & 1) as u64;
+            self.pos += 1;
+        }
+        Some(value)
+    }
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+fn to_nanos(time: &DateTime) -> Result<i64> {
+    let nanos = time.timestamp_subsec_nanos() as i64;
+    if nanos >= NANOS_PER_SEC {
+        bail!("leap seconds are not supported: {}", time.to_rfc3339());
+    }
+    time.timestamp()
+        .checked_mul(NANOS_PER_SEC)
+        .and_then(|time| time.checked_add(nanos))
+        .ok_or_else(|| anyhow!("timestamp out of range: {}", time.to_rfc3339()))
+}
+
+fn from_nanos(time: i64) -> DateTime {
+    DateTime(::chrono::DateTime::from_utc(
+        ::chrono::NaiveDateTime::from_timestamp(
+            time.div_euclid(NANOS_PER_SEC),
+            time.rem_euclid(NANOS_PER_SEC) as u32,
+        ),
+        ::chrono::Utc,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timestamp(secs: i64, nanos: u32) -> DateTime {
+        DateTime(::chrono::DateTime::from_utc(
+            ::chrono::NaiveDateTime::from_timestamp(secs, nanos),
+            ::chrono::Utc,
+        ))
+    }
+
+    #[test]
+    fn test_time_series() {
+        // regular samples with a few irregular ones
+        let samples: Vec<_> = (0..1000)
+            .map(|index| {
+                let secs = 1_600_000_000 + index * 10 + (index % 97 == 0) as i64;
+                let value = 20.0 + (index / 50) as f64 * 0.5;
+                (timestamp(secs, (index % 7 == 0) as u32 * 1_234), value)
+            })
+            .chain([
+                (timestamp(1_600_100_000, 0), f64::NAN),
+                (timestamp(1_600_100_000, 0), -0.0),
+                (timestamp(i64::MAX / NANOS_PER_SEC - 1, 0), f64::MAX),
+                (
+                    timestamp(i64::MAX / NANOS_PER_SEC - 1, 0),
+                    f64::MIN_POSITIVE,
+                ),
+            ])
+            .collect();
+
+        let series = TimeSeries::try_from_samples(samples.iter().copied()).unwrap();
+        assert_eq!(series.len(), samples.len());
+        assert!(series
+            .iter()
+            .zip(&samples)
+            .all(|(a, b)| a.0 == b.0 && a.1.to_bits() == b.1.to_bits()));
+
+        // much smaller than the samples
+        assert!(
+            series.data.len() < samples.len() * 16 / 4,
+            "{}",
+            series.data.len()
+        );
+
+        // appending
+        let (head, tail) = samples.split_at(500);
+        let mut appended = TimeSeries::try_from_samples(head.iter().copied()).unwrap();
+        appended.extend(tail.iter().copied()).unwrap();
+        assert_eq!(appended, series);
+        assert!(appended.push(timestamp(0, 0), 1.0).is_err());
+        assert_eq!(appended, series);
+
+        // building one by one
+        let mut builder = TimeSeries::builder();
+        for &(time, value) in &samples {
+            builder.push(time, value).unwrap();
+        }
+        assert!(builder.push(timestamp(0, 0), 1.0).is_err());
+        assert_eq!(builder.len(), samples.len());
+        assert_eq!(builder.build(), series);
+
+        let mut builder = TimeSeries::try_from_samples(head.iter().copied())
+            .unwrap()
+            .into_builder()
+            .unwrap();
+        builder.extend(tail.iter().copied()).unwrap();
+        assert_eq!(builder.build(), series);
+
+        // unsupported timestamps
+        assert!(TimeSeries::try_from_samples([(timestamp(32_503_680_000, 0), 1.0)]).is_err());
+        assert!(TimeSeries::try_from_samples([(timestamp(0, 1_500_000_000), 1.0)]).is_err());
+
+        // archive
+        let bytes = ::rkyv::to_bytes::<_, 4096>(&series).unwrap();
+        let archived = ::rkyv::check_archived_root::<TimeSeries>(&bytes).unwrap();
+        assert_eq!(archived, &series);
+        assert_eq!(archived.len(), series.len());
+        assert!(archived
+            .iter()
+            .zip(series.iter())
+            .all(|(a, b)| a.0 == b.0 && a.1.to_bits() == b.1.to_bits()));
+
+        // malformed bits
+        let mut truncated = series.clone();
+        truncated.data.truncate(100);
+        assert!(truncated.iter().count() < series.len());
+        assert!(truncated
+            .push(timestamp(i64::MAX / NANOS_PER_SEC, 0), 0.0)
+            .is_err());
+    }
+
+    #[test]
+    fn test_time_series_changing_values() {
+        // a noisy sine of single precision readings, which changes at every sample
+        let samples: Vec<_> = (0..1000)
+            .map(|index| {
+                let noise = ((index * 7919) % 101) as f64 / 1000.0;
+                let value = (index as f64 / 20.0).sin() * 10.0 + noise;
+                (
+                    timestamp(1_600_000_000 + index * 10, 0),
+                    value as f32 as f64,
+                )
+            })
+            .collect();
+        assert!(samples.windows(2).all(|pair| pair[0].1 != pair[1].1));
+
+        let series = TimeSeries::try_from_samples(samples.iter().copied()).unwrap();
+        assert!(series
+            .iter()
+            .zip(&samples)
+            .all(|(a, b)| a.0 == b.0 && a.1.to_bits() == b.1.to_bits()));
+
+        // the changed values are written within their windows, in much less than 64 bits
+        let bits_per_sample = series.data.len() * 8 / samples.len();
+        assert!(bits_per_sample < 48, "{bits_per_sample}");
+    }
+
+    #[test]
+    fn test_time_series_queries() {
+        let series = TimeSeries::try_from_samples(
+            (0..60).map(|index| (timestamp(index * 15, 0), index as f64)),
+        )
+        .unwrap();
+
+        // range
+        let range: Vec<_> = series
+            .range(timestamp(30, 0)..timestamp(75, 0))
+            .map(|(_, value)| value)
+            .collect();
+        assert_eq!(range, [2.0, 3.0, 4.0]);
+        assert_eq!(series.range(timestamp(30, 0)..=timestamp(75, 0)).count(), 4);
+        assert_eq!(series.range(..timestamp(0, 1)).count(), 1);
+        assert_eq!(series.range(timestamp(900, 0)..).count(), 0);
+
+        // resample by minutes
+        let resampled = |aggregate| {
+            series
+                .resample(::chrono::Duration::minutes(1), aggregate)
+                .unwrap()
+                .iter()
+                .map(|(_, value)| value)
+                .take(2)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(resampled(Aggregate::First), [0.0, 4.0]);
+        assert_eq!(resampled(Aggregate::Last), [3.0, 7.0]);
+        assert_eq!(resampled(Aggregate::Min), [0.0, 4.0]);
+        assert_eq!(resampled(Aggregate::Max), [3.0, 7.0]);
+        assert_eq!(resampled(Aggregate::Sum), [6.0, 22.0]);
+        assert_eq!(resampled(Aggregate::Mean), [1.5, 5.5]);
+        assert_eq!(resampled(Aggregate::Count), [4.0, 4.0]);
+
+        let resampled = series
+            .resample(::chrono::Duration::minutes(1), Aggregate::Count)
+            .unwrap();
+        assert_eq!(resampled.len(), 15);
+        assert_eq!(resampled.iter().nth(1).unwrap().0, timestamp(60, 0));
+        assert!(series
+            .resample(::chrono::Duration::zero(), Aggregate::Sum)
+            .is_err());
+
+        // the interval of the earliest sample would start before the supported range
+        let earliest =
+            TimeSeries::try_from_samples([(timestamp(i64::MIN / NANOS_PER_SEC, 0), 1.0)]).unwrap();
+        assert!(earliest
+            .resample(::chrono::Duration::hours(1), Aggregate::Sum)
+            .is_err());
+        assert_eq!(
+            earliest
+                .resample(::chrono::Duration::nanoseconds(1), Aggregate::Sum)
+                .unwrap(),
+            earliest,
+        );
+    }
+}
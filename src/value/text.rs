@@ -2,8 +2,9 @@ use bytecheck::CheckBytes;
 use rkyv::{ser::Serializer, string::ArchivedString, Archive, Deserialize, Fallible, Serialize};
 
 use super::hash::Hash;
+use crate::schema::HasSchema;
 
-#[derive(Clone, PartialEq, Eq, Hash, Archive, Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Eq, Hash, Archive, Serialize, Deserialize, HasSchema)]
 #[archive(compare(PartialEq))]
 #[archive_attr(derive(CheckBytes, Debug, PartialEq, Eq, Hash))]
 pub struct Text {
@@ -38,7 +39,7 @@ impl Text {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Archive, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Archive, Serialize, Deserialize, HasSchema)]
 #[archive(compare(PartialEq))]
 #[archive_attr(derive(CheckBytes, Debug, PartialEq, Eq, Hash))]
 pub struct TextHash {
@@ -76,25 +77,13 @@ impl ::core::ops::Deref for LanguageTag {
     }
 }
 
-impl PartialEq<LanguageTag> for ArchivedString {
-    fn eq(&self, other: &LanguageTag) -> bool {
-        self.as_ref() == other.0.as_str()
-    }
-}
-
-impl PartialOrd<LanguageTag> for ArchivedString {
-    fn partial_cmp(&self, other: &LanguageTag) -> Option<::core::cmp::Ordering> {
-        self.as_ref().partial_cmp(other.0.as_str())
-    }
-}
-
 impl Archive for LanguageTag {
-    type Archived = <String as Archive>::Archived;
+    type Archived = ArchivedLanguageTag;
     type Resolver = <String as Archive>::Resolver;
 
     #[inline]
     unsafe fn resolve(&self, pos: usize, resolver: Self::Resolver, out: *mut Self::Archived) {
-        self.0.to_string().resolve(pos, resolver, out)
+        ArchivedString::resolve_from_str(self.0.as_str(), pos, resolver, out.cast())
     }
 }
 
@@ -104,21 +93,102 @@ where
 {
     #[inline]
     fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
-        self.0.to_string().serialize(serializer)
+        ArchivedString::serialize_from_str(self.0.as_str(), serializer)
     }
 }
 
-impl<D: Fallible + ?Sized> Deserialize<LanguageTag, D> for <LanguageTag as Archive>::Archived {
+impl<D: Fallible + ?Sized> Deserialize<LanguageTag, D> for ArchivedLanguageTag
+where
+    D::Error: From<::language_tags::ParseError>,
+{
     #[inline]
-    fn deserialize(&self, deserializer: &mut D) -> Result<LanguageTag, D::Error> {
-        Deserialize::<String, D>::deserialize(self, deserializer)
-            // FIXME: handle chrono input errors
-            .map(|ref e| e.parse().unwrap())
+    fn deserialize(&self, _deserializer: &mut D) -> Result<LanguageTag, D::Error> {
+        self.0.parse().map_err(Into::into)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct ArchivedLanguageTag(ArchivedString);
+
+impl ::core::ops::Deref for ArchivedLanguageTag {
+    type Target = ArchivedString;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl PartialEq<LanguageTag> for ArchivedLanguageTag {
+    fn eq(&self, other: &LanguageTag) -> bool {
+        self.0.as_str() == other.0.as_str()
     }
 }
 
+impl PartialOrd<LanguageTag> for ArchivedLanguageTag {
+    fn partial_cmp(&self, other: &LanguageTag) -> Option<::core::cmp::Ordering> {
+        self.0.as_str().partial_cmp(other.0.as_str())
+    }
+}
+
+impl<C: ?Sized> CheckBytes<C> for ArchivedLanguageTag
+where
+    ArchivedString: CheckBytes<C>,
+{
+    type Error = LanguageTagCheckError<<ArchivedString as CheckBytes<C>>::Error>;
+
+    #[inline]
+    unsafe fn check_bytes<'a>(
+        value: *const Self,
+        context: &mut C,
+    ) -> Result<&'a Self, Self::Error> {
+        let value = ArchivedString::check_bytes(value.cast(), context)
+            .map_err(LanguageTagCheckError::String)?;
+        value
+            .parse::<LanguageTag>()
+            .map_err(LanguageTagCheckError::Parse)?;
+        Ok(&*(value as *const ArchivedString).cast())
+    }
+}
+
+#[derive(Debug)]
+pub enum LanguageTagCheckError<E> {
+    String(E),
+    Parse(::language_tags::ParseError),
+}
+
+impl<E> ::core::fmt::Display for LanguageTagCheckError<E>
+where
+    E: ::core::fmt::Display,
+{
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+        match self {
+            Self::String(error) => write!(f, "failed to check LanguageTag: {error}"),
+            Self::Parse(error) => write!(f, "failed to parse LanguageTag: {error}"),
+        }
+    }
+}
+
+impl<E> ::std::error::Error for LanguageTagCheckError<E> where E: ::std::error::Error {}
+
 impl LanguageTag {
     pub fn new_en_us() -> Self {
         "en-us".parse().unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_malformed_language_tag() {
+        // a string which is not a language tag
+        let bytes = ::rkyv::to_bytes::<_, 64>(&String::from("not a tag")).unwrap();
+        assert!(::rkyv::check_archived_root::<LanguageTag>(&bytes).is_err());
+        assert!(crate::signed::from_bytes::<LanguageTag>(&bytes).is_err());
+
+        let bytes = ::rkyv::to_bytes::<_, 64>(&String::from("en-us")).unwrap();
+        assert!(::rkyv::check_archived_root::<LanguageTag>(&bytes).is_ok());
+    }
+}
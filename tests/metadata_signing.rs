@@ -1,20 +1,34 @@
 use bytecheck::CheckBytes;
 use ipi::{
-    account::{Account, GuarantorSigned, Signer, Verifier},
+    account::{Account, GuaranteeSigned, GuarantorSigned, Signer, Verifier},
+    canonical::Canonical,
+    detached::Detached,
     metadata::Metadata,
-    signed::SERIALIZER_HEAP_SIZE,
+    schema::HasSchema,
+    signature::SignatureContext,
+    signed::{Deserializer, SERIALIZER_HEAP_SIZE},
+    value::{
+        array::{chunked::ChunkedArray, sparse::CsrArray, Array},
+        chrono::{DateTime, Duration},
+        compressed::{CompressedArray, Compression, Encoding},
+        expr::Expr,
+        schema::{Constraint, FieldSchema, RecordSchema},
+        series::{Aggregate, TimeSeries},
+        ArchivedValue, Field, Value, ValueType,
+    },
 };
-use rkyv::{de::deserializers::SharedDeserializeMap, Archive, Deserialize, Serialize};
+use rkyv::{Archive, Deserialize, Serialize};
 
 #[test]
 fn test_simple() {
-    #[derive(Debug, PartialEq, Eq, Archive, Serialize, Deserialize)]
+    #[derive(Debug, PartialEq, Eq, Archive, Serialize, Deserialize, HasSchema)]
     #[archive(compare(PartialEq))]
     #[archive_attr(derive(CheckBytes, Debug, PartialEq))]
     pub struct MyData {
         pub msg: String,
         pub num: u64,
     }
+    ::ipi::impl_canonical!(MyData { msg, num });
 
     // create a data
     let data = MyData {
@@ -46,10 +60,424 @@ fn test_simple() {
 
     // deserialize
     let deserialized: GuarantorSigned<MyData> =
-        Deserialize::deserialize(archived, &mut SharedDeserializeMap::default()).unwrap();
+        Deserialize::deserialize(archived, &mut Deserializer::default()).unwrap();
     assert_eq!(&signed, &deserialized);
 }
 
+#[test]
+fn test_schema_mismatch() {
+    #[derive(Debug, PartialEq, Eq, Archive, Serialize, Deserialize, HasSchema)]
+    #[archive(compare(PartialEq))]
+    #[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+    pub struct MyData {
+        pub a: u32,
+        pub b: u32,
+    }
+    ::ipi::impl_canonical!(MyData { a, b });
+
+    // the same fields in the reversed order
+    #[derive(Debug, PartialEq, Eq, Archive, Serialize, Deserialize, HasSchema)]
+    #[archive(compare(PartialEq))]
+    #[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+    pub struct MyDataReordered {
+        pub b: u32,
+        pub a: u32,
+    }
+    ::ipi::impl_canonical!(MyDataReordered { b, a });
+
+    // sign as guarantee
+    let guarantee = Account::generate();
+    let signed = Metadata::builder()
+        .build(&guarantee, guarantee.account_ref(), MyData { a: 1, b: 2 })
+        .unwrap();
+
+    // archive
+    let bytes = ::rkyv::to_bytes::<_, SERIALIZER_HEAP_SIZE>(&signed).unwrap();
+    assert!(::rkyv::check_archived_root::<GuaranteeSigned<MyData>>(&bytes[..]).is_ok());
+
+    // decode with the reordered schema
+    let error =
+        ::rkyv::check_archived_root::<GuaranteeSigned<MyDataReordered>>(&bytes[..]).unwrap_err();
+    assert!(error.to_string().contains("schema mismatching"));
+}
+
+#[test]
+fn test_dynamic() {
+    // create a dynamic document
+    let data = Value::Record(vec![
+        Field {
+            name: "title".into(),
+            value: Value::String("Hello world!".into()),
+        },
+        Field {
+            name: "scores".into(),
+            value: Value::Map(
+                [
+                    (
+                        "a".to_string(),
+                        Value::List(vec![Value::U8(1), Value::U8(2)]),
+                    ),
+                    ("b".to_string(), Value::List(vec![])),
+                ]
+                .into(),
+            ),
+        },
+    ]);
+
+    // sign as guarantee
+    let guarantee = Account::generate();
+    let signed = Metadata::builder()
+        .build(&guarantee, guarantee.account_ref(), data)
+        .unwrap();
+    signed.verify(Some(guarantee.account_ref())).unwrap();
+
+    // archive
+    let bytes = ::rkyv::to_bytes::<_, SERIALIZER_HEAP_SIZE>(&signed).unwrap();
+    let archived = ::rkyv::check_archived_root::<GuaranteeSigned<Value>>(&bytes[..]).unwrap();
+    assert_eq!(archived, &signed);
+
+    // access the archived document in place
+    let scores = match &archived.data.data {
+        ArchivedValue::Record(fields) => &fields[1].value,
+        _ => unreachable!(),
+    };
+    match scores {
+        ArchivedValue::Map(scores) => match scores.get("a") {
+            Some(ArchivedValue::List(values)) => assert_eq!(values.len(), 2),
+            _ => unreachable!(),
+        },
+        _ => unreachable!(),
+    }
+
+    // deserialize
+    let deserialized: GuaranteeSigned<Value> =
+        Deserialize::deserialize(archived, &mut Deserializer::default()).unwrap();
+    assert_eq!(&signed, &deserialized);
+}
+
+#[test]
+fn test_record_schema() {
+    // publish a signed schema as guarantor
+    let guarantor = Account::generate();
+    let schema = RecordSchema {
+        fields: vec![FieldSchema {
+            name: "title".into(),
+            ty: ValueType::String,
+            optional: false,
+            default: None,
+            constraints: vec![Constraint::Length {
+                min: Some(1),
+                max: Some(16),
+            }],
+        }],
+    };
+    let schema = Metadata::builder()
+        .build(&guarantor, guarantor.account_ref(), schema)
+        .unwrap();
+    let bytes = ::rkyv::to_bytes::<_, SERIALIZER_HEAP_SIZE>(&schema).unwrap();
+
+    // load the published schema
+    let schema: GuaranteeSigned<RecordSchema> = ::ipi::signed::from_bytes(&bytes).unwrap();
+    schema.verify(Some(guarantor.account_ref())).unwrap();
+
+    // check the payloads
+    let payload = Value::Record(vec![Field {
+        name: "title".into(),
+        value: Value::String("Hello world!".into()),
+    }]);
+    schema.data.data.validate(&payload).unwrap();
+
+    let payload = Value::Record(vec![Field {
+        name: "title".into(),
+        value: Value::String(String::new()),
+    }]);
+    let error = schema.data.data.validate(&payload).unwrap_err();
+    assert_eq!(error.to_string(), "$.title: length 0 is less than 1");
+}
+
+#[test]
+fn test_filter_policy() {
+    // publish a signed filter policy as guarantor
+    let guarantor = Account::generate();
+    let policy: Expr = "score > 0.7 and kind in [\"news\", \"sports\"]"
+        .parse()
+        .unwrap();
+    let policy = Metadata::builder()
+        .build(&guarantor, guarantor.account_ref(), policy)
+        .unwrap();
+    let bytes = ::rkyv::to_bytes::<_, SERIALIZER_HEAP_SIZE>(&policy).unwrap();
+
+    // load the published policy
+    let policy: GuaranteeSigned<Expr> = ::ipi::signed::from_bytes(&bytes).unwrap();
+    policy.verify(Some(guarantor.account_ref())).unwrap();
+
+    // evaluate the policy against the archived records
+    for (score, expected) in [(0.9, true), (0.5, false)] {
+        let record = Value::Record(vec![
+            Field {
+                name: "score".into(),
+                value: Value::F64(score),
+            },
+            Field {
+                name: "kind".into(),
+                value: Value::String("news".into()),
+            },
+        ]);
+        let bytes = ::rkyv::to_bytes::<_, SERIALIZER_HEAP_SIZE>(&record).unwrap();
+        let archived = ::rkyv::check_archived_root::<Value>(&bytes).unwrap();
+        assert_eq!(policy.data.data.eval_archived(archived), expected);
+    }
+}
+
+#[test]
+fn test_compressed_array() {
+    #[derive(Debug, PartialEq, Archive, Serialize, Deserialize, HasSchema)]
+    #[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+    pub struct MySensor {
+        pub name: String,
+        pub samples: CompressedArray<f32, ::ndarray::Ix2>,
+    }
+    ::ipi::impl_canonical!(MySensor { name, samples });
+
+    // compress the samples
+    let samples = ::ndarray::Array::from_shape_fn((128, 4), |(i, j)| (i * j) as f32 * 0.5);
+    let encoding = Encoding {
+        compression: Compression::None,
+        shuffle: true,
+    };
+    let data = MySensor {
+        name: "thermometer".into(),
+        samples: CompressedArray::encode(&samples, encoding).unwrap(),
+    };
+
+    // the signature covers the compressed bytes
+    let guarantee = Account::generate();
+    let signed = Metadata::builder()
+        .build(&guarantee, guarantee.account_ref(), data)
+        .unwrap();
+    signed.verify(Some(guarantee.account_ref())).unwrap();
+
+    // decode the archived samples
+    let bytes = ::rkyv::to_bytes::<_, SERIALIZER_HEAP_SIZE>(&signed).unwrap();
+    let archived = ::rkyv::check_archived_root::<GuaranteeSigned<MySensor>>(&bytes).unwrap();
+    assert_eq!(archived.data.data.samples.decode().unwrap().0, samples);
+}
+
+#[test]
+fn test_sparse_array() {
+    #[derive(Debug, PartialEq, Archive, Serialize, Deserialize, HasSchema)]
+    #[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+    pub struct MyGraph {
+        pub edges: CsrArray<u32>,
+    }
+    ::ipi::impl_canonical!(MyGraph { edges });
+
+    // store only the edges of the adjacency matrix
+    let matrix =
+        ::ndarray::Array::from_shape_fn(
+            (64, 64),
+            |(i, j)| {
+                if (i + 1) % 64 == j {
+                    i as u32 + 1
+                } else {
+                    0
+                }
+            },
+        );
+    let data = MyGraph {
+        edges: CsrArray::from_dense(&Array(matrix.clone().into_shared())),
+    };
+    assert_eq!(data.edges.nnz(), 64);
+
+    let guarantee = Account::generate();
+    let signed = Metadata::builder()
+        .build(&guarantee, guarantee.account_ref(), data)
+        .unwrap();
+    signed.verify(Some(guarantee.account_ref())).unwrap();
+
+    // iterate the archived edges in place
+    let bytes = ::rkyv::to_bytes::<_, SERIALIZER_HEAP_SIZE>(&signed).unwrap();
+    let archived = ::rkyv::check_archived_root::<GuaranteeSigned<MyGraph>>(&bytes).unwrap();
+    let edges = &archived.data.data.edges;
+    assert_eq!(edges.nnz(), 64);
+    assert!(edges.iter().all(|(i, j, _)| (i + 1) % 64 == j));
+}
+
+#[test]
+fn test_chunked_array() {
+    // store the chunks apart from the manifest
+    let mut store = ::std::collections::BTreeMap::new();
+    let array = ::ndarray::Array::from_shape_fn((100, 50), |(i, j)| (i * 50 + j) as f32);
+    let manifest = ChunkedArray::write(&array, &[16, 16], Encoding::default(), &mut store).unwrap();
+    assert_eq!(store.len(), 7 * 4);
+
+    // sign the manifest once
+    let guarantee = Account::generate();
+    let signed = Metadata::builder()
+        .build(&guarantee, guarantee.account_ref(), manifest)
+        .unwrap();
+    signed.verify(Some(guarantee.account_ref())).unwrap();
+
+    // read a slice of the archived manifest piecewise
+    let bytes = ::rkyv::to_bytes::<_, SERIALIZER_HEAP_SIZE>(&signed).unwrap();
+    let archived =
+        ::rkyv::check_archived_root::<GuaranteeSigned<ChunkedArray<f32>>>(&bytes).unwrap();
+    let slice = archived
+        .data
+        .data
+        .read_slice(&[40..60, 10..20], &store)
+        .unwrap();
+    assert_eq!(
+        slice.0,
+        array.slice(::ndarray::s![40..60, 10..20]).into_dyn()
+    );
+}
+
+#[test]
+fn test_time_series() {
+    #[derive(Clone, Debug, PartialEq, Archive, Serialize, Deserialize, HasSchema)]
+    #[archive(compare(PartialEq))]
+    #[archive_attr(derive(CheckBytes, Debug, PartialEq))]
+    pub struct Sensor {
+        pub name: String,
+        pub readings: TimeSeries,
+    }
+    ::ipi::impl_canonical!(Sensor { name, readings });
+
+    // sample every 10 seconds, from a multiple of 10 minutes
+    let start = 1_599_999_600;
+    let time = |secs: i64| DateTime(::chrono::TimeZone::timestamp(&::chrono::Utc, secs, 0));
+    let readings = TimeSeries::try_from_samples(
+        (0..360).map(|index| (time(start + index * 10), 20.0 + (index / 60) as f64)),
+    )
+    .unwrap();
+    let data = Sensor {
+        name: "thermometer".into(),
+        readings,
+    };
+
+    let guarantee = Account::generate();
+    let signed = Metadata::builder()
+        .build(&guarantee, guarantee.account_ref(), data.clone())
+        .unwrap();
+    signed.verify(Some(guarantee.account_ref())).unwrap();
+
+    // query the archived samples in place
+    let bytes = ::rkyv::to_bytes::<_, SERIALIZER_HEAP_SIZE>(&signed).unwrap();
+    let archived = ::rkyv::check_archived_root::<GuaranteeSigned<Sensor>>(&bytes).unwrap();
+    let readings = &archived.data.data.readings;
+    assert_eq!(readings.len(), 360);
+    let window: Vec<_> = readings
+        .range(time(start + 600)..time(start + 650))
+        .collect();
+    assert_eq!(window.len(), 5);
+    assert!(window.iter().all(|&(_, value)| value == 21.0));
+
+    // resample into the averages per 10 minutes
+    let resampled = data
+        .readings
+        .resample(::chrono::Duration::minutes(10), Aggregate::Mean)
+        .unwrap();
+    let means: Vec<_> = resampled.iter().map(|(_, value)| value).collect();
+    assert_eq!(means, [20.0, 21.0, 22.0, 23.0, 24.0, 25.0]);
+
+    // as a dynamic value
+    let value = Value::TimeSeries(data.readings);
+    assert_eq!(
+        Value::parse(&value.to_string(), ValueType::TimeSeries).unwrap(),
+        value
+    );
+}
+
+#[test]
+fn test_ttl() {
+    let guarantee = Account::generate();
+    let ttl = Duration(::chrono::Duration::hours(1));
+    let signed = Metadata::builder()
+        .expiration_date("2020-01-01T00:00:00Z".parse().unwrap())
+        .ttl(ttl)
+        .build(&guarantee, guarantee.account_ref(), 42u64)
+        .unwrap();
+    signed.verify(Some(guarantee.account_ref())).unwrap();
+
+    // the expiration date follows the creation
+    let metadata = &signed.data;
+    assert_eq!(metadata.expiration_date, Some(metadata.created_date + ttl));
+
+    // an overflowing ttl
+    assert!(Metadata::builder()
+        .ttl(Duration(::chrono::Duration::max_value()))
+        .build(&guarantee, guarantee.account_ref(), 42u64)
+        .is_err());
+}
+
+#[test]
+fn test_context() {
+    // create a shared context
+    let context = ::std::sync::Arc::new(SignatureContext::new());
+    let guarantee = ::std::sync::Arc::new(Account::generate());
+
+    let handles: Vec<_> = (0..4u64)
+        .map(|num| {
+            let context = context.clone();
+            let guarantee = guarantee.clone();
+            ::std::thread::spawn(move || {
+                for _ in 0..16 {
+                    let signed = Metadata::builder()
+                        .build_with(&context, &guarantee, guarantee.account_ref(), num)
+                        .unwrap();
+                    signed
+                        .verify_with(&context, Some(guarantee.account_ref()))
+                        .unwrap();
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    // sign already-encoded bytes
+    let bytes = 42u64.to_canonical_bytes();
+    let identity = guarantee.sign_bytes(&bytes);
+    identity.verify_bytes(&bytes).unwrap();
+    assert!(identity.verify_bytes(&bytes[1..]).is_err());
+}
+
+#[test]
+fn test_detached() {
+    // a payload too large to be signed in place
+    let content: Vec<u8> = (0..1_000_000u32).map(|e| e as u8).collect();
+
+    // create client pair
+    let guarantee = Account::generate();
+    let guarantor = Account::generate();
+
+    // sign the content reference as guarantee
+    let detached = Detached::with_reader(&content[..]).unwrap();
+    let signed = Metadata::builder()
+        .build(&guarantee, guarantor.account_ref(), detached)
+        .unwrap();
+
+    // sign as guarantor
+    let signed = GuarantorSigned::sign(&guarantor, signed).unwrap();
+
+    // verify with the original content
+    signed
+        .verify_content(Some(guarantor.account_ref()), &content[..])
+        .unwrap();
+
+    // verify with a tampered content
+    let mut tampered = content.clone();
+    tampered[42] ^= 1;
+    assert!(signed
+        .verify_content(Some(guarantor.account_ref()), &tampered[..])
+        .is_err());
+    assert!(signed
+        .verify_content(Some(guarantor.account_ref()), &content[1..])
+        .is_err());
+}
+
 #[test]
 fn test_strict() {
     let account = ::ipi::account::Account {
@@ -67,22 +495,59 @@ fn test_strict() {
         ),
         expiration_date: None,
         guarantor: account.account_ref(),
+        schema: Default::default(),
         data: 42,
     };
 
-    let signed = ::ipi::account::GuaranteeSigned::sign(&account, metadata).unwrap();
-    let signed = ::rkyv::to_bytes::<_, 4096>(&signed).unwrap();
-
+    // the signed bytes are the same on every platform
     let bytes = &[
         85, 14, 132, 0, 226, 155, 65, 212, 167, 22, 68, 102, 85, 68, 0, 0, 0, 0, 0, 0, 24, 250,
-        181, 234, 16, 84, 232, 128, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 42, 178, 127, 84, 7, 76, 6, 240, 252, 66, 76, 107, 153, 78,
-        227, 199, 47, 255, 205, 198, 205, 169, 240, 131, 27, 107, 97, 3, 20, 99, 143, 106, 117, 0,
-        0, 0, 0, 178, 127, 84, 7, 76, 6, 240, 252, 66, 76, 107, 153, 78, 227, 199, 47, 255, 205,
-        198, 205, 169, 240, 131, 27, 107, 97, 3, 20, 99, 143, 106, 117, 20, 155, 255, 188, 70, 111,
-        125, 199, 143, 89, 40, 85, 122, 83, 50, 246, 101, 130, 239, 19, 255, 248, 252, 51, 33, 3,
-        53, 39, 207, 68, 254, 105, 94, 185, 75, 147, 107, 196, 15, 27, 11, 90, 199, 243, 72, 232,
-        214, 40, 60, 73, 20, 91, 240, 36, 152, 59, 252, 45, 63, 65, 220, 234, 217, 15,
+        181, 234, 16, 84, 232, 128, 0, 178, 127, 84, 7, 76, 6, 240, 252, 66, 76, 107, 153, 78, 227,
+        199, 47, 255, 205, 198, 205, 169, 240, 131, 27, 107, 97, 3, 20, 99, 143, 106, 117, 87, 154,
+        110, 107, 52, 42, 17, 185, 192, 31, 255, 212, 14, 219, 36, 173, 59, 166, 60, 229, 15, 21,
+        176, 150, 97, 157, 68, 21, 194, 21, 9, 175, 0, 0, 0, 42,
+    ];
+    assert_eq!(metadata.to_canonical_bytes().as_slice(), bytes);
+
+    let signed = ::ipi::account::GuaranteeSigned::sign(&account, metadata).unwrap();
+
+    let signature = &[
+        142, 136, 144, 203, 118, 39, 28, 67, 89, 69, 217, 71, 8, 162, 237, 101, 27, 69, 211, 121,
+        114, 102, 16, 38, 211, 234, 160, 144, 131, 219, 22, 193, 94, 106, 76, 208, 112, 101, 89,
+        223, 12, 219, 102, 133, 87, 135, 98, 116, 14, 154, 175, 213, 209, 9, 48, 127, 42, 164, 99,
+        89, 163, 148, 118, 4,
+    ];
+    assert_eq!(&signed.guarantee.signature.to_bytes(), signature);
+
+    // the archives are the same on every platform of the same byte order
+    #[cfg(feature = "archive_be")]
+    let archived = &[
+        142, 136, 144, 203, 118, 39, 28, 67, 89, 69, 217, 71, 8, 162, 237, 101, 27, 69, 211, 121,
+        114, 102, 16, 38, 211, 234, 160, 144, 131, 219, 22, 193, 94, 106, 76, 208, 112, 101, 89,
+        223, 12, 219, 102, 133, 87, 135, 98, 116, 14, 154, 175, 213, 209, 9, 48, 127, 42, 164, 99,
+        89, 163, 148, 118, 4, 178, 127, 84, 7, 76, 6, 240, 252, 66, 76, 107, 153, 78, 227, 199, 47,
+        255, 205, 198, 205, 169, 240, 131, 27, 107, 97, 3, 20, 99, 143, 106, 117, 85, 14, 132, 0,
+        226, 155, 65, 212, 167, 22, 68, 102, 85, 68, 0, 0, 0, 0, 0, 0, 24, 250, 181, 234, 16, 84,
+        232, 128, 0, 0, 0, 0, 178, 127, 84, 7, 76, 6, 240, 252, 66, 76, 107, 153, 78, 227, 199, 47,
+        255, 205, 198, 205, 169, 240, 131, 27, 107, 97, 3, 20, 99, 143, 106, 117, 87, 154, 110,
+        107, 52, 42, 17, 185, 192, 31, 255, 212, 14, 219, 36, 173, 59, 166, 60, 229, 15, 21, 176,
+        150, 97, 157, 68, 21, 194, 21, 9, 175, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 42, 0, 0, 0, 0,
+    ];
+    #[cfg(feature = "archive_le")]
+    let archived = &[
+        142, 136, 144, 203, 118, 39, 28, 67, 89, 69, 217, 71, 8, 162, 237, 101, 27, 69, 211, 121,
+        114, 102, 16, 38, 211, 234, 160, 144, 131, 219, 22, 193, 94, 106, 76, 208, 112, 101, 89,
+        223, 12, 219, 102, 133, 87, 135, 98, 116, 14, 154, 175, 213, 209, 9, 48, 127, 42, 164, 99,
+        89, 163, 148, 118, 4, 178, 127, 84, 7, 76, 6, 240, 252, 66, 76, 107, 153, 78, 227, 199, 47,
+        255, 205, 198, 205, 169, 240, 131, 27, 107, 97, 3, 20, 99, 143, 106, 117, 0, 0, 68, 85,
+        102, 68, 22, 167, 212, 65, 155, 226, 0, 132, 14, 85, 234, 181, 250, 24, 0, 0, 0, 0, 128,
+        232, 84, 16, 0, 0, 0, 0, 178, 127, 84, 7, 76, 6, 240, 252, 66, 76, 107, 153, 78, 227, 199,
+        47, 255, 205, 198, 205, 169, 240, 131, 27, 107, 97, 3, 20, 99, 143, 106, 117, 87, 154, 110,
+        107, 52, 42, 17, 185, 192, 31, 255, 212, 14, 219, 36, 173, 59, 166, 60, 229, 15, 21, 176,
+        150, 97, 157, 68, 21, 194, 21, 9, 175, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 42, 0, 0, 0, 0, 0, 0, 0,
     ];
-    assert_eq!(signed.as_slice(), bytes);
+    let bytes = ::rkyv::to_bytes::<_, 4096>(&signed).unwrap();
+    assert_eq!(bytes.as_slice(), archived);
 }